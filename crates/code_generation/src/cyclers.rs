@@ -1,4 +1,4 @@
-use std::{collections::BTreeSet, iter::once};
+use std::{collections::BTreeSet, iter::once, path::PathBuf, process::Command};
 
 use convert_case::{Case, Casing};
 use itertools::Itertools;
@@ -22,10 +22,9 @@ pub fn generate_cyclers(cyclers: &Cyclers, mode: CyclerMode) -> TokenStream {
         let recording_frame_variants = cyclers.instances().map(|(_cycler, instance)| {
             let instance_name = format_ident!("{}", instance);
             quote! {
+                /// Frames in chronological order, sent at once to not stall the cycler on large histories
                 #instance_name {
-                    timestamp: std::time::SystemTime,
-                    duration: std::time::Duration,
-                    data: std::vec::Vec<u8>,
+                    frames: std::vec::Vec<(framework::Timing, std::vec::Vec<u8>)>,
                 },
             }
         });
@@ -37,10 +36,14 @@ pub fn generate_cyclers(cyclers: &Cyclers, mode: CyclerMode) -> TokenStream {
     } else {
         Default::default()
     };
+    let commit_hash = match current_commit_hash() {
+        Some(commit_hash) => quote! { Some(#commit_hash.to_string()) },
+        None => quote! { None },
+    };
     let cyclers: Vec<_> = cyclers
         .cyclers
         .iter()
-        .map(|cycler| generate_module(cycler, cyclers, mode))
+        .map(|cycler| generate_module(cycler, cyclers, mode, &commit_hash))
         .collect();
 
     quote! {
@@ -50,10 +53,48 @@ pub fn generate_cyclers(cyclers: &Cyclers, mode: CyclerMode) -> TokenStream {
     }
 }
 
-fn generate_module(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -> TokenStream {
+fn current_commit_hash() -> Option<String> {
+    git(&["rev-parse", "HEAD"])
+}
+
+/// Files which change whenever the commit returned by `git rev-parse HEAD` changes, build scripts
+/// have to rerun on changes of these to keep the commit hash in recording headers up to date
+pub fn commit_hash_watch_paths() -> Vec<PathBuf> {
+    let mut references = vec!["HEAD".to_string(), "packed-refs".to_string()];
+    // a detached HEAD resolves to itself instead of a branch
+    references
+        .extend(git(&["rev-parse", "--symbolic-full-name", "HEAD"]).filter(|name| name != "HEAD"));
+    references
+        .iter()
+        .filter_map(|reference| git(&["rev-parse", "--git-path", reference]))
+        .map(PathBuf::from)
+        // cargo reruns build scripts on every build if a watched path does not exist
+        .filter(|path| path.exists())
+        .collect()
+}
+
+fn git(arguments: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(arguments).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn generate_module(
+    cycler: &Cycler,
+    cyclers: &Cyclers,
+    mode: CyclerMode,
+    commit_hash: &TokenStream,
+) -> TokenStream {
     let module_name = format_ident!("{}", cycler.name.to_case(Case::Snake));
     let cycler_instance = generate_cycler_instance(cycler);
     let database_struct = generate_database_struct();
+    let node_timing_structs = generate_node_timing_structs(cycler);
+    let node_failure_structs = generate_node_failure_structs(cycler);
+    let overrides_structs = generate_overrides_structs(cycler);
+    let recording_header_function =
+        generate_recording_header_function(cycler, cyclers, commit_hash);
     let cycler_struct = generate_struct(cycler, cyclers, mode);
     let cycler_implementation = generate_implementation(cycler, cyclers, mode);
 
@@ -65,6 +106,10 @@ fn generate_module(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -> Toke
 
             #cycler_instance
             #database_struct
+            #node_timing_structs
+            #node_failure_structs
+            #overrides_structs
+            #recording_header_function
             #cycler_struct
             #cycler_implementation
         }
@@ -97,10 +142,226 @@ fn generate_database_struct() -> TokenStream {
         pub struct Database {
             pub main_outputs: MainOutputs,
             pub additional_outputs: AdditionalOutputs,
+            pub timing: NodeTimings,
+            pub node_failures: NodeFailures,
+        }
+    }
+}
+
+fn generate_node_failure_structs(cycler: &Cycler) -> TokenStream {
+    let node_members: Vec<_> = cycler
+        .iter_nodes()
+        .map(|node| format_ident!("{}", node.name.to_case(Case::Snake)))
+        .collect();
+    quote! {
+        /// Number of failures of the nodes tolerated by their failure policy
+        #[derive(
+            Default,
+            Clone,
+            serde::Serialize,
+            serde::Deserialize,
+            path_serde::PathSerialize,
+            path_serde::PathIntrospect,
+        )]
+        pub struct NodeFailures {
+            #(pub #node_members: usize,)*
+        }
+
+        #[derive(Default)]
+        struct NodeFailureHandlers {
+            #(#node_members: framework::NodeFailureHandler,)*
+        }
+    }
+}
+
+fn generate_overrides_structs(cycler: &Cycler) -> TokenStream {
+    let node_members = cycler
+        .iter_nodes()
+        .map(|node| format_ident!("{}", node.name.to_case(Case::Snake)));
+    let injected_main_outputs = cycler.iter_nodes().flat_map(|node| {
+        node.contexts
+            .main_outputs
+            .iter()
+            .filter_map(|field| match field {
+                Field::MainOutput { data_type, name } => Some(quote! {
+                    #[path_serde(leaf)]
+                    pub #name: Option<#data_type>,
+                }),
+                _ => None,
+            })
+    });
+    quote! {
+        /// Runtime overrides of the node execution, written through the communication server
+        #[derive(
+            Default,
+            Clone,
+            serde::Serialize,
+            serde::Deserialize,
+            path_serde::PathSerialize,
+            path_serde::PathDeserialize,
+            path_serde::PathIntrospect,
+        )]
+        pub struct Overrides {
+            pub disabled_nodes: DisabledNodes,
+            pub injected_main_outputs: InjectedMainOutputs,
+        }
+
+        /// Disabled nodes are not executed and write default main outputs
+        #[derive(
+            Default,
+            Clone,
+            serde::Serialize,
+            serde::Deserialize,
+            path_serde::PathSerialize,
+            path_serde::PathDeserialize,
+            path_serde::PathIntrospect,
+        )]
+        pub struct DisabledNodes {
+            #(pub #node_members: bool,)*
+        }
+
+        /// Injected values replace the main outputs written by their node
+        #[derive(
+            Default,
+            Clone,
+            serde::Serialize,
+            serde::Deserialize,
+            path_serde::PathSerialize,
+            path_serde::PathDeserialize,
+            path_serde::PathIntrospect,
+        )]
+        pub struct InjectedMainOutputs {
+            #(#injected_main_outputs)*
         }
     }
 }
 
+fn generate_node_timing_structs(cycler: &Cycler) -> TokenStream {
+    let node_members: Vec<_> = cycler
+        .iter_nodes()
+        .map(|node| format_ident!("{}", node.name.to_case(Case::Snake)))
+        .collect();
+    quote! {
+        /// Statistics over the execution durations of the nodes, filled if subscribed
+        #[derive(
+            Default,
+            Clone,
+            serde::Serialize,
+            serde::Deserialize,
+            path_serde::PathSerialize,
+            path_serde::PathIntrospect,
+        )]
+        pub struct NodeTimings {
+            #(pub #node_members: framework::TimingStatistics,)*
+        }
+
+        #[derive(Default)]
+        struct NodeDurations {
+            #(#node_members: framework::DurationStatistics,)*
+        }
+    }
+}
+
+fn generate_recording_header_function(
+    cycler: &Cycler,
+    cyclers: &Cyclers,
+    commit_hash: &TokenStream,
+) -> TokenStream {
+    let cycler_instances = cyclers.instances().map(|(_cycler, instance)| instance);
+    let recording_layout = generate_recording_layout(cycler);
+
+    quote! {
+        pub(crate) fn recording_header(cycler_instance: &str, has_main_outputs: bool) -> framework::RecordingHeader {
+            // the field paths of the main outputs cover changes within the recorded types
+            let mut main_output_fields: Vec<_> =
+                <MainOutputs as path_serde::PathIntrospect>::get_fields().into_iter().collect();
+            main_output_fields.sort();
+            framework::RecordingHeader {
+                format_version: framework::RECORDING_FORMAT_VERSION,
+                commit_hash: #commit_hash,
+                cycler_instance: cycler_instance.to_string(),
+                cycler_instances: vec![#(#cycler_instances.to_string(),)*],
+                schema_hash: framework::schema_hash(
+                    [#(#recording_layout,)*]
+                        .into_iter()
+                        .chain(main_output_fields.iter().map(String::as_str)),
+                ),
+                has_main_outputs,
+                is_compressed: false,
+            }
+        }
+    }
+}
+
+/// Describes the nodes, their state fields and the names and types of everything a cycler records
+fn generate_recording_layout(cycler: &Cycler) -> Vec<String> {
+    let nodes = cycler.iter_nodes().flat_map(|node| {
+        once(format!("node {}", node.name)).chain(node.state_fields.iter().enumerate().map(
+            move |(index, field)| {
+                let name = field
+                    .ident
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| index.to_string());
+                let data_type = &field.ty;
+                format!("node {} state {name}: {}", node.name, quote! { #data_type })
+            },
+        ))
+    });
+    let main_outputs = cycler.iter_nodes().flat_map(|node| {
+        node.contexts
+            .main_outputs
+            .iter()
+            .filter_map(|field| match field {
+                Field::MainOutput { data_type, name } => {
+                    Some(format!("main output {name}: {}", quote! { #data_type }))
+                }
+                _ => None,
+            })
+    });
+    let cross_inputs = get_cross_input_fields(cycler)
+        .into_iter()
+        .map(|field| match field {
+            Field::CyclerState {
+                data_type, name, ..
+            }
+            | Field::HistoricInput {
+                data_type, name, ..
+            }
+            | Field::Input {
+                data_type, name, ..
+            }
+            | Field::PerceptionInput {
+                data_type, name, ..
+            }
+            | Field::RequiredInput {
+                data_type, name, ..
+            } => format!("cross input {name}: {}", quote! { #data_type }),
+            _ => panic!("unexpected field {field:?}"),
+        });
+    let additional_outputs = cycler.iter_nodes().flat_map(|node| {
+        node.contexts
+            .cycle_context
+            .iter()
+            .filter_map(|field| match field {
+                Field::AdditionalOutput {
+                    data_type, path, ..
+                } => Some(format!(
+                    "additional output {}: {}",
+                    additional_output_path(path),
+                    quote! { #data_type }
+                )),
+                _ => None,
+            })
+    });
+    once("overrides".to_string())
+        .chain(nodes)
+        .chain(main_outputs)
+        .chain(cross_inputs)
+        .chain(additional_outputs)
+        .collect()
+}
+
 fn generate_struct(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -> TokenStream {
     let module_name = format_ident!("{}", cycler.name.to_case(Case::Snake));
     let input_output_fields = generate_input_output_fields(cycler, cyclers);
@@ -116,11 +377,17 @@ fn generate_struct(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -> Toke
     let node_fields = generate_node_fields(cycler);
     let recording_fields = if mode == CyclerMode::Run {
         quote! {
+            overrides_receiver: buffered_watch::Receiver<(std::time::SystemTime, Overrides)>,
             recording_sender: std::sync::mpsc::SyncSender<crate::cyclers::RecordingFrame>,
             recording_trigger: framework::RecordingTrigger,
+            recorded_additional_outputs: Vec<String>,
+            verify_main_outputs: bool,
         }
     } else {
-        Default::default()
+        quote! {
+            has_recorded_main_outputs: bool,
+            recorded_main_outputs: MainOutputs,
+        }
     };
 
     quote! {
@@ -134,6 +401,9 @@ fn generate_struct(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -> Toke
             #realtime_inputs
             #input_output_fields
             #node_fields
+            node_durations: NodeDurations,
+            node_failure_handlers: NodeFailureHandlers,
+            last_main_outputs: MainOutputs,
             #recording_fields
         }
     }
@@ -208,6 +478,10 @@ fn generate_implementation(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode)
         CyclerMode::Run => generate_start_method(cycler.kind),
         CyclerMode::Replay => Default::default(),
     };
+    let recording_methods = match mode {
+        CyclerMode::Run => generate_recording_methods(cycler),
+        CyclerMode::Replay => generate_replay_methods(cycler),
+    };
     let cycle_method = generate_cycle_method(cycler, cyclers, mode);
 
     quote! {
@@ -217,6 +491,7 @@ fn generate_implementation(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode)
         {
             #new_method
             #start_method
+            #recording_methods
             #cycle_method
         }
     }
@@ -232,20 +507,56 @@ fn generate_new_method(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) ->
     let input_output_identifiers = generate_input_output_identifiers(cycler, cyclers);
     let recording_parameter_fields = if mode == CyclerMode::Run {
         quote! {
+            overrides_receiver: buffered_watch::Receiver<(std::time::SystemTime, Overrides)>,
             recording_sender: std::sync::mpsc::SyncSender<crate::cyclers::RecordingFrame>,
             recording_trigger: framework::RecordingTrigger,
+            recorded_additional_outputs: Vec<String>,
+            verify_main_outputs: bool,
         }
     } else {
-        Default::default()
+        quote! {
+            has_recorded_main_outputs: bool,
+        }
     };
     let recording_initializer_fields = if mode == CyclerMode::Run {
         quote! {
+            overrides_receiver,
             recording_sender,
             recording_trigger,
+            recorded_additional_outputs,
+            verify_main_outputs,
+        }
+    } else {
+        quote! {
+            has_recorded_main_outputs,
+            recorded_main_outputs: Default::default(),
+        }
+    };
+    let node_failure_policies_parameter = if mode == CyclerMode::Run {
+        quote! {
+            node_failure_policies: &std::collections::HashMap<String, framework::NodeFailurePolicy>,
         }
     } else {
         Default::default()
     };
+    let node_failure_handlers_initializer = if mode == CyclerMode::Run {
+        let node_handlers = cycler.iter_nodes().map(|node| {
+            let node_member = format_ident!("{}", node.name.to_case(Case::Snake));
+            let node_name = &node.name;
+            quote! {
+                #node_member: framework::NodeFailureHandler::new(
+                    node_failure_policies.get(#node_name).copied().unwrap_or_default()
+                ),
+            }
+        });
+        quote! {
+            NodeFailureHandlers {
+                #(#node_handlers)*
+            }
+        }
+    } else {
+        quote! { Default::default() }
+    };
 
     quote! {
         pub(crate) fn new(
@@ -256,6 +567,7 @@ fn generate_new_method(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) ->
             mut parameters_receiver: buffered_watch::Receiver<(std::time::SystemTime, crate::structs::Parameters)>,
             #input_output_fields
             #recording_parameter_fields
+            #node_failure_policies_parameter
         ) -> color_eyre::Result<Self> {
             let parameters_guard = parameters_receiver.borrow_and_mark_as_seen();
             let (_, parameters) = &* parameters_guard;
@@ -271,6 +583,9 @@ fn generate_new_method(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) ->
                 cycler_state,
                 #input_output_identifiers
                 #(#node_identifiers,)*
+                node_durations: Default::default(),
+                node_failure_handlers: #node_failure_handlers_initializer,
+                last_main_outputs: Default::default(),
                 #recording_initializer_fields
             })
         }
@@ -384,6 +699,103 @@ fn generate_consumer_identifiers(cyclers: &Cyclers) -> Vec<Ident> {
         .collect()
 }
 
+fn generate_recording_methods(cycler: &Cycler) -> TokenStream {
+    let recording_variants = cycler.instances.iter().map(|instance| {
+        let instance_name = format_ident!("{}", instance);
+        quote! {
+            CyclerInstance::#instance_name => crate::cyclers::RecordingFrame::#instance_name { frames },
+        }
+    });
+
+    quote! {
+        fn recording_frame(
+            instance: CyclerInstance,
+            frames: Vec<(framework::Timing, Vec<u8>)>,
+        ) -> crate::cyclers::RecordingFrame {
+            match instance {
+                #(#recording_variants)*
+            }
+        }
+
+        /// Takes the sender and trigger instead of `self` to be callable while the database is borrowed
+        fn send_recording_frames(
+            recording_sender: &std::sync::mpsc::SyncSender<crate::cyclers::RecordingFrame>,
+            recording_trigger: &mut framework::RecordingTrigger,
+            instance: CyclerInstance,
+            frames: Vec<(framework::Timing, Vec<u8>)>,
+        ) -> color_eyre::Result<()> {
+            if frames.is_empty() {
+                return Ok(());
+            }
+            let number_of_frames = frames.len();
+            match recording_sender.try_send(Self::recording_frame(instance, frames)) {
+                Ok(()) => Ok(()),
+                // waiting for the recording thread would stall the cycler, losing frames is preferred
+                Err(std::sync::mpsc::TrySendError::Full(_)) => {
+                    recording_trigger.record_dropped_frames(&format!("{instance:?}"), number_of_frames);
+                    Ok(())
+                }
+                Err(std::sync::mpsc::TrySendError::Disconnected(_)) => {
+                    color_eyre::eyre::bail!("failed to send recording frames, the recording thread exited")
+                }
+            }
+        }
+    }
+}
+
+fn generate_replay_methods(cycler: &Cycler) -> TokenStream {
+    let comparisons = cycler.cycle_nodes.iter().flat_map(|node| {
+        let node_name = &node.name;
+        node.contexts
+            .main_outputs
+            .iter()
+            .filter_map(move |field| match field {
+                Field::MainOutput { name, .. } => {
+                    let name_string = name.to_string();
+                    Some(quote! {
+                        if let Some(divergence) = framework::find_divergence(
+                            #node_name,
+                            #name_string,
+                            &self.recorded_main_outputs.#name,
+                            &own_database.main_outputs.#name,
+                            tolerances.get(&instance_name, #name_string),
+                        )? {
+                            divergences.push(divergence);
+                        }
+                    })
+                }
+                _ => None,
+            })
+    });
+
+    quote! {
+        /// Compares the main outputs of the last replayed cycle with the recorded ones
+        pub fn find_main_output_divergences(
+            &mut self,
+            tolerances: &framework::Tolerances,
+        ) -> color_eyre::Result<Vec<framework::Divergence>> {
+            let instance_name = format!("{:?}", self.instance);
+            if !self.has_recorded_main_outputs {
+                color_eyre::eyre::bail!("recording of `{instance_name}` contains no main outputs, set `verify_main_outputs` to record them");
+            }
+            let (_, own_database) = &*self.own_sender.borrow();
+            let mut divergences = Vec::new();
+            #(#comparisons)*
+            Ok(divergences)
+        }
+
+        /// Serializes the data at the path within the database of the last replayed cycle
+        pub fn serialize_database_path(&mut self, path: &str) -> color_eyre::Result<serde_json::Value> {
+            let (_, own_database) = &*self.own_sender.borrow();
+            if path.is_empty() {
+                return serde_json::to_value(own_database).wrap_err("failed to serialize database");
+            }
+            path_serde::PathSerialize::serialize_path(own_database, path, serde_json::value::Serializer)
+                .wrap_err_with(|| format!("failed to serialize `{path}`"))
+        }
+    }
+}
+
 fn generate_start_method(cycler_kind: CyclerKind) -> TokenStream {
     let scheduler_tokens = match cycler_kind {
         CyclerKind::Perception => TokenStream::new(),
@@ -418,6 +830,16 @@ fn generate_start_method(cycler_kind: CyclerKind) -> TokenStream {
                     #scheduler_tokens
                     while !keep_running.is_cancelled() {
                         if let Err(error) = self.cycle() {
+                            let recording_history = self.recording_trigger.record_node_error();
+                            // the cycler terminates anyway, therefore wait for the recording thread
+                            if !recording_history.is_empty() {
+                                if let Err(error) = self
+                                    .recording_sender
+                                    .send(Self::recording_frame(self.instance, recording_history))
+                                {
+                                    log::error!("failed to send recording history: {error}");
+                                }
+                            }
                             keep_running.cancel();
                             return Err(error).wrap_err_with(|| {
                                 format!("failed to execute cycle of cycler `{:?}`", self.instance)
@@ -450,6 +872,14 @@ fn generate_cycle_method(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -
         .cycle_nodes
         .iter()
         .map(|node| generate_node_execution(node, cycler, NodeType::Cycle, mode));
+    let fill_node_timings = generate_fill_node_timings(cycler);
+    let fill_node_failures = cycler.iter_nodes().map(|node| {
+        let node_member = format_ident!("{}", node.name.to_case(Case::Snake));
+        quote! {
+            own_database.node_failures.#node_member = self.node_failure_handlers.#node_member.number_of_failures();
+        }
+    });
+    let fill_node_failures = quote! { #(#fill_node_failures)* };
     let cross_input_fields = get_cross_input_fields(cycler);
     let cross_inputs = match mode {
         CyclerMode::Run => generate_cross_inputs_recording(cycler, cross_input_fields),
@@ -458,11 +888,18 @@ fn generate_cycle_method(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -
 
     let pre_setup = match mode {
         CyclerMode::Run => quote! {
-            let enable_recording = self.recording_trigger.should_record() && self.hardware_interface.should_record();
+            let is_recorded_by_interval = self.recording_trigger.should_record() && self.hardware_interface.should_record();
+            let enable_recording = is_recorded_by_interval || self.recording_trigger.has_event_triggers();
             self.recording_trigger.update();
             let mut recording_frame = Vec::new(); // TODO: possible optimization: cache capacity
+            let overrides = self.overrides_receiver.borrow_and_mark_as_seen().1.clone();
+            if enable_recording {
+                bincode::serialize_into(&mut recording_frame, &overrides).wrap_err("failed to record overrides")?;
+            }
+        },
+        CyclerMode::Replay => quote! {
+            let overrides: Overrides = bincode::deserialize_from(&mut recording_frame).wrap_err("failed to extract overrides")?;
         },
-        CyclerMode::Replay => Default::default(),
     };
     let post_setup = match mode {
         CyclerMode::Run => quote! {
@@ -517,39 +954,38 @@ fn generate_cycle_method(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -
         },
     };
     let after_remaining_nodes = match mode {
-        CyclerMode::Run => {
-            let recording_variants = cycler.instances.iter().map(|instance| {
-                let instance_name = format_ident!("{}", instance);
-                quote! {
-                    CyclerInstance::#instance_name => crate::cyclers::RecordingFrame::#instance_name {
+        CyclerMode::Run => quote! {
+            #after_remaining_nodes
+            let recording_duration = recording_timestamp.elapsed().expect("time ran backwards");
+
+            const EXECUTION_TIME_UPPER_BOUND: f32 = 0.4;
+            if recording_duration.as_secs_f32() > EXECUTION_TIME_UPPER_BOUND {
+                log::warn!("Cycle took {}s!", recording_duration.as_secs_f32());
+                self
+                    .hardware_interface
+                    .write_to_speakers(types::audio::SpeakerRequest::PlaySound {
+                        sound: types::audio::Sound::Donk,
+                    });
+            }
+
+            if enable_recording {
+                let recording_frames = self.recording_trigger.record_frame(
+                    framework::Timing {
                         timestamp: recording_timestamp,
                         duration: recording_duration,
-                        data: recording_frame,
                     },
-                }
-            });
-
-            quote! {
-                #after_remaining_nodes
-                let recording_duration = recording_timestamp.elapsed().expect("time ran backwards");
-
-                const EXECUTION_TIME_UPPER_BOUND: f32 = 0.4;
-                if recording_duration.as_secs_f32() > EXECUTION_TIME_UPPER_BOUND {
-                    log::warn!("Cycle took {}s!", recording_duration.as_secs_f32());
-                    self
-                        .hardware_interface
-                        .write_to_speakers(types::audio::SpeakerRequest::PlaySound {
-                            sound: types::audio::Sound::Donk,
-                        });
-                }
-
-                if enable_recording {
-                    self.recording_sender.try_send(match instance {
-                        #(#recording_variants)*
-                    }).wrap_err("failed to send recording frame")?;
-                }
+                    recording_frame,
+                    is_recorded_by_interval,
+                    &*own_database,
+                );
+                Self::send_recording_frames(
+                    &self.recording_sender,
+                    &mut self.recording_trigger,
+                    instance,
+                    recording_frames,
+                )?;
             }
-        }
+        },
         CyclerMode::Replay => after_remaining_nodes,
     };
 
@@ -581,6 +1017,8 @@ fn generate_cycle_method(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -
                 #borrow_receivers
                 #cross_inputs
                 #(#cycle_node_executions)*
+                #fill_node_timings
+                #fill_node_failures
             }
 
             #after_remaining_nodes
@@ -589,6 +1027,24 @@ fn generate_cycle_method(cycler: &Cycler, cyclers: &Cyclers, mode: CyclerMode) -
     }
 }
 
+fn generate_fill_node_timings(cycler: &Cycler) -> TokenStream {
+    cycler
+        .iter_nodes()
+        .map(|node| {
+            let node_member = format_ident!("{}", node.name.to_case(Case::Snake));
+            let path_string = format!("timing.{node_member}");
+            quote! {
+                if own_subscribed_outputs
+                    .iter()
+                    .any(|subscribed_output| framework::should_be_filled(subscribed_output, #path_string))
+                {
+                    own_database.timing.#node_member = self.node_durations.#node_member.statistics();
+                }
+            }
+        })
+        .collect()
+}
+
 fn get_cross_input_fields(cycler: &Cycler) -> BTreeSet<Field> {
     cycler
         .setup_nodes
@@ -853,39 +1309,56 @@ fn generate_node_execution(
             let execute_node_and_write_main_outputs =
                 generate_execute_node_and_write_main_outputs(node, cycler, mode);
             let record_main_outputs = generate_record_main_outputs(node);
+            let record_additional_outputs = generate_record_additional_outputs(node, cycler);
             quote! {
-                #execute_node_and_write_main_outputs
+                let node_has_run = #execute_node_and_write_main_outputs;
                 #record_main_outputs
+                #record_additional_outputs
             }
         }
         (NodeType::Cycle, CyclerMode::Run) => {
             let record_node_state = generate_record_node_state(node);
             let execute_node_and_write_main_outputs =
                 generate_execute_node_and_write_main_outputs(node, cycler, mode);
+            let record_main_outputs = generate_record_main_outputs(node);
+            let record_additional_outputs = generate_record_additional_outputs(node, cycler);
             quote! {
                 #record_node_state
-                #execute_node_and_write_main_outputs
+                let node_has_run = #execute_node_and_write_main_outputs;
+                if self.verify_main_outputs {
+                    #record_main_outputs
+                }
+                #record_additional_outputs
             }
         }
         (NodeType::Setup, CyclerMode::Replay) => {
             let deserialize_frame_and_write_main_outputs =
                 generate_deserialize_frame_and_write_main_outputs(node);
+            let restore_additional_outputs = generate_restore_additional_outputs(node, cycler);
             quote! {
                 #deserialize_frame_and_write_main_outputs
+                #restore_additional_outputs
             }
         }
         (NodeType::Cycle, CyclerMode::Replay) => {
             let restore_node_state = generate_restore_node_state(node);
             let execute_node_and_write_main_outputs =
                 generate_execute_node_and_write_main_outputs(node, cycler, mode);
+            let extract_recorded_main_outputs = generate_extract_recorded_main_outputs(node);
+            let restore_additional_outputs = generate_restore_additional_outputs(node, cycler);
             quote! {
                 #restore_node_state
-                #execute_node_and_write_main_outputs
+                #execute_node_and_write_main_outputs;
+                if self.has_recorded_main_outputs {
+                    #extract_recorded_main_outputs
+                }
+                #restore_additional_outputs
             }
         }
     }
 }
 
+/// Evaluates to whether the node was executed in this cycle instead of being skipped
 fn generate_execute_node_and_write_main_outputs(
     node: &Node,
     cycler: &Cycler,
@@ -899,29 +1372,102 @@ fn generate_execute_node_and_write_main_outputs(
     let cycle_error_message = format!("failed to execute cycle of `{}`", node.name);
     let write_main_outputs = generate_write_main_outputs(node);
     let write_main_outputs_from_defaults = generate_write_main_outputs_from_defaults(node);
+    let (store_last_main_outputs, write_last_main_outputs) = generate_last_main_outputs(node);
+    let inject_main_outputs = generate_inject_main_outputs(node);
+    let record_node_error = match mode {
+        CyclerMode::Run => quote! {
+            let recording_history = self.recording_trigger.record_node_error();
+            Self::send_recording_frames(
+                &self.recording_sender,
+                &mut self.recording_trigger,
+                instance,
+                recording_history,
+            )?;
+        },
+        CyclerMode::Replay => quote! {},
+    };
 
     quote! {
         {
+            let node_has_run = !overrides.disabled_nodes.#node_member && #are_required_inputs_some;
             #[allow(clippy::needless_else)]
-            if #are_required_inputs_some {
-                let main_outputs = {
+            if node_has_run {
+                let cycle_result = {
                     let _task = ittapi::Task::begin(&itt_domain, #node_name);
-                    self.#node_member.cycle(
+                    let node_start = std::time::Instant::now();
+                    let cycle_result = self.#node_member.cycle(
                         #node_module::CycleContext::new(
                             #context_initializers
                         ),
                     )
-                    .wrap_err(#cycle_error_message)?
+                    .wrap_err(#cycle_error_message);
+                    self.node_durations.#node_member.update(node_start.elapsed());
+                    cycle_result
                 };
-                #write_main_outputs
+                match cycle_result {
+                    Ok(main_outputs) => {
+                        #write_main_outputs
+                        if self.node_failure_handlers.#node_member.policy() == framework::NodeFailurePolicy::ReuseLastMainOutputs {
+                            #store_last_main_outputs
+                        }
+                    }
+                    Err(error) => {
+                        let policy = self.node_failure_handlers.#node_member.handle(#node_name, error)?;
+                        #record_node_error
+                        match policy {
+                            framework::NodeFailurePolicy::ReuseLastMainOutputs => {
+                                #write_last_main_outputs
+                            }
+                            _ => {
+                                #write_main_outputs_from_defaults
+                            }
+                        }
+                    }
+                }
             }
             else {
                 #write_main_outputs_from_defaults
             }
+            #inject_main_outputs
+            node_has_run
         }
     }
 }
 
+fn generate_inject_main_outputs(node: &Node) -> TokenStream {
+    node.contexts
+        .main_outputs
+        .iter()
+        .filter_map(|field| match field {
+            Field::MainOutput { name, .. } => Some(quote! {
+                if let Some(injected_output) = &overrides.injected_main_outputs.#name {
+                    own_database.main_outputs.#name = injected_output.clone();
+                }
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Copies the main outputs of a node from and to the last successful cycle
+fn generate_last_main_outputs(node: &Node) -> (TokenStream, TokenStream) {
+    node.contexts
+        .main_outputs
+        .iter()
+        .filter_map(|field| match field {
+            Field::MainOutput { name, .. } => Some((
+                quote! {
+                    self.last_main_outputs.#name = own_database.main_outputs.#name.clone();
+                },
+                quote! {
+                    own_database.main_outputs.#name = self.last_main_outputs.#name.clone();
+                },
+            )),
+            _ => None,
+        })
+        .unzip()
+}
+
 fn generate_record_main_outputs(node: &Node) -> TokenStream {
     node.contexts
         .main_outputs
@@ -950,6 +1496,73 @@ fn generate_record_node_state(node: &Node) -> TokenStream {
     }
 }
 
+fn generate_record_additional_outputs(node: &Node, cycler: &Cycler) -> TokenStream {
+    node.contexts
+        .cycle_context
+        .iter()
+        .filter_map(|field| match field {
+            Field::AdditionalOutput { path, .. } => {
+                let accessor = path_to_accessor_token_stream(
+                    quote! { own_database.additional_outputs },
+                    path,
+                    ReferenceKind::Immutable,
+                    cycler,
+                );
+                let path_string = additional_output_path(path);
+                let error_message = format!("failed to record {path_string}");
+                // unrecorded additional outputs and those of nodes which did not run take a single
+                // byte to keep the frame layout fixed
+                Some(quote! {
+                    if enable_recording {
+                        let is_recorded = node_has_run && self
+                            .recorded_additional_outputs
+                            .iter()
+                            .any(|recorded_output| framework::should_be_filled(recorded_output, #path_string));
+                        let additional_output = if is_recorded { #accessor } else { &None };
+                        bincode::serialize_into(&mut recording_frame, additional_output).wrap_err(#error_message)?;
+                    }
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn generate_restore_additional_outputs(node: &Node, cycler: &Cycler) -> TokenStream {
+    node.contexts
+        .cycle_context
+        .iter()
+        .filter_map(|field| match field {
+            Field::AdditionalOutput { path, .. } => {
+                let accessor = path_to_accessor_token_stream(
+                    quote! { own_database.additional_outputs },
+                    path,
+                    ReferenceKind::Mutable,
+                    cycler,
+                );
+                let error_message = format!("failed to extract {}", additional_output_path(path));
+                // recorded additional outputs show what happened on the robot, even if recomputed
+                Some(quote! {
+                    {
+                        let recorded_output: Option<_> = bincode::deserialize_from(&mut recording_frame).wrap_err(#error_message)?;
+                        if recorded_output.is_some() {
+                            let additional_output = #accessor;
+                            *additional_output = recorded_output;
+                        }
+                    }
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn additional_output_path(path: &Path) -> String {
+    once("additional_outputs")
+        .chain(path.segments.iter().map(|segment| segment.name.as_str()))
+        .join(".")
+}
+
 fn generate_deserialize_frame_and_write_main_outputs(node: &Node) -> TokenStream {
     node.contexts
         .main_outputs
@@ -966,6 +1579,23 @@ fn generate_deserialize_frame_and_write_main_outputs(node: &Node) -> TokenStream
         .collect()
 }
 
+/// Keeps the recorded main outputs of cycle nodes to verify the recomputed ones against them
+fn generate_extract_recorded_main_outputs(node: &Node) -> TokenStream {
+    node.contexts
+        .main_outputs
+        .iter()
+        .filter_map(|field| match field {
+            Field::MainOutput { name, .. } => {
+                let error_message = format!("failed to extract {name}");
+                Some(quote! {
+                    self.recorded_main_outputs.#name = bincode::deserialize_from(&mut recording_frame).wrap_err(#error_message)?;
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 fn generate_restore_node_state(node: &Node) -> TokenStream {
     let node_member = format_ident!("{}", node.name.to_case(Case::Snake));
     let error_message = format!("failed to extract `{}`", node.name);
@@ -1072,14 +1702,22 @@ fn generate_context_initializers(node: &Node, cycler: &Cycler, mode: CyclerMode)
                         ReferenceKind::Mutable,
                         cycler,
                     );
-                    let path_string = once("additional_outputs").chain(
-                            path.segments.iter().map(|segment| segment.name.as_str())
-                        ).join(".");
+                    let path_string = additional_output_path(path);
+                    let is_recorded = match mode {
+                        CyclerMode::Run => quote! {
+                            || (enable_recording && self
+                                .recorded_additional_outputs
+                                .iter()
+                                .any(|recorded_output| framework::should_be_filled(recorded_output, #path_string)))
+                        },
+                        CyclerMode::Replay => Default::default(),
+                    };
                     quote! {
                         framework::AdditionalOutput::new(
                             own_subscribed_outputs
                                 .iter()
-                                .any(|subscribed_output| framework::should_be_filled(subscribed_output, #path_string)),
+                                .any(|subscribed_output| framework::should_be_filled(subscribed_output, #path_string))
+                                #is_recorded,
                             #accessor,
                         )
                     }
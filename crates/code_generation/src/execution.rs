@@ -11,6 +11,7 @@ pub fn generate_run_function(cyclers: &Cyclers) -> TokenStream {
     let recording_thread = generate_recording_thread(cyclers);
     let construct_cyclers = generate_cycler_constructors(cyclers, CyclerMode::Run);
     let communication_registrations = generate_communication_registrations(cyclers);
+    let overrides_registrations = generate_overrides_registrations(cyclers);
     let start_cyclers = generate_cycler_starts(cyclers);
     let join_cyclers = generate_cycler_joins(cyclers);
 
@@ -19,16 +20,27 @@ pub fn generate_run_function(cyclers: &Cyclers) -> TokenStream {
         #[allow(clippy::too_many_arguments)]
         pub fn run(
             hardware_interface: std::sync::Arc<impl crate::HardwareInterface + Send + Sync + 'static>,
-            addresses: Option<impl tokio::net::ToSocketAddrs + std::marker::Send + std::marker::Sync + 'static>,
-            parameters_directory: impl std::convert::AsRef<std::path::Path> + std::marker::Send + std::marker::Sync + 'static,
+            framework_parameters: framework::Parameters,
             log_path: impl std::convert::AsRef<std::path::Path> + std::marker::Send + std::marker::Sync + 'static,
             hardware_ids: types::hardware::Ids,
             keep_running: tokio_util::sync::CancellationToken,
-            recording_intervals: std::collections::HashMap<String, usize>,
         ) -> color_eyre::Result<()>
         {
             use color_eyre::eyre::WrapErr;
 
+            let framework::Parameters {
+                communication_addresses: addresses,
+                communication_access_control,
+                recording_intervals,
+                recording_storage,
+                recording_triggers,
+                recorded_additional_outputs,
+                node_failure_policies,
+                verify_main_outputs,
+                parameters_directory,
+                ..
+            } = framework_parameters;
+
             {
                 let keep_running = keep_running.clone();
                 std::panic::set_hook(Box::new(move |panic_info| {
@@ -68,7 +80,11 @@ pub fn generate_run_function(cyclers: &Cyclers) -> TokenStream {
                             .wrap_err("failed to create async runtime")?;
                         async_runtime.block_on(async move {
                             let mut communication_server = communication::server::Server::default();
+                            communication_server.set_access_control(
+                                crate::access_control::communication_access_control(communication_access_control),
+                            );
                             #communication_registrations
+                            #overrides_registrations
                             let (parameters_subscriptions, _) = buffered_watch::channel(Default::default());
                             communication_server.expose_source("parameters", parameters_receiver, parameters_subscriptions)?;
                             communication_server.expose_sink("parameters", parameters_sender)?;
@@ -141,10 +157,18 @@ pub fn generate_replayer_struct(cyclers: &Cyclers, with_communication: bool) ->
             },
             {
                 let communication_registrations = generate_communication_registrations(cyclers);
+                // the replayer keeps the receivers, the communication thread gets clones of them
+                let receiver_clones = cyclers.instances().map(|(_cycler, instance)| {
+                    let receiver = format_ident!("{}_receiver", instance.to_case(Case::Snake));
+                    quote! {
+                        let #receiver = #receiver.clone();
+                    }
+                });
                 quote! {
                     let _communication_thread = addresses.map(|addresses| {
                         let keep_running = keep_running.clone();
                         let parameters_receiver = parameters_receiver.clone();
+                        #(#receiver_clones)*
                         std::thread::Builder::new()
                             .name("Communication".to_string())
                             .spawn(move || -> color_eyre::Result<()> {
@@ -174,6 +198,9 @@ pub fn generate_replayer_struct(cyclers: &Cyclers, with_communication: bool) ->
     let recording_index_entries_mut =
         generate_recording_index_entries(cyclers, ReferenceKind::Mutable);
     let cycler_replays = generate_cycler_replays(cyclers);
+    let cycler_verifications = generate_cycler_verifications(cyclers);
+    let cycler_database_serializations = generate_cycler_database_serializations(cyclers);
+    let cycler_main_output_fields = generate_cycler_main_output_fields(cyclers);
 
     quote! {
         pub struct Replayer<Hardware> {
@@ -238,6 +265,44 @@ pub fn generate_replayer_struct(cyclers: &Cyclers, with_communication: bool) ->
                 }
             }
 
+            pub fn find_main_output_divergences(
+                &mut self,
+                cycler_instance_name: &str,
+                tolerances: &framework::Tolerances,
+            ) -> color_eyre::Result<Vec<framework::Divergence>> {
+                use color_eyre::eyre::{bail, WrapErr};
+
+                match cycler_instance_name {
+                    #cycler_verifications
+                    _ => bail!("unexpected cycler instance name {cycler_instance_name}"),
+                }
+            }
+
+            pub fn serialize_database_path(
+                &mut self,
+                cycler_instance_name: &str,
+                path: &str,
+            ) -> color_eyre::Result<serde_json::Value> {
+                use color_eyre::eyre::bail;
+
+                match cycler_instance_name {
+                    #cycler_database_serializations
+                    _ => bail!("unexpected cycler instance name {cycler_instance_name}"),
+                }
+            }
+
+            /// Field paths of the main outputs of the cycler instance, see `PathIntrospect`
+            pub fn get_main_output_fields(
+                cycler_instance_name: &str,
+            ) -> color_eyre::Result<std::collections::HashSet<String>> {
+                use color_eyre::eyre::bail;
+
+                match cycler_instance_name {
+                    #cycler_main_output_fields
+                    _ => bail!("unexpected cycler instance name {cycler_instance_name}"),
+                }
+            }
+
             pub fn replay_at(&mut self, timestamp: std::time::SystemTime) -> color_eyre::Result<()> {
                 use color_eyre::eyre::WrapErr;
 
@@ -278,57 +343,61 @@ fn generate_replayer_token_streams(
     cyclers: &Cyclers,
     with_communication: bool,
 ) -> ReplayerTokenStreams {
-    if with_communication {
-        ReplayerTokenStreams {
-            fields: quote! {
+    let receiver_tokens: Vec<_> = cyclers
+        .instances()
+        .map(|(cycler, instance)| {
+            (
+                format_ident!("{}_receiver", instance.to_case(Case::Snake)),
+                format_ident!("{}", cycler.name.to_case(Case::Snake)),
+            )
+        })
+        .collect();
+
+    let receiver_identifiers = receiver_tokens.iter().map(|(receiver, _cycler)| receiver);
+    let receiver_fields = receiver_tokens.iter().map(|(receiver, cycler)| {
+        quote! {
+            #receiver: buffered_watch::Receiver<(std::time::SystemTime, crate::cyclers::#cycler::Database)>,
+        }
+    });
+    let receiver_accessors = receiver_tokens.iter().map(|(receiver, cycler)| {
+        quote! {
+            #[allow(unused)]
+            pub(crate) fn #receiver(&self) -> buffered_watch::Receiver<(std::time::SystemTime, crate::cyclers::#cycler::Database)> {
+                self.#receiver.clone()
+            }
+        }
+    });
+    let (parameters_field, parameters_identifier, parameters_accessor) = if with_communication {
+        (
+            quote! {
                 parameters_receiver: buffered_watch::Receiver<(std::time::SystemTime, crate::structs::Parameters)>,
             },
-            parameters: quote! {
+            quote! {
                 parameters_receiver,
             },
-            accessors: quote! {
+            quote! {
                 pub fn get_parameters_receiver(&self) -> buffered_watch::Receiver<(std::time::SystemTime, crate::structs::Parameters)> {
                     self.parameters_receiver.clone()
                 }
             },
-        }
+        )
     } else {
-        let receiver_tokens: Vec<_> = cyclers
-            .instances()
-            .map(|(cycler, instance)| {
-                (
-                    format_ident!("{}_receiver", instance.to_case(Case::Snake)),
-                    format_ident!("{}", cycler.name.to_case(Case::Snake)),
-                )
-            })
-            .collect();
-
-        let receiver_identifiers = receiver_tokens.iter().map(|(receiver, _cycler)| receiver);
-        let receiver_fields = receiver_tokens.iter().map(|(receiver, cycler)| {
-            quote! {
-                #receiver: buffered_watch::Receiver<(std::time::SystemTime, crate::cyclers::#cycler::Database)>,
-            }
-        });
-        let receiver_accessors = receiver_tokens.iter().map(|(receiver, cycler)| {
-            quote! {
-                #[allow(unused)]
-                pub(crate) fn #receiver(&self) -> buffered_watch::Receiver<(std::time::SystemTime, crate::cyclers::#cycler::Database)> {
-                    self.#receiver.clone()
-                }
-            }
-        });
+        Default::default()
+    };
 
-        ReplayerTokenStreams {
-            fields: quote! {
-                #(#receiver_fields)*
-            },
-            parameters: quote! {
-                #(#receiver_identifiers,)*
-            },
-            accessors: quote! {
-                #(#receiver_accessors)*
-            },
-        }
+    ReplayerTokenStreams {
+        fields: quote! {
+            #parameters_field
+            #(#receiver_fields)*
+        },
+        parameters: quote! {
+            #parameters_identifier
+            #(#receiver_identifiers,)*
+        },
+        accessors: quote! {
+            #parameters_accessor
+            #(#receiver_accessors)*
+        },
     }
 }
 
@@ -379,19 +448,21 @@ fn generate_future_queues(cyclers: &Cyclers) -> TokenStream {
 }
 
 fn generate_recording_thread(cyclers: &Cyclers) -> TokenStream {
-    let file_creations = cyclers.instances().map(|(_cycler, instance)| {
+    let writer_creations = cyclers.instances().map(|(cycler, instance)| {
         let instance_name_snake_case = format_ident!("{}", instance.to_case(Case::Snake));
-        let recording_file_name = format!("{instance}.bincode");
-        let error_message_file = format!("failed to create recording file for {instance}");
+        let cycler_module_name = format_ident!("{}", cycler.name.to_case(Case::Snake));
+        let error_message = format!("failed to create recording writer for {instance}");
 
         quote! {
-            let recording_file_path = log_path.as_ref().join(#recording_file_name);
-            std::fs::create_dir_all(
-                recording_file_path.parent()
-                    .expect("recording file path has no parent directory")
-            ).wrap_err("failed to create logs folder")?;
-
-            let mut #instance_name_snake_case = std::io::BufWriter::new(std::fs::File::create(recording_file_path).wrap_err(#error_message_file)?); // TODO: possible optimization: buffer size
+            let mut #instance_name_snake_case = framework::RecordingWriter::new(
+                log_path.as_ref(),
+                #instance,
+                crate::cyclers::#cycler_module_name::recording_header(
+                    #instance,
+                    verify_main_outputs.get(#instance).copied().unwrap_or(false),
+                ),
+                recording_storage.get(#instance).cloned().unwrap_or_default(),
+            ).wrap_err(#error_message)?;
         }
     });
     let frame_writes = cyclers.instances().map(|(_cycler, instance)| {
@@ -399,16 +470,22 @@ fn generate_recording_thread(cyclers: &Cyclers) -> TokenStream {
         let instance_name_snake_case = format_ident!("{}", instance.to_case(Case::Snake));
         let error_message = format!("failed to write into recording file for {instance}");
         quote! {
-            crate::cyclers::RecordingFrame::#instance_name { timestamp, duration, data } => {
-                let mut recording_header = Vec::new();
-                bincode::serialize_into(&mut recording_header, &timestamp).wrap_err("failed to serialize timestamp")?;
-                bincode::serialize_into(&mut recording_header, &duration).wrap_err("failed to serialize duration")?;
-                bincode::serialize_into(&mut recording_header, &data.len()).wrap_err("failed to serialize data length")?;
-                #instance_name_snake_case.write_all(recording_header.as_slice()).wrap_err(#error_message)?;
-                #instance_name_snake_case.write_all(data.as_slice()).wrap_err(#error_message)?;
+            crate::cyclers::RecordingFrame::#instance_name { frames } => {
+                for (timing, data) in frames {
+                    #instance_name_snake_case
+                        .write_frame(timing, &data)
+                        .wrap_err(#error_message)?;
+                }
             },
         }
     });
+    let writer_finishes = cyclers.instances().map(|(_cycler, instance)| {
+        let instance_name_snake_case = format_ident!("{}", instance.to_case(Case::Snake));
+        let error_message = format!("failed to finish recording for {instance}");
+        quote! {
+            #instance_name_snake_case.finish().wrap_err(#error_message)?;
+        }
+    });
 
     quote! {
         {
@@ -418,20 +495,22 @@ fn generate_recording_thread(cyclers: &Cyclers) -> TokenStream {
                 .name("Recording".to_string())
                 .spawn(move || -> color_eyre::Result<()> {
                     let result = (|| {
-                        use std::io::Write;
                         {
                             let (_, parameters) = &*parameters_receiver.borrow_and_mark_as_seen();
+                            std::fs::create_dir_all(log_path.as_ref()).wrap_err("failed to create logs folder")?;
                             std::fs::write(
                                 log_path.as_ref().join("default.json"),
                                 serde_json::to_string_pretty(parameters)?,
                             )?;
                         }
-                        #(#file_creations)*
+                        #(#writer_creations)*
                         for recording_frame in recording_receiver {
                             match recording_frame {
                                 #(#frame_writes)*
                             }
                         }
+                        // the recording channel is closed on shutdown, the index speeds up opening the recording later
+                        #(#writer_finishes)*
                         Ok(())
                     })();
 
@@ -454,11 +533,25 @@ fn generate_cycler_constructors(cyclers: &Cyclers, mode: CyclerMode) -> TokenStr
         let own_sender_identifier = format_ident!("{instance_name_snake_case}_sender");
         let own_subscriptions_sender_identifier = format_ident!("{instance_name_snake_case}_subscriptions_sender");
         let own_subscriptions_receiver_identifier = format_ident!("{instance_name_snake_case}_subscriptions_receiver");
+        let overrides_sender_identifier = format_ident!("{instance_name_snake_case}_overrides_sender");
+        let overrides_receiver_identifier = format_ident!("{instance_name_snake_case}_overrides_receiver");
+        let overrides_channel = if mode == CyclerMode::Run {
+            quote! {
+                let (#overrides_sender_identifier, #overrides_receiver_identifier) =
+                    buffered_watch::channel((std::time::SystemTime::now(), Default::default()));
+            }
+        } else {
+            Default::default()
+        };
+        let recording_trigger_error_message =
+            format!("failed to create recording trigger for {instance}");
         let recording_trigger = if mode == CyclerMode::Run {
             quote! {
                 let recording_trigger = framework::RecordingTrigger::new(
-                    recording_intervals.get(#cycler_instance_name).copied().unwrap_or(0)
-                );
+                    recording_intervals.get(#cycler_instance_name).copied().unwrap_or(0),
+                    recording_triggers.get(#cycler_instance_name).cloned().unwrap_or_default(),
+                    &<crate::cyclers::#cycler_module_name::Database as path_serde::PathIntrospect>::get_fields(),
+                ).wrap_err(#recording_trigger_error_message)?;
             }
         } else {
             Default::default()
@@ -467,7 +560,8 @@ fn generate_cycler_constructors(cyclers: &Cyclers, mode: CyclerMode) -> TokenStr
             let recording_file_name = format!("{instance}.bincode");
             quote! {
                 let #cycler_index_identifier = framework::RecordingIndex::read_from(
-                    recordings_file_path.as_ref().join(#recording_file_name)
+                    recordings_file_path.as_ref().join(#recording_file_name),
+                    &crate::cyclers::#cycler_module_name::recording_header(#cycler_instance_name, false),
                 ).wrap_err("failed to read recording index")?;
             }
         } else {
@@ -496,15 +590,22 @@ fn generate_cycler_constructors(cyclers: &Cyclers, mode: CyclerMode) -> TokenStr
             });
         let recording_parameters = if mode == CyclerMode::Run {
             quote! {
+                #overrides_receiver_identifier.clone(),
                 recording_sender.clone(),
                 recording_trigger,
+                recorded_additional_outputs.get(#cycler_instance_name).cloned().unwrap_or_default(),
+                verify_main_outputs.get(#cycler_instance_name).copied().unwrap_or(false),
+                &node_failure_policies,
             }
         } else {
-            Default::default()
+            quote! {
+                #cycler_index_identifier.header().is_some_and(|header| header.has_main_outputs),
+            }
         };
         let error_message = format!("failed to create cycler `{}`", instance);
 
         quote! {
+            #overrides_channel
             #[allow(unused)]
             #recording_trigger
             #recording_index
@@ -545,6 +646,34 @@ fn generate_communication_registrations(cyclers: &Cyclers) -> TokenStream {
         .collect()
 }
 
+fn generate_overrides_registrations(cyclers: &Cyclers) -> TokenStream {
+    cyclers
+        .instances()
+        .map(|(_cycler, instance)| {
+            let instance_name_snake_case = instance.to_case(Case::Snake);
+            let overrides_sender_identifier =
+                format_ident!("{instance_name_snake_case}_overrides_sender");
+            let overrides_receiver_identifier =
+                format_ident!("{instance_name_snake_case}_overrides_receiver");
+            let path = format!("overrides.{instance}");
+            quote! {
+                {
+                    let (overrides_subscriptions, _) = buffered_watch::channel(Default::default());
+                    communication_server.expose_source(
+                        #path,
+                        #overrides_receiver_identifier,
+                        overrides_subscriptions,
+                    ).wrap_err("failed to expose source in communication")?;
+                    communication_server.expose_sink(
+                        #path,
+                        #overrides_sender_identifier,
+                    ).wrap_err("failed to expose sink in communication")?;
+                }
+            }
+        })
+        .collect()
+}
+
 fn generate_cycler_starts(cyclers: &Cyclers) -> TokenStream {
     cyclers
         .instances()
@@ -637,3 +766,42 @@ fn generate_cycler_replays(cyclers: &Cyclers) -> TokenStream {
         })
         .collect()
 }
+
+fn generate_cycler_verifications(cyclers: &Cyclers) -> TokenStream {
+    cyclers
+        .instances()
+        .map(|(_cycler, instance)| {
+            let cycler_variable_identifier =
+                format_ident!("{}_cycler", instance.to_case(Case::Snake));
+            let error_message = format!("failed to verify {} cycle", instance);
+            quote! {
+                #instance => self.#cycler_variable_identifier.find_main_output_divergences(tolerances).wrap_err(#error_message),
+            }
+        })
+        .collect()
+}
+
+fn generate_cycler_database_serializations(cyclers: &Cyclers) -> TokenStream {
+    cyclers
+        .instances()
+        .map(|(_cycler, instance)| {
+            let cycler_variable_identifier =
+                format_ident!("{}_cycler", instance.to_case(Case::Snake));
+            quote! {
+                #instance => self.#cycler_variable_identifier.serialize_database_path(path),
+            }
+        })
+        .collect()
+}
+
+fn generate_cycler_main_output_fields(cyclers: &Cyclers) -> TokenStream {
+    cyclers
+        .instances()
+        .map(|(cycler, instance)| {
+            let cycler_module_name = format_ident!("{}", cycler.name.to_case(Case::Snake));
+            quote! {
+                #instance => Ok(<crate::structs::#cycler_module_name::MainOutputs as path_serde::PathIntrospect>::get_fields()),
+            }
+        })
+        .collect()
+}
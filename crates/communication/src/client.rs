@@ -21,6 +21,10 @@ use tokio::{
 use tokio_tungstenite::{
     connect_async,
     tungstenite::{
+        self,
+        client::IntoClientRequest,
+        handshake::client::Request as ClientRequest,
+        http::{self, header::AUTHORIZATION, HeaderValue},
         protocol::{frame::coding::CloseCode, CloseFrame},
         Message,
     },
@@ -29,7 +33,7 @@ use tokio_tungstenite::{
 
 use crate::{
     client::protocol::Protocol,
-    messages::{Path, Paths, TextOrBinary},
+    messages::{Path, Paths, SubscriptionOptions, TextOrBinary},
     send_or_log::SendOrLogExt,
 };
 
@@ -58,6 +62,7 @@ enum Event {
     Connect,
     Disconnect,
     SetAddress(String),
+    SetToken(Option<String>),
     ReadText {
         path: Path,
         return_sender: oneshot::Sender<Result<(SystemTime, Value), RequestError>>,
@@ -68,10 +73,12 @@ enum Event {
     },
     SubscribeText {
         path: Path,
+        options: SubscriptionOptions,
         return_sender: oneshot::Sender<JsonSubscriptionHandle>,
     },
     SubscribeBinary {
         path: Path,
+        options: SubscriptionOptions,
         return_sender: oneshot::Sender<BinarySubscriptionHandle>,
     },
     Write {
@@ -79,6 +86,10 @@ enum Event {
         value: TextOrBinary,
         return_sender: oneshot::Sender<Result<(), RequestError>>,
     },
+    WriteMany {
+        values: Vec<(Path, TextOrBinary)>,
+        return_sender: oneshot::Sender<Result<(), RequestError>>,
+    },
     GetStatus {
         return_sender: oneshot::Sender<Status>,
     },
@@ -125,6 +136,11 @@ impl ClientHandle {
         self.sender.send(Event::SetAddress(address)).await.unwrap();
     }
 
+    /// Sets the token to authenticate with at the server, reconnects if already connected
+    pub async fn set_token(&self, token: Option<String>) {
+        self.sender.send(Event::SetToken(token)).await.unwrap();
+    }
+
     pub async fn read_text(
         &self,
         path: impl Into<Path>,
@@ -156,10 +172,20 @@ impl ClientHandle {
     }
 
     pub async fn subscribe_text(&self, path: impl Into<Path>) -> JsonSubscriptionHandle {
+        self.subscribe_text_with_options(path, SubscriptionOptions::default())
+            .await
+    }
+
+    pub async fn subscribe_text_with_options(
+        &self,
+        path: impl Into<Path>,
+        options: SubscriptionOptions,
+    ) -> JsonSubscriptionHandle {
         let (return_sender, return_receiver) = oneshot::channel();
         self.sender
             .send(Event::SubscribeText {
                 path: path.into(),
+                options,
                 return_sender,
             })
             .await
@@ -168,10 +194,20 @@ impl ClientHandle {
     }
 
     pub async fn subscribe_binary(&self, path: impl Into<Path>) -> BinarySubscriptionHandle {
+        self.subscribe_binary_with_options(path, SubscriptionOptions::default())
+            .await
+    }
+
+    pub async fn subscribe_binary_with_options(
+        &self,
+        path: impl Into<Path>,
+        options: SubscriptionOptions,
+    ) -> BinarySubscriptionHandle {
         let (return_sender, return_receiver) = oneshot::channel();
         self.sender
             .send(Event::SubscribeBinary {
                 path: path.into(),
+                options,
                 return_sender,
             })
             .await
@@ -192,6 +228,19 @@ impl ClientHandle {
         return_receiver.await.unwrap()
     }
 
+    /// Writes all values at once, either all of them are written or none if any is invalid
+    pub async fn write_many(&self, values: Vec<(Path, TextOrBinary)>) -> Result<(), RequestError> {
+        let (return_sender, return_receiver) = oneshot::channel();
+        self.sender
+            .send(Event::WriteMany {
+                values,
+                return_sender,
+            })
+            .await
+            .unwrap();
+        return_receiver.await.unwrap()
+    }
+
     pub async fn status(&self) -> Status {
         let (return_sender, return_receiver) = oneshot::channel();
         self.sender
@@ -211,6 +260,9 @@ impl ClientHandle {
     }
 }
 
+/// Subscriptions of the same path with different options are served separately by the server
+type SubscriptionKey = (Path, SubscriptionOptions);
+
 struct Subscription<T> {
     sender: broadcast::Sender<Arc<SubscriptionEvent<T>>>,
     drop: mpsc::WeakSender<()>,
@@ -222,11 +274,12 @@ pub struct Client {
     change_watch: watch::Sender<()>,
     connection_state: State,
     peer_address: String,
+    token: Option<String>,
     paths_sender: watch::Sender<PathsEvent>,
-    text_subscriptions: HashMap<Path, Subscription<Value>>,
-    text_unsubscriptions: JoinSet<Path>,
-    binary_subscriptions: HashMap<Path, Subscription<Vec<u8>>>,
-    binary_unsubscriptions: JoinSet<Path>,
+    text_subscriptions: HashMap<SubscriptionKey, Subscription<Value>>,
+    text_unsubscriptions: JoinSet<SubscriptionKey>,
+    binary_subscriptions: HashMap<SubscriptionKey, Subscription<Vec<u8>>>,
+    binary_unsubscriptions: JoinSet<SubscriptionKey>,
 }
 
 impl Client {
@@ -240,6 +293,7 @@ impl Client {
             change_watch: change_sender,
             connection_state: State::Disconnected,
             peer_address,
+            token: None,
             paths_sender,
             text_subscriptions: HashMap::new(),
             text_unsubscriptions: JoinSet::new(),
@@ -267,13 +321,13 @@ impl Client {
                                 None => break,
                             }
                         }
-                        Some(path) = self.text_unsubscriptions.join_next() => {
-                            let path = path.unwrap();
-                            self.text_subscriptions.remove(&path);
+                        Some(key) = self.text_unsubscriptions.join_next() => {
+                            let key = key.unwrap();
+                            self.text_subscriptions.remove(&key);
                         }
-                        Some(path) = self.binary_unsubscriptions.join_next() => {
-                            let path = path.unwrap();
-                            self.binary_subscriptions.remove(&path);
+                        Some(key) = self.binary_unsubscriptions.join_next() => {
+                            let key = key.unwrap();
+                            self.binary_subscriptions.remove(&key);
                         }
                     }
                 }
@@ -291,13 +345,13 @@ impl Client {
                             let socket = maybe_socket.unwrap();
                             self.handle_successful_connection(socket);
                         }
-                        Some(path) = self.text_unsubscriptions.join_next() => {
-                            let path = path.unwrap();
-                            self.text_subscriptions.remove(&path);
+                        Some(key) = self.text_unsubscriptions.join_next() => {
+                            let key = key.unwrap();
+                            self.text_subscriptions.remove(&key);
                         }
-                        Some(path) = self.binary_unsubscriptions.join_next() => {
-                            let path = path.unwrap();
-                            self.binary_subscriptions.remove(&path);
+                        Some(key) = self.binary_unsubscriptions.join_next() => {
+                            let key = key.unwrap();
+                            self.binary_subscriptions.remove(&key);
                         }
                     }
                 }
@@ -314,16 +368,16 @@ impl Client {
                         result = protocol_task => {
                             result.unwrap();
                             self.connection_state = State::Connecting {
-                                ongoing_connection: spawn(try_connect(self.peer_address.clone()))
+                                ongoing_connection: spawn(try_connect(self.peer_address.clone(), self.token.clone()))
                             };
                         }
-                        Some(path) = self.text_unsubscriptions.join_next() => {
-                            let path = path.unwrap();
-                            self.text_subscriptions.remove(&path);
+                        Some(key) = self.text_unsubscriptions.join_next() => {
+                            let key = key.unwrap();
+                            self.text_subscriptions.remove(&key);
                         }
-                        Some(path) = self.binary_unsubscriptions.join_next() => {
-                            let path = path.unwrap();
-                            self.binary_subscriptions.remove(&path);
+                        Some(key) = self.binary_unsubscriptions.join_next() => {
+                            let key = key.unwrap();
+                            self.binary_subscriptions.remove(&key);
                         }
                     }
                 }
@@ -336,7 +390,8 @@ impl Client {
         match command {
             Event::Connect => {
                 if matches!(&self.connection_state, State::Disconnected) {
-                    let ongoing_connection = spawn(try_connect(self.peer_address.clone()));
+                    let ongoing_connection =
+                        spawn(try_connect(self.peer_address.clone(), self.token.clone()));
                     self.connection_state = State::Connecting { ongoing_connection };
                 }
             }
@@ -359,20 +414,11 @@ impl Client {
             },
             Event::SetAddress(address) => {
                 self.peer_address = address;
-                match &mut self.connection_state {
-                    State::Disconnected => {}
-                    State::Connecting { ongoing_connection } => {
-                        ongoing_connection.abort();
-                        self.connection_state = State::Connecting {
-                            ongoing_connection: spawn(try_connect(self.peer_address.clone())),
-                        };
-                    }
-                    State::Connected { .. } => {
-                        self.connection_state = State::Connecting {
-                            ongoing_connection: spawn(try_connect(self.peer_address.clone())),
-                        };
-                    }
-                }
+                self.reconnect();
+            }
+            Event::SetToken(token) => {
+                self.token = token;
+                self.reconnect();
             }
             Event::ReadText {
                 path,
@@ -414,16 +460,18 @@ impl Client {
             }
             Event::SubscribeText {
                 path,
+                options,
                 return_sender,
             } => {
-                let handle = self.subscribe_text(path).await;
+                let handle = self.subscribe_text(path, options).await;
                 let _ = return_sender.send(handle);
             }
             Event::SubscribeBinary {
                 path,
+                options,
                 return_sender,
             } => {
-                let handle = self.subscribe_binary(path).await;
+                let handle = self.subscribe_binary(path, options).await;
                 let _ = return_sender.send(handle);
             }
             Event::Write {
@@ -446,6 +494,25 @@ impl Client {
                     }
                 };
             }
+            Event::WriteMany {
+                values,
+                return_sender,
+            } => {
+                match &self.connection_state {
+                    State::Disconnected | State::Connecting { .. } => {
+                        let _ = return_sender.send(Err(RequestError::NotConnected));
+                    }
+                    State::Connected {
+                        protocol_handle, ..
+                    } => {
+                        let protocol_handle = protocol_handle.clone();
+                        spawn(async move {
+                            let result = protocol_handle.write_many(values).await;
+                            let _ = return_sender.send(result.map_err(RequestError::from));
+                        });
+                    }
+                };
+            }
             Event::GetStatus { return_sender } => {
                 let status = match &self.connection_state {
                     State::Disconnected => Status::Disconnected,
@@ -457,6 +524,29 @@ impl Client {
         }
     }
 
+    fn reconnect(&mut self) {
+        match &mut self.connection_state {
+            State::Disconnected => {}
+            State::Connecting { ongoing_connection } => {
+                ongoing_connection.abort();
+                self.connection_state = State::Connecting {
+                    ongoing_connection: spawn(try_connect(
+                        self.peer_address.clone(),
+                        self.token.clone(),
+                    )),
+                };
+            }
+            State::Connected { .. } => {
+                self.connection_state = State::Connecting {
+                    ongoing_connection: spawn(try_connect(
+                        self.peer_address.clone(),
+                        self.token.clone(),
+                    )),
+                };
+            }
+        }
+    }
+
     fn handle_successful_connection(&mut self, socket: WebSocketStream<MaybeTlsStream<TcpStream>>) {
         info!("connected to {address}", address = self.peer_address);
 
@@ -476,13 +566,14 @@ impl Client {
             });
         }
 
-        for (path, subscription) in &mut self.text_subscriptions {
+        for ((path, options), subscription) in &mut self.text_subscriptions {
             let handle = handle.clone();
             let path = path.clone();
+            let options = *options;
             let update_sender = subscription.sender.clone();
             let (unsubscribe_sender, unsubscribe_receiver) = oneshot::channel();
             spawn(async move {
-                if let Ok(protocol_receiver) = handle.subscribe_text(path).await {
+                if let Ok(protocol_receiver) = handle.subscribe_text(path, options).await {
                     spawn(serve_subscription(
                         protocol_receiver,
                         update_sender,
@@ -493,13 +584,14 @@ impl Client {
             subscription.protocol_unsubscribe = Some(unsubscribe_receiver);
         }
 
-        for (path, subscription) in &mut self.binary_subscriptions {
+        for ((path, options), subscription) in &mut self.binary_subscriptions {
             let handle = handle.clone();
             let path = path.clone();
+            let options = *options;
             let update_sender = subscription.sender.clone();
             let (unsubscribe_sender, unsubscribe_receiver) = oneshot::channel();
             spawn(async move {
-                if let Ok(protocol_receiver) = handle.subscribe_binary(path).await {
+                if let Ok(protocol_receiver) = handle.subscribe_binary(path, options).await {
                     spawn(serve_subscription(
                         protocol_receiver,
                         update_sender,
@@ -511,8 +603,12 @@ impl Client {
         }
     }
 
-    async fn subscribe_text(&mut self, path: Path) -> SubscriptionHandle<Value> {
-        match self.text_subscriptions.entry(path.clone()) {
+    async fn subscribe_text(
+        &mut self,
+        path: Path,
+        options: SubscriptionOptions,
+    ) -> SubscriptionHandle<Value> {
+        match self.text_subscriptions.entry((path.clone(), options)) {
             Occupied(mut entry) => {
                 let subscription = entry.get();
                 match subscription.drop.upgrade() {
@@ -546,7 +642,7 @@ impl Client {
                         } = &self.connection_state
                         {
                             protocol_handle
-                                .subscribe_text(path.clone())
+                                .subscribe_text(path.clone(), options)
                                 .await
                                 .map_or_else(
                                     |_| None,
@@ -570,7 +666,7 @@ impl Client {
                             protocol_unsubscribe: unsubscribe_receiver,
                         };
                         self.text_unsubscriptions
-                            .spawn(wait_for_unsubscription(drop_receiver, path));
+                            .spawn(wait_for_unsubscription(drop_receiver, (path, options)));
                         entry.insert(subscription);
                         SubscriptionHandle {
                             receiver: update_receiver,
@@ -587,7 +683,7 @@ impl Client {
                 } = &self.connection_state
                 {
                     protocol_handle
-                        .subscribe_text(path.clone())
+                        .subscribe_text(path.clone(), options)
                         .await
                         .map_or_else(
                             |_| None,
@@ -610,7 +706,7 @@ impl Client {
                     protocol_unsubscribe: unsubscribe_receiver,
                 };
                 self.text_unsubscriptions
-                    .spawn(wait_for_unsubscription(drop_receiver, path));
+                    .spawn(wait_for_unsubscription(drop_receiver, (path, options)));
                 entry.insert(subscription);
                 SubscriptionHandle {
                     receiver: update_receiver,
@@ -620,8 +716,12 @@ impl Client {
         }
     }
 
-    async fn subscribe_binary(&mut self, path: Path) -> SubscriptionHandle<Vec<u8>> {
-        match self.binary_subscriptions.entry(path.clone()) {
+    async fn subscribe_binary(
+        &mut self,
+        path: Path,
+        options: SubscriptionOptions,
+    ) -> SubscriptionHandle<Vec<u8>> {
+        match self.binary_subscriptions.entry((path.clone(), options)) {
             Occupied(mut entry) => {
                 let subscription = entry.get();
                 match subscription.drop.upgrade() {
@@ -655,7 +755,7 @@ impl Client {
                         } = &self.connection_state
                         {
                             protocol_handle
-                                .subscribe_binary(path.clone())
+                                .subscribe_binary(path.clone(), options)
                                 .await
                                 .map_or_else(
                                     |_| None,
@@ -679,7 +779,7 @@ impl Client {
                             protocol_unsubscribe: unsubscribe_receiver,
                         };
                         self.binary_unsubscriptions
-                            .spawn(wait_for_unsubscription(drop_receiver, path));
+                            .spawn(wait_for_unsubscription(drop_receiver, (path, options)));
                         entry.insert(subscription);
                         SubscriptionHandle {
                             receiver: update_receiver,
@@ -696,7 +796,7 @@ impl Client {
                 } = &self.connection_state
                 {
                     protocol_handle
-                        .subscribe_binary(path.clone())
+                        .subscribe_binary(path.clone(), options)
                         .await
                         .map_or_else(
                             |_| None,
@@ -719,7 +819,7 @@ impl Client {
                     protocol_unsubscribe: unsubscribe_receiver,
                 };
                 self.binary_unsubscriptions
-                    .spawn(wait_for_unsubscription(drop_receiver, path));
+                    .spawn(wait_for_unsubscription(drop_receiver, (path, options)));
                 entry.insert(subscription);
                 SubscriptionHandle {
                     receiver: update_receiver,
@@ -730,10 +830,17 @@ impl Client {
     }
 }
 
-async fn try_connect(address: String) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
+async fn try_connect(
+    address: String,
+    token: Option<String>,
+) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
     info!("connecting to {address} ...");
     loop {
-        match connect_async(&address).await {
+        let result = match create_request(&address, token.as_deref()) {
+            Ok(request) => connect_async(request).await,
+            Err(error) => Err(error),
+        };
+        match result {
             Ok((socket, _)) => {
                 return socket;
             }
@@ -745,6 +852,15 @@ async fn try_connect(address: String) -> WebSocketStream<MaybeTlsStream<TcpStrea
     }
 }
 
+fn create_request(address: &str, token: Option<&str>) -> Result<ClientRequest, tungstenite::Error> {
+    let mut request = address.into_client_request()?;
+    if let Some(token) = token {
+        let value = HeaderValue::from_str(&format!("Bearer {token}")).map_err(http::Error::from)?;
+        request.headers_mut().insert(AUTHORIZATION, value);
+    }
+    Ok(request)
+}
+
 async fn serve_subscription<T>(
     mut protocol_receiver: mpsc::Receiver<SubscriptionEvent<T>>,
     update_sender: broadcast::Sender<Arc<SubscriptionEvent<T>>>,
@@ -769,7 +885,10 @@ async fn serve_subscription<T>(
     }
 }
 
-async fn wait_for_unsubscription(mut drop_receiver: mpsc::Receiver<()>, path: Path) -> Path {
+async fn wait_for_unsubscription(
+    mut drop_receiver: mpsc::Receiver<()>,
+    key: SubscriptionKey,
+) -> SubscriptionKey {
     while drop_receiver.recv().await.is_some() {}
-    path
+    key
 }
@@ -21,8 +21,10 @@ use tokio_tungstenite::{
 
 use crate::{
     messages::{
-        Format, Path, Paths, Request, RequestId, RequestKind, Response, ResponseKind, TextOrBinary,
+        Format, Path, Paths, Request, RequestId, RequestKind, Response, ResponseKind,
+        SubscriptionOptions, TextOrBinary,
     },
+    patch::apply,
     send_or_log::SendOrLogExt,
 };
 
@@ -108,10 +110,12 @@ enum Event {
     },
     SubscribeText {
         path: Path,
+        options: SubscriptionOptions,
         return_sender: oneshot::Sender<mpsc::Receiver<SubscriptionEvent<Value>>>,
     },
     SubscribeBinary {
         path: Path,
+        options: SubscriptionOptions,
         return_sender: oneshot::Sender<mpsc::Receiver<SubscriptionEvent<Vec<u8>>>>,
     },
     Write {
@@ -119,6 +123,10 @@ enum Event {
         value: TextOrBinary,
         return_sender: oneshot::Sender<Result<(), Error>>,
     },
+    WriteMany {
+        values: Vec<(Path, TextOrBinary)>,
+        return_sender: oneshot::Sender<Result<(), Error>>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -160,12 +168,14 @@ impl ProtocolHandle {
     pub async fn subscribe_text(
         &self,
         path: Path,
+        options: SubscriptionOptions,
     ) -> Result<mpsc::Receiver<SubscriptionEvent<Value>>, Error> {
         let (return_sender, return_receiver) = oneshot::channel();
         let _ = self
             .sender
             .send(Event::SubscribeText {
                 path,
+                options,
                 return_sender,
             })
             .await;
@@ -175,12 +185,14 @@ impl ProtocolHandle {
     pub async fn subscribe_binary(
         &self,
         path: Path,
+        options: SubscriptionOptions,
     ) -> Result<mpsc::Receiver<SubscriptionEvent<Vec<u8>>>, Error> {
         let (return_sender, return_receiver) = oneshot::channel();
         let _ = self
             .sender
             .send(Event::SubscribeBinary {
                 path,
+                options,
                 return_sender,
             })
             .await;
@@ -199,6 +211,18 @@ impl ProtocolHandle {
             .await;
         return_receiver.await.map_err(|_| Error::Close)?
     }
+
+    pub async fn write_many(&self, values: Vec<(Path, TextOrBinary)>) -> Result<(), Error> {
+        let (return_sender, return_receiver) = oneshot::channel();
+        let _ = self
+            .sender
+            .send(Event::WriteMany {
+                values,
+                return_sender,
+            })
+            .await;
+        return_receiver.await.map_err(|_| Error::Close)?
+    }
 }
 
 #[derive(Debug, Error)]
@@ -235,7 +259,11 @@ pub struct Protocol {
     next_request_id: RequestId,
     pending_requests: HashMap<RequestId, oneshot::Sender<Response>>,
     subscriptions: HashMap<RequestId, mpsc::Sender<Response>>,
+    /// Paths and options of delta encoded subscriptions, to resubscribe if a patch cannot be applied
+    delta_subscriptions: HashMap<RequestId, (Path, SubscriptionOptions)>,
     subscription_tasks: JoinSet<RequestId>,
+    resubscription_sender: mpsc::UnboundedSender<RequestId>,
+    resubscription_receiver: mpsc::UnboundedReceiver<RequestId>,
 }
 
 impl Protocol {
@@ -244,6 +272,8 @@ impl Protocol {
         change_watch: watch::Sender<()>,
     ) -> (Self, ProtocolHandle) {
         let (event_sender, event_receiver) = mpsc::channel(1);
+        // unbounded to not block subscription tasks while the protocol waits for them
+        let (resubscription_sender, resubscription_receiver) = mpsc::unbounded_channel();
         let task = Self {
             socket,
             event_receiver,
@@ -251,7 +281,10 @@ impl Protocol {
             next_request_id: 0,
             pending_requests: HashMap::new(),
             subscriptions: HashMap::new(),
+            delta_subscriptions: HashMap::new(),
             subscription_tasks: JoinSet::new(),
+            resubscription_sender,
+            resubscription_receiver,
         };
         let handle = ProtocolHandle {
             sender: event_sender,
@@ -296,6 +329,9 @@ impl Protocol {
                     let id = maybe_id.unwrap();
                     self.unsubscribe(id).await?;
                 }
+                Some(id) = self.resubscription_receiver.recv() => {
+                    self.resubscribe(id).await?;
+                }
             };
             let _ = self.change_watch.send(());
         }
@@ -346,16 +382,18 @@ impl Protocol {
             }
             Event::SubscribeText {
                 path,
+                options,
                 return_sender,
             } => {
-                let update_receiver = self.subscribe_text(path).await?;
+                let update_receiver = self.subscribe_text(path, options).await?;
                 let _ = return_sender.send(update_receiver);
             }
             Event::SubscribeBinary {
                 path,
+                options,
                 return_sender,
             } => {
-                let update_receiver = self.subscribe_binary(path).await?;
+                let update_receiver = self.subscribe_binary(path, options).await?;
                 let _ = return_sender.send(update_receiver);
             }
             Event::Write {
@@ -368,6 +406,15 @@ impl Protocol {
                     .await?;
                 spawn(wait_for_write_response(response_receiver, return_sender));
             }
+            Event::WriteMany {
+                values,
+                return_sender,
+            } => {
+                let (response_sender, response_receiver) = oneshot::channel();
+                self.request(RequestKind::WriteMany { values }, response_sender)
+                    .await?;
+                spawn(wait_for_write_response(response_receiver, return_sender));
+            }
         }
         Ok(())
     }
@@ -420,51 +467,102 @@ impl Protocol {
         &mut self,
         path: Path,
         format: Format,
+        options: SubscriptionOptions,
     ) -> Result<(mpsc::Receiver<Response>, RequestId), ClosingError> {
         let (response_sender, response_receiver) = mpsc::channel(1);
+        let id = self.request_subscription(path, format, options).await?;
+        self.subscriptions.insert(id, response_sender);
+        Ok((response_receiver, id))
+    }
+
+    async fn request_subscription(
+        &mut self,
+        path: Path,
+        format: Format,
+        options: SubscriptionOptions,
+    ) -> Result<RequestId, ClosingError> {
         let id = self.next_request_id;
         self.next_request_id += 1;
         let request = Request {
             id,
-            kind: RequestKind::Subscribe { path, format },
+            kind: RequestKind::Subscribe {
+                path,
+                format,
+                options,
+            },
         };
         let message = Message::Text(
             serde_json::to_string(&request).map_err(ClosingError::JsonSerialization)?,
         );
         self.socket.send_or_log(message).await;
-        self.subscriptions.insert(id, response_sender);
-        Ok((response_receiver, id))
+        Ok(id)
     }
 
     async fn subscribe_text(
         &mut self,
         path: Path,
+        options: SubscriptionOptions,
     ) -> Result<mpsc::Receiver<SubscriptionEvent<Value>>, ClosingError> {
-        let (response_receiver, id) = self.subscribe(path, Format::Text).await?;
+        let (response_receiver, id) = self.subscribe(path.clone(), Format::Text, options).await?;
         let (update_sender, update_receiver) = mpsc::channel(1);
-        self.subscription_tasks
-            .spawn(serve_subscription(response_receiver, update_sender, id));
+        let resubscription_sender = if options.delta {
+            self.delta_subscriptions.insert(id, (path, options));
+            Some(self.resubscription_sender.clone())
+        } else {
+            None
+        };
+        self.subscription_tasks.spawn(serve_subscription(
+            response_receiver,
+            update_sender,
+            id,
+            resubscription_sender,
+        ));
         Ok(update_receiver)
     }
 
     async fn subscribe_binary(
         &mut self,
         path: Path,
+        options: SubscriptionOptions,
     ) -> Result<mpsc::Receiver<SubscriptionEvent<Vec<u8>>>, ClosingError> {
-        let (response_receiver, id) = self.subscribe(path, Format::Binary).await?;
+        let (response_receiver, id) = self.subscribe(path, Format::Binary, options).await?;
         let (update_sender, update_receiver) = mpsc::channel(1);
-        self.subscription_tasks
-            .spawn(serve_subscription(response_receiver, update_sender, id));
+        // binary updates are never delta encoded
+        self.subscription_tasks.spawn(serve_subscription(
+            response_receiver,
+            update_sender,
+            id,
+            None,
+        ));
         Ok(update_receiver)
     }
 
     async fn unsubscribe(&mut self, id: RequestId) -> Result<(), ClosingError> {
+        self.delta_subscriptions.remove(&id);
         let (response_sender, response_receiver) = oneshot::channel();
         self.request(RequestKind::Unsubscribe { id }, response_sender)
             .await?;
         spawn(wait_for_unsubscribe_response(response_receiver));
         Ok(())
     }
+
+    /// Replaces a delta encoded subscription whose patches cannot be applied anymore by a new one,
+    /// the server responds with the full value to it
+    async fn resubscribe(&mut self, id: RequestId) -> Result<(), ClosingError> {
+        let (Some(response_sender), Some((path, options))) = (
+            self.subscriptions.remove(&id),
+            self.delta_subscriptions.remove(&id),
+        ) else {
+            return Ok(());
+        };
+        self.unsubscribe(id).await?;
+        let new_id = self
+            .request_subscription(path.clone(), Format::Text, options)
+            .await?;
+        self.subscriptions.insert(new_id, response_sender);
+        self.delta_subscriptions.insert(new_id, (path, options));
+        Ok(())
+    }
 }
 
 async fn wait_for_paths_response(
@@ -545,13 +643,47 @@ async fn wait_for_read_binary_response(
 async fn serve_subscription(
     mut response_receiver: mpsc::Receiver<Response>,
     update_sender: mpsc::Sender<impl From<Response>>,
-    id: RequestId,
+    mut id: RequestId,
+    resubscription_sender: Option<mpsc::UnboundedSender<RequestId>>,
 ) -> RequestId {
+    let mut last_value = None;
+    let mut is_resubscribing = false;
     loop {
         select! {
             maybe_response = response_receiver.recv() => {
                 match maybe_response {
                     Some(response) => {
+                        // the id changes if a delta encoded subscription is resubscribed
+                        id = response.id;
+                        let response = match &resubscription_sender {
+                            Some(resubscription_sender) => {
+                                let is_subscribe_response = matches!(
+                                    response.kind,
+                                    Ok(ResponseKind::Subscribe { .. }) | Err(_)
+                                );
+                                if is_resubscribing && !is_subscribe_response {
+                                    // patches against the corrupted value are dropped until the full value arrives
+                                    continue;
+                                }
+                                let response = if is_resubscribing {
+                                    is_resubscribing = false;
+                                    subscribe_to_update_response(response)
+                                } else {
+                                    response
+                                };
+                                match reconstruct_value(response, &mut last_value) {
+                                    Ok(response) => response,
+                                    Err(error) => {
+                                        warn!("resubscribing subscription {id}: {error}");
+                                        last_value = None;
+                                        is_resubscribing = true;
+                                        let _ = resubscription_sender.send(id);
+                                        continue;
+                                    }
+                                }
+                            }
+                            None => response,
+                        };
                         let _ = update_sender.send(response.into()).await;
                     },
                     None => break,
@@ -566,6 +698,72 @@ async fn serve_subscription(
     id
 }
 
+/// Replaces the patch of a delta encoded update by the full value, the patch is applied to a copy of
+/// the last value, which is only replaced if the whole patch applies.
+///
+/// Patches are computed by the server against the value it sent last, there is no acknowledgement
+/// of received values. This relies on the in-order delivery of websocket messages, if a patch does
+/// not apply nonetheless, the subscription has to be resubscribed to receive the full value.
+fn reconstruct_value(
+    response: Response,
+    last_value: &mut Option<Value>,
+) -> Result<Response, String> {
+    let kind = match response.kind {
+        Ok(ResponseKind::Subscribe {
+            timestamp,
+            value: TextOrBinary::Text(value),
+        }) => {
+            *last_value = Some(value.clone());
+            Ok(ResponseKind::Subscribe {
+                timestamp,
+                value: TextOrBinary::Text(value),
+            })
+        }
+        Ok(ResponseKind::Update {
+            timestamp,
+            value: TextOrBinary::Text(value),
+        }) => {
+            *last_value = Some(value.clone());
+            Ok(ResponseKind::Update {
+                timestamp,
+                value: TextOrBinary::Text(value),
+            })
+        }
+        Ok(ResponseKind::UpdatePatch { timestamp, patch }) => {
+            let Some(value) = last_value else {
+                return Err("received patch without previous value".to_string());
+            };
+            let mut value = value.clone();
+            apply(&mut value, &patch).map_err(|error| format!("failed to apply patch: {error}"))?;
+            *last_value = Some(value.clone());
+            Ok(ResponseKind::Update {
+                timestamp,
+                value: TextOrBinary::Text(value),
+            })
+        }
+        kind => kind,
+    };
+    Ok(Response {
+        id: response.id,
+        kind,
+    })
+}
+
+/// The response to a resubscription is passed on as update, the subscription handle only expects
+/// a single successful subscription
+fn subscribe_to_update_response(response: Response) -> Response {
+    let kind = match response.kind {
+        Ok(ResponseKind::Subscribe { timestamp, value }) => {
+            Ok(ResponseKind::Update { timestamp, value })
+        }
+        kind => kind,
+    };
+    Response {
+        id: response.id,
+        kind,
+    }
+}
+
 async fn wait_for_unsubscribe_response(response_receiver: oneshot::Receiver<Response>) {
     let Ok(response) = response_receiver.await else {
         return;
@@ -603,3 +801,35 @@ async fn wait_for_write_response(
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::messages::PatchOperation;
+
+    use super::*;
+
+    #[test]
+    fn failed_patch_leaves_last_value_untouched() {
+        let mut last_value = Some(json!({"position": [1.0, 2.0], "age": 3}));
+        let response = Response {
+            id: 4,
+            kind: Ok(ResponseKind::UpdatePatch {
+                timestamp: SystemTime::now(),
+                patch: vec![
+                    PatchOperation::Replace {
+                        path: "/age".to_string(),
+                        value: json!(4),
+                    },
+                    PatchOperation::Remove {
+                        path: "/velocity".to_string(),
+                    },
+                ],
+            }),
+        };
+
+        assert!(reconstruct_value(response, &mut last_value).is_err());
+        assert_eq!(last_value, Some(json!({"position": [1.0, 2.0], "age": 3})));
+    }
+}
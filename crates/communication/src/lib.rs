@@ -10,5 +10,6 @@
 
 pub mod client;
 pub mod messages;
+pub mod patch;
 mod send_or_log;
 pub mod server;
@@ -1,4 +1,8 @@
-use std::{collections::BTreeMap, time::SystemTime};
+use std::{
+    collections::BTreeMap,
+    num::NonZeroUsize,
+    time::{Duration, SystemTime},
+};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -24,6 +28,19 @@ pub enum Format {
     Binary,
 }
 
+/// Limits the updates of a subscription, by default every cycle of the source is sent
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct SubscriptionOptions {
+    /// Updates following the last sent one within this interval are dropped
+    pub minimum_interval: Option<Duration>,
+    /// Only every n-th update is sent
+    pub decimation: Option<NonZeroUsize>,
+    /// Updates are only sent if the value differs from the last sent one
+    pub only_on_change: bool,
+    /// Text updates are sent as patches against the last sent value, binary updates are unaffected
+    pub delta: bool,
+}
+
 pub type Path = String;
 pub type Error = String;
 pub type RequestId = usize;
@@ -39,10 +56,26 @@ pub struct Request {
 #[non_exhaustive]
 pub enum RequestKind {
     GetPaths,
-    Read { path: Path, format: Format },
-    Subscribe { path: Path, format: Format },
-    Unsubscribe { id: RequestId },
-    Write { path: Path, value: TextOrBinary },
+    Read {
+        path: Path,
+        format: Format,
+    },
+    Subscribe {
+        path: Path,
+        format: Format,
+        #[serde(default)]
+        options: SubscriptionOptions,
+    },
+    Unsubscribe {
+        id: RequestId,
+    },
+    Write {
+        path: Path,
+        value: TextOrBinary,
+    },
+    WriteMany {
+        values: Vec<(Path, TextOrBinary)>,
+    },
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -54,6 +87,17 @@ pub struct Response {
 
 pub type Paths = BTreeMap<Path, Entry>;
 
+/// Operation of a JSON-patch style diff, paths are JSON pointers into the value
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+pub type Patch = Vec<PatchOperation>;
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[non_exhaustive]
 pub enum ResponseKind {
@@ -72,6 +116,10 @@ pub enum ResponseKind {
         timestamp: SystemTime,
         value: TextOrBinary,
     },
+    UpdatePatch {
+        timestamp: SystemTime,
+        patch: Patch,
+    },
     Unsubscribe,
     Write,
 }
@@ -0,0 +1,195 @@
+//! JSON-patch style diffs between subsequent values of delta encoded subscriptions
+//!
+//! Paths of the operations are JSON pointers (RFC 6901) into the value. The operations are a
+//! subset of JSON patch (RFC 6902): array items are only appended or removed from the end, other
+//! changes within arrays are replaced item by item.
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::messages::{Patch, PatchOperation};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no such path: `{0}`")]
+    NoSuchPath(String),
+    #[error("invalid array index in `{0}`")]
+    InvalidIndex(String),
+}
+
+/// Computes the operations which turn `old` into `new`
+pub fn diff(old: &Value, new: &Value) -> Patch {
+    let mut patch = Vec::new();
+    diff_at("", old, new, &mut patch);
+    patch
+}
+
+fn diff_at(path: &str, old: &Value, new: &Value, patch: &mut Patch) {
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields)) => {
+            for key in old_fields.keys() {
+                if !new_fields.contains_key(key) {
+                    patch.push(PatchOperation::Remove {
+                        path: child_path(path, key),
+                    });
+                }
+            }
+            for (key, new_field) in new_fields {
+                let field_path = child_path(path, key);
+                match old_fields.get(key) {
+                    Some(old_field) => diff_at(&field_path, old_field, new_field, patch),
+                    None => patch.push(PatchOperation::Add {
+                        path: field_path,
+                        value: new_field.clone(),
+                    }),
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            for (index, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate() {
+                diff_at(
+                    &child_path(path, &index.to_string()),
+                    old_item,
+                    new_item,
+                    patch,
+                );
+            }
+            for index in (new_items.len()..old_items.len()).rev() {
+                patch.push(PatchOperation::Remove {
+                    path: child_path(path, &index.to_string()),
+                });
+            }
+            for (index, new_item) in new_items.iter().enumerate().skip(old_items.len()) {
+                patch.push(PatchOperation::Add {
+                    path: child_path(path, &index.to_string()),
+                    value: new_item.clone(),
+                });
+            }
+        }
+        _ => {
+            if old != new {
+                patch.push(PatchOperation::Replace {
+                    path: path.to_string(),
+                    value: new.clone(),
+                });
+            }
+        }
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Applies the operations of a patch computed by [`diff`] to `value`
+pub fn apply(value: &mut Value, patch: &[PatchOperation]) -> Result<(), Error> {
+    for operation in patch {
+        match operation {
+            PatchOperation::Add {
+                path,
+                value: new_value,
+            } => match parent_and_key(value, path)? {
+                (Value::Object(fields), key) => {
+                    fields.insert(key, new_value.clone());
+                }
+                (Value::Array(items), key) => {
+                    let index = key
+                        .parse()
+                        .ok()
+                        .filter(|index| *index <= items.len())
+                        .ok_or_else(|| Error::InvalidIndex(path.clone()))?;
+                    items.insert(index, new_value.clone());
+                }
+                _ => return Err(Error::NoSuchPath(path.clone())),
+            },
+            PatchOperation::Remove { path } => match parent_and_key(value, path)? {
+                (Value::Object(fields), key) => {
+                    fields
+                        .remove(&key)
+                        .ok_or_else(|| Error::NoSuchPath(path.clone()))?;
+                }
+                (Value::Array(items), key) => {
+                    let index = key
+                        .parse()
+                        .ok()
+                        .filter(|index| *index < items.len())
+                        .ok_or_else(|| Error::InvalidIndex(path.clone()))?;
+                    items.remove(index);
+                }
+                _ => return Err(Error::NoSuchPath(path.clone())),
+            },
+            PatchOperation::Replace {
+                path,
+                value: new_value,
+            } => {
+                *value
+                    .pointer_mut(path)
+                    .ok_or_else(|| Error::NoSuchPath(path.clone()))? = new_value.clone();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parent_and_key<'value>(
+    value: &'value mut Value,
+    path: &str,
+) -> Result<(&'value mut Value, String), Error> {
+    let (parent_path, key) = path
+        .rsplit_once('/')
+        .ok_or_else(|| Error::NoSuchPath(path.to_string()))?;
+    let parent = value
+        .pointer_mut(parent_path)
+        .ok_or_else(|| Error::NoSuchPath(path.to_string()))?;
+    Ok((parent, key.replace("~1", "/").replace("~0", "~")))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn applied_diff_reconstructs_new_value() {
+        let old = json!({
+            "ball": {"position": [1.0, 2.0], "age": 3},
+            "obstacles": [{"radius": 0.3}, {"radius": 0.2}],
+            "removed": true,
+            "a/b~c": 1,
+        });
+        let new = json!({
+            "ball": {"position": [1.0, 2.5], "age": 3},
+            "obstacles": [{"radius": 0.4}],
+            "added": null,
+            "a/b~c": 2,
+        });
+
+        let patch = diff(&old, &new);
+        let mut value = old.clone();
+        apply(&mut value, &patch).unwrap();
+
+        assert_eq!(value, new);
+    }
+
+    #[test]
+    fn only_changed_values_are_patched() {
+        let old = json!({"ball": {"position": [1.0, 2.0], "age": 3}, "items": [1]});
+        let new = json!({"ball": {"position": [1.0, 2.5], "age": 3}, "items": [1, 2]});
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                PatchOperation::Replace {
+                    path: "/ball/position/1".to_string(),
+                    value: json!(2.5),
+                },
+                PatchOperation::Add {
+                    path: "/items/1".to_string(),
+                    value: json!(2),
+                },
+            ]
+        );
+        assert_eq!(diff(&new, &new), Vec::new());
+    }
+}
@@ -1,4 +1,5 @@
 mod acceptor;
+mod access_control;
 mod connection;
 mod router;
 mod sink;
@@ -8,6 +9,7 @@ use std::{
     collections::{BTreeMap, HashMap, HashSet},
     io,
     marker::{Send, Sync},
+    sync::Arc,
     time::SystemTime,
 };
 
@@ -27,6 +29,8 @@ use crate::{
     server::{acceptor::Acceptor, router::Router},
 };
 
+pub use self::access_control::{AccessControl, Role};
+
 use self::{
     sink::{Sink, SinkHandle},
     source::{Source, SourceHandle},
@@ -74,9 +78,14 @@ pub struct Server {
     sources: HashMap<Path, SourceHandle>,
     sinks: HashMap<Path, SinkHandle>,
     tasks: JoinSet<()>,
+    access_control: AccessControl,
 }
 
 impl Server {
+    pub fn set_access_control(&mut self, access_control: AccessControl) {
+        self.access_control = access_control;
+    }
+
     pub async fn serve(
         mut self,
         addresses: impl ToSocketAddrs + Send,
@@ -86,9 +95,14 @@ impl Server {
         let (router, router_handle) = Router::new(self.tree, self.sources, self.sinks);
         let router_task = spawn(router.run());
 
-        Acceptor::new(listener, router_handle, cancellation_token.clone())
-            .run()
-            .await;
+        Acceptor::new(
+            listener,
+            router_handle,
+            Arc::new(self.access_control),
+            cancellation_token.clone(),
+        )
+        .run()
+        .await;
 
         router_task.await.unwrap();
         while let Some(result) = self.tasks.join_next().await {
@@ -1,13 +1,18 @@
+use std::sync::Arc;
+
 use log::{error, info};
 use tokio::{
     net::{TcpListener, TcpStream},
     select,
     task::JoinSet,
 };
-use tokio_tungstenite::accept_async;
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::handshake::server::{ErrorResponse, Request, Response},
+};
 use tokio_util::sync::CancellationToken;
 
-use super::{connection::Connection, router::RouterHandle};
+use super::{access_control::AccessControl, connection::Connection, router::RouterHandle};
 
 pub type ClientId = usize;
 
@@ -15,6 +20,7 @@ pub struct Acceptor {
     listener: TcpListener,
     cancellation_token: CancellationToken,
     router: RouterHandle,
+    access_control: Arc<AccessControl>,
     next_client_id: usize,
     connection_tasks: JoinSet<()>,
 }
@@ -23,12 +29,14 @@ impl Acceptor {
     pub fn new(
         listener: TcpListener,
         router: RouterHandle,
+        access_control: Arc<AccessControl>,
         cancellation_token: CancellationToken,
     ) -> Self {
         Self {
             listener,
             cancellation_token,
             router,
+            access_control,
             next_client_id: 0,
             connection_tasks: JoinSet::new(),
         }
@@ -70,17 +78,26 @@ impl Acceptor {
     }
 
     async fn accept(&mut self, socket: TcpStream) {
-        let stream = match accept_async(socket).await {
+        let mut role = None;
+        let authenticate = |request: &Request, response: Response| -> Result<_, ErrorResponse> {
+            let (response, client_role) = self.access_control.authenticate(request, response)?;
+            role = Some(client_role);
+            Ok(response)
+        };
+        let stream = match accept_hdr_async(socket, authenticate).await {
             Ok(stream) => stream,
             Err(error) => {
                 error!("failed to accept websocket connection: {error}");
                 return;
             }
         };
+        let role = role.expect("successful handshake should have authenticated the client");
         let (connection, _) = Connection::new(
             stream,
             self.next_client_id,
+            role,
             self.router.clone(),
+            self.access_control.clone(),
             self.cancellation_token.clone(),
         );
         self.next_client_id += 1;
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::{header::AUTHORIZATION, StatusCode},
+};
+
+use crate::messages::Path;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Authentication and authorization of clients, by default every client may read and write every path
+#[derive(Clone, Debug)]
+pub struct AccessControl {
+    /// Pre-shared tokens and the role they grant, clients send them as `Authorization: Bearer <token>` header during the handshake
+    pub tokens: HashMap<String, Role>,
+    /// Role of clients without a known token, these clients are rejected if `None`
+    pub unauthenticated_role: Option<Role>,
+    /// Prefixes of writable paths, e.g. `parameters`, every path is writable if `None`
+    pub writable_paths: Option<Vec<Path>>,
+}
+
+impl Default for AccessControl {
+    fn default() -> Self {
+        Self {
+            tokens: HashMap::new(),
+            unauthenticated_role: Some(Role::ReadWrite),
+            writable_paths: None,
+        }
+    }
+}
+
+impl AccessControl {
+    pub fn role(&self, token: Option<&str>) -> Option<Role> {
+        token
+            .and_then(|token| self.tokens.get(token))
+            .copied()
+            .or(self.unauthenticated_role)
+    }
+
+    pub fn is_writable(&self, role: Role, path: &str) -> bool {
+        if role != Role::ReadWrite {
+            return false;
+        }
+        match &self.writable_paths {
+            Some(prefixes) => prefixes.iter().any(|prefix| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|suffix| suffix.is_empty() || suffix.starts_with('.'))
+            }),
+            None => true,
+        }
+    }
+
+    /// Determines the role of a connecting client from its handshake request, unknown clients are rejected
+    pub fn authenticate(
+        &self,
+        request: &Request,
+        response: Response,
+    ) -> Result<(Response, Role), ErrorResponse> {
+        let token = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match self.role(token) {
+            Some(role) => Ok((response, role)),
+            None => {
+                let mut response = ErrorResponse::new(Some("unknown token".to_string()));
+                *response.status_mut() = StatusCode::UNAUTHORIZED;
+                Err(response)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writable_paths_are_matched_by_path_segments() {
+        let access_control = AccessControl {
+            writable_paths: Some(vec!["parameters".to_string()]),
+            ..Default::default()
+        };
+
+        assert!(access_control.is_writable(Role::ReadWrite, "parameters"));
+        assert!(access_control.is_writable(Role::ReadWrite, "parameters.ball_filter"));
+        assert!(!access_control.is_writable(Role::ReadWrite, "parameters_backup"));
+        assert!(!access_control.is_writable(Role::ReadWrite, "overrides.Control"));
+        assert!(!access_control.is_writable(Role::ReadOnly, "parameters.ball_filter"));
+    }
+
+    #[test]
+    fn unknown_tokens_get_unauthenticated_role() {
+        let access_control = AccessControl {
+            tokens: [("secret".to_string(), Role::ReadWrite)].into(),
+            unauthenticated_role: Some(Role::ReadOnly),
+            writable_paths: None,
+        };
+
+        assert_eq!(access_control.role(Some("secret")), Some(Role::ReadWrite));
+        assert_eq!(access_control.role(Some("guess")), Some(Role::ReadOnly));
+        assert_eq!(access_control.role(None), Some(Role::ReadOnly));
+
+        let access_control = AccessControl {
+            unauthenticated_role: None,
+            ..access_control
+        };
+        assert_eq!(access_control.role(None), None);
+    }
+}
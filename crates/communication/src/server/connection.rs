@@ -1,8 +1,9 @@
-use std::{collections::HashMap, time::SystemTime};
+use std::{collections::HashMap, sync::Arc, time::SystemTime};
 
-use color_eyre::eyre::{eyre, Report};
+use color_eyre::eyre::{bail, eyre, Report};
 use futures_util::{SinkExt, StreamExt};
 use log::{error, info};
+use serde_json::Value;
 use thiserror::Error;
 use tokio::{net::TcpStream, select, sync::mpsc};
 use tokio_tungstenite::{
@@ -17,11 +18,13 @@ use tokio_util::sync::CancellationToken;
 
 use crate::{
     messages::{Request, RequestId, RequestKind, Response, ResponseKind, TextOrBinary},
+    patch::diff,
     send_or_log::SendOrLogExt,
 };
 
 use super::{
     acceptor::ClientId,
+    access_control::{AccessControl, Role},
     router::RouterHandle,
     source::{SubscriptionHandle, Update},
 };
@@ -72,16 +75,23 @@ impl ConnectionHandle {
         self.id
     }
 
-    pub fn try_send_update(&self, update: Update) {
-        let _ = self.event_sender.try_send(Event::SendUpdate(update));
+    /// Returns whether the update was queued, it is dropped if the connection is congested
+    pub fn try_send_update(&self, update: Update) -> bool {
+        self.event_sender
+            .try_send(Event::SendUpdate(update))
+            .is_ok()
     }
 }
 
 pub struct Connection {
     subscriptions: HashMap<RequestId, SubscriptionHandle>,
+    /// Last sent values of delta encoded subscriptions, patches of updates are computed against them
+    delta_values: HashMap<RequestId, Value>,
     handle: ConnectionHandle,
     stream: WebSocketStream<TcpStream>,
+    role: Role,
     router: RouterHandle,
+    access_control: Arc<AccessControl>,
     event_receiver: mpsc::Receiver<Event>,
     server_cancellation: CancellationToken,
 }
@@ -90,7 +100,9 @@ impl Connection {
     pub fn new(
         stream: WebSocketStream<TcpStream>,
         id: ClientId,
+        role: Role,
         router: RouterHandle,
+        access_control: Arc<AccessControl>,
         server_cancellation: CancellationToken,
     ) -> (Self, ConnectionHandle) {
         let (event_sender, event_receiver) = mpsc::channel(10);
@@ -98,9 +110,12 @@ impl Connection {
 
         let task = Self {
             subscriptions: HashMap::new(),
+            delta_values: HashMap::new(),
             handle: handle.clone(),
             stream,
+            role,
             router,
+            access_control,
             event_receiver,
             server_cancellation,
         };
@@ -201,19 +216,31 @@ impl Connection {
     async fn handle_request(&mut self, request: Request) -> Result<ResponseKind, Report> {
         match request.kind {
             RequestKind::GetPaths => {
-                let paths = self.router.get_paths().await;
+                let mut paths = self.router.get_paths().await;
+                for (path, entry) in &mut paths {
+                    entry.is_writable &= self.access_control.is_writable(self.role, path);
+                }
                 Ok(ResponseKind::Paths { paths })
             }
             RequestKind::Read { path, format } => {
                 let (timestamp, value) = self.router.read(path, format).await?;
                 Ok(ResponseKind::Read { timestamp, value })
             }
-            RequestKind::Subscribe { path, format } => {
+            RequestKind::Subscribe {
+                path,
+                format,
+                options,
+            } => {
                 let (handle, timestamp, value) = self
                     .router
-                    .subscribe(path, format, self.handle.clone(), request.id)
+                    .subscribe(path, format, options, self.handle.clone(), request.id)
                     .await?;
                 self.subscriptions.insert(request.id, handle);
+                if options.delta {
+                    if let TextOrBinary::Text(value) = &value {
+                        self.delta_values.insert(request.id, value.clone());
+                    }
+                }
                 Ok(ResponseKind::Subscribe { timestamp, value })
             }
             RequestKind::Unsubscribe { id } => {
@@ -221,18 +248,33 @@ impl Connection {
                     .subscriptions
                     .remove(&id)
                     .ok_or_else(|| eyre!("no subscription with id `{id}`"))?;
+                self.delta_values.remove(&id);
                 Ok(ResponseKind::Unsubscribe)
             }
             RequestKind::Write { path, value } => {
+                if !self.access_control.is_writable(self.role, &path) {
+                    bail!("client is not permitted to write `{path}`");
+                }
                 let timestamp = SystemTime::now();
                 self.router.write(path, timestamp, value).await?;
                 Ok(ResponseKind::Write)
             }
+            RequestKind::WriteMany { values } => {
+                if let Some((path, _)) = values
+                    .iter()
+                    .find(|(path, _)| !self.access_control.is_writable(self.role, path))
+                {
+                    bail!("client is not permitted to write `{path}`");
+                }
+                let timestamp = SystemTime::now();
+                self.router.write_many(values, timestamp).await?;
+                Ok(ResponseKind::Write)
+            }
         }
     }
 
     async fn send_update(&mut self, update: Update) -> Result<(), ClosingError> {
-        let messages = compose_update_messages(update)?;
+        let messages = compose_update_messages(update, &mut self.delta_values)?;
         for message in messages {
             if let Err(error) = self.stream.feed(message).await {
                 error!("failed to send update: {error:#}");
@@ -245,15 +287,25 @@ impl Connection {
     }
 }
 
-fn compose_update_messages(update: Update) -> Result<Vec<Message>, ClosingError> {
+fn compose_update_messages(
+    update: Update,
+    delta_values: &mut HashMap<RequestId, Value>,
+) -> Result<Vec<Message>, ClosingError> {
     let timestamp = update.timestamp;
     update
         .texts
         .into_iter()
         .map(|(id, value)| {
-            let kind = value.map(|value| ResponseKind::Update {
-                timestamp,
-                value: TextOrBinary::Text(value),
+            let kind = value.map(|value| match delta_values.get_mut(&id) {
+                Some(last_value) => {
+                    let patch = diff(last_value, &value);
+                    *last_value = value;
+                    ResponseKind::UpdatePatch { timestamp, patch }
+                }
+                None => ResponseKind::Update {
+                    timestamp,
+                    value: TextOrBinary::Text(value),
+                },
             });
             let response = Response { id, kind };
             let string =
@@ -284,9 +336,10 @@ mod tests {
     use path_serde::{PathIntrospect, PathSerialize};
     use serde::Serialize;
     use serde_json::json;
+    use tokio::task::yield_now;
 
     use crate::{
-        messages::{Format, Path},
+        messages::{Format, PatchOperation, Path, SubscriptionOptions},
         server::source::Source,
     };
 
@@ -317,7 +370,13 @@ mod tests {
         let format = Format::Text;
         let id = 4;
         let (subscription, timestamp, value) = handle
-            .subscribe(path, format, client.clone(), id)
+            .subscribe(
+                path,
+                format,
+                SubscriptionOptions::default(),
+                client.clone(),
+                id,
+            )
             .await
             .unwrap();
 
@@ -350,4 +409,189 @@ mod tests {
         drop(handle);
         task.await.unwrap();
     }
+
+    #[tokio::test]
+    async fn subscribe_only_on_change() {
+        let (mut data_sender, data_receiver) =
+            buffered_watch::channel((UNIX_EPOCH, Data { field: 42 }));
+        let (subscriptions_sender, _subscriptions_receiver) =
+            buffered_watch::channel(HashSet::new());
+
+        let (source, handle) = Source::new(data_receiver, subscriptions_sender);
+        let task = tokio::spawn(source.run());
+
+        let (client_event_sender, mut client_event_receiver) = mpsc::channel(1);
+        let client = ConnectionHandle {
+            event_sender: client_event_sender,
+            id: 13,
+        };
+
+        let options = SubscriptionOptions {
+            only_on_change: true,
+            ..Default::default()
+        };
+        let id = 4;
+        let (subscription, _timestamp, _value) = handle
+            .subscribe(Path::from("field"), Format::Text, options, client, id)
+            .await
+            .unwrap();
+
+        // The value did not change, so the client should not receive this update.
+        *data_sender.borrow_mut() = (SystemTime::now(), Data { field: 42 });
+        // Let the source handle the update before the next one, otherwise both are coalesced
+        yield_now().await;
+        assert_eq!(
+            client_event_receiver.try_recv(),
+            Err(mpsc::error::TryRecvError::Empty)
+        );
+        let timestamp = SystemTime::now();
+        *data_sender.borrow_mut() = (timestamp, Data { field: 1337 });
+
+        let update = Update {
+            timestamp,
+            texts: once((id, Ok(json!(1337)))).collect(),
+            binaries: HashMap::new(),
+        };
+        let received_event = client_event_receiver.recv().await.unwrap();
+        assert_eq!(received_event, Event::SendUpdate(update));
+
+        drop(subscription);
+        drop(handle);
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn subscribe_only_on_change_resends_dropped_values() {
+        let (mut data_sender, data_receiver) =
+            buffered_watch::channel((UNIX_EPOCH, Data { field: 42 }));
+        let (subscriptions_sender, _subscriptions_receiver) =
+            buffered_watch::channel(HashSet::new());
+
+        let (source, handle) = Source::new(data_receiver, subscriptions_sender);
+        let task = tokio::spawn(source.run());
+
+        let (client_event_sender, mut client_event_receiver) = mpsc::channel(1);
+        let client = ConnectionHandle {
+            event_sender: client_event_sender,
+            id: 13,
+        };
+
+        let options = SubscriptionOptions {
+            only_on_change: true,
+            ..Default::default()
+        };
+        let id = 4;
+        let (subscription, _timestamp, _value) = handle
+            .subscribe(Path::from("field"), Format::Text, options, client, id)
+            .await
+            .unwrap();
+
+        // The first update fills the event channel, the second one is dropped.
+        *data_sender.borrow_mut() = (SystemTime::now(), Data { field: 1337 });
+        yield_now().await;
+        *data_sender.borrow_mut() = (SystemTime::now(), Data { field: 7 });
+        yield_now().await;
+        client_event_receiver.recv().await.unwrap();
+
+        // The value did not change, but the client has not received it yet.
+        let timestamp = SystemTime::now();
+        *data_sender.borrow_mut() = (timestamp, Data { field: 7 });
+
+        let update = Update {
+            timestamp,
+            texts: once((id, Ok(json!(7)))).collect(),
+            binaries: HashMap::new(),
+        };
+        let received_event = client_event_receiver.recv().await.unwrap();
+        assert_eq!(received_event, Event::SendUpdate(update));
+
+        drop(subscription);
+        drop(handle);
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn subscribe_wildcard() {
+        let (mut data_sender, data_receiver) =
+            buffered_watch::channel((UNIX_EPOCH, Data { field: 42 }));
+        let (subscriptions_sender, mut subscriptions_receiver) =
+            buffered_watch::channel(HashSet::new());
+
+        let (source, handle) = Source::new(data_receiver, subscriptions_sender);
+        let task = tokio::spawn(source.run());
+
+        let (client_event_sender, mut client_event_receiver) = mpsc::channel(1);
+        let client = ConnectionHandle {
+            event_sender: client_event_sender,
+            id: 13,
+        };
+
+        let paths = [
+            (Path::from("data.field"), Path::from("field")),
+            (Path::from("data.missing"), Path::from("missing")),
+        ]
+        .into();
+        let id = 4;
+        let (subscription, _timestamp, value) = handle
+            .subscribe_wildcard(
+                paths,
+                Format::Text,
+                SubscriptionOptions::default(),
+                client,
+                id,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(value, TextOrBinary::Text(json!({"data.field": 42})));
+        assert_eq!(
+            *subscriptions_receiver.borrow(),
+            [Path::from("field"), Path::from("missing")].into()
+        );
+
+        let timestamp = SystemTime::now();
+        *data_sender.borrow_mut() = (timestamp, Data { field: 1337 });
+
+        let update = Update {
+            timestamp,
+            texts: once((id, Ok(json!({"data.field": 1337})))).collect(),
+            binaries: HashMap::new(),
+        };
+        let received_event = client_event_receiver.recv().await.unwrap();
+        assert_eq!(received_event, Event::SendUpdate(update));
+
+        drop(subscription);
+        drop(handle);
+        task.await.unwrap();
+    }
+
+    #[test]
+    fn delta_encoded_updates_are_patches_against_last_sent_value() {
+        let id = 4;
+        let mut delta_values = once((id, json!({"position": [1.0, 2.0], "age": 3}))).collect();
+        let timestamp = SystemTime::now();
+        let update = Update {
+            timestamp,
+            texts: once((id, Ok(json!({"position": [1.0, 2.5], "age": 3})))).collect(),
+            binaries: HashMap::new(),
+        };
+
+        let messages = compose_update_messages(update, &mut delta_values).unwrap();
+
+        let response = Response {
+            id,
+            kind: Ok(ResponseKind::UpdatePatch {
+                timestamp,
+                patch: vec![PatchOperation::Replace {
+                    path: "/position/1".to_string(),
+                    value: json!(2.5),
+                }],
+            }),
+        };
+        assert_eq!(
+            messages,
+            vec![Message::Text(serde_json::to_string(&response).unwrap())]
+        );
+        assert_eq!(delta_values[&id], json!({"position": [1.0, 2.5], "age": 3}));
+    }
 }
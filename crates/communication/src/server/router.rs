@@ -1,5 +1,6 @@
 use std::{
     collections::{BTreeMap, HashMap},
+    iter::once,
     time::SystemTime,
 };
 
@@ -7,7 +8,7 @@ use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 
 use crate::{
-    messages::{Entry, Format, Path, RequestId, TextOrBinary},
+    messages::{Entry, Format, Path, RequestId, SubscriptionOptions, TextOrBinary},
     server::source,
 };
 
@@ -34,6 +35,8 @@ pub enum Error {
         #[source]
         error: sink::Error,
     },
+    #[error("`{0}` and `{1}` belong to different sinks and cannot be written at once")]
+    DifferentSinks(Path, Path),
 }
 
 enum Event {
@@ -48,6 +51,7 @@ enum Event {
     Subscribe {
         path: Path,
         format: Format,
+        options: SubscriptionOptions,
         client: ConnectionHandle,
         id: RequestId,
         return_sender:
@@ -59,6 +63,11 @@ enum Event {
         value: TextOrBinary,
         return_sender: oneshot::Sender<Result<(), Error>>,
     },
+    WriteMany {
+        values: Vec<(Path, TextOrBinary)>,
+        timestamp: SystemTime,
+        return_sender: oneshot::Sender<Result<(), Error>>,
+    },
 }
 
 #[derive(Clone)]
@@ -97,6 +106,7 @@ impl RouterHandle {
         &self,
         path: Path,
         format: Format,
+        options: SubscriptionOptions,
         client: ConnectionHandle,
         id: RequestId,
     ) -> Result<(SubscriptionHandle, SystemTime, TextOrBinary), Error> {
@@ -105,6 +115,7 @@ impl RouterHandle {
             .send(Event::Subscribe {
                 path,
                 format,
+                options,
                 client,
                 id,
                 return_sender,
@@ -132,6 +143,23 @@ impl RouterHandle {
             .unwrap();
         return_receiver.await.unwrap()
     }
+
+    pub async fn write_many(
+        &self,
+        values: Vec<(Path, TextOrBinary)>,
+        timestamp: SystemTime,
+    ) -> Result<(), Error> {
+        let (return_sender, return_receiver) = oneshot::channel();
+        self.command_sender
+            .send(Event::WriteMany {
+                values,
+                timestamp,
+                return_sender,
+            })
+            .await
+            .unwrap();
+        return_receiver.await.unwrap()
+    }
 }
 
 pub struct Router {
@@ -175,11 +203,12 @@ impl Router {
                 Event::Subscribe {
                     path,
                     format,
+                    options,
                     client,
                     id,
                     return_sender,
                 } => {
-                    let result = self.subscribe(path, format, client, id).await;
+                    let result = self.subscribe(path, format, options, client, id).await;
                     let _ = return_sender.send(result);
                 }
                 Event::Write {
@@ -191,6 +220,14 @@ impl Router {
                     let result = self.write(path, timestamp, value).await;
                     let _ = return_sender.send(result);
                 }
+                Event::WriteMany {
+                    values,
+                    timestamp,
+                    return_sender,
+                } => {
+                    let result = self.write_many(values, timestamp).await;
+                    let _ = return_sender.send(result);
+                }
             }
         }
     }
@@ -214,19 +251,41 @@ impl Router {
         &self,
         path: Path,
         format: Format,
+        options: SubscriptionOptions,
         client: ConnectionHandle,
         id: RequestId,
     ) -> Result<(SubscriptionHandle, SystemTime, TextOrBinary), Error> {
         let hit = find_mount(&self.sources, &path)?;
 
-        let response = hit
-            .mount
-            .subscribe(hit.path, format, client, id)
-            .await
-            .map_err(|error| Error::Source {
-                source: hit.mount_point.to_string(),
-                error,
-            })?;
+        let response = if path.contains('*') {
+            let paths: BTreeMap<_, _> = self
+                .tree
+                .paths
+                .iter()
+                .filter(|(matched_path, entry)| {
+                    entry.is_readable && matches_wildcard(&path, matched_path)
+                })
+                .filter_map(|(matched_path, _)| {
+                    let source_path = matched_path.strip_prefix(hit.mount_point.as_str())?;
+                    let source_path = source_path.strip_prefix('.').unwrap_or("");
+                    Some((matched_path.clone(), source_path.to_string()))
+                })
+                .collect();
+            if paths.is_empty() {
+                return Err(Error::NoSuchPath(path.clone()));
+            }
+            hit.mount
+                .subscribe_wildcard(paths, format, options, client, id)
+                .await
+        } else {
+            hit.mount
+                .subscribe(hit.path, format, options, client, id)
+                .await
+        };
+        let response = response.map_err(|error| Error::Source {
+            source: hit.mount_point.to_string(),
+            error,
+        })?;
 
         Ok(response)
     }
@@ -247,6 +306,60 @@ impl Router {
             })?;
         Ok(())
     }
+
+    /// Writes all values within one update of their sink, hence they have to belong to the same sink
+    async fn write_many(
+        &self,
+        values: Vec<(Path, TextOrBinary)>,
+        timestamp: SystemTime,
+    ) -> Result<(), Error> {
+        let mut mount_point: Option<(Path, Path)> = None;
+        let mut sink_values = Vec::with_capacity(values.len());
+        for (path, value) in values {
+            let hit = find_mount(&self.sinks, &path)?;
+            match &mount_point {
+                Some((first_mount_point, first_path)) if first_mount_point != hit.mount_point => {
+                    return Err(Error::DifferentSinks(first_path.clone(), path.clone()));
+                }
+                Some(_) => {}
+                None => mount_point = Some((hit.mount_point.clone(), path.clone())),
+            }
+            sink_values.push((hit.path.to_string(), value));
+        }
+        let Some((mount_point, _)) = mount_point else {
+            return Ok(());
+        };
+
+        self.sinks[&mount_point]
+            .write_many(sink_values, timestamp)
+            .await
+            .map_err(|error| Error::Sink {
+                sink: mount_point,
+                error,
+            })?;
+        Ok(())
+    }
+}
+
+/// Matches paths segment by segment, a `*` matches any characters within a segment
+fn matches_wildcard(pattern: &str, path: &str) -> bool {
+    pattern.split('.').count() == path.split('.').count()
+        && pattern
+            .split('.')
+            .zip(path.split('.'))
+            .all(|(pattern, segment)| matches_segment(pattern, segment))
+}
+
+fn matches_segment(pattern: &str, segment: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((head, tail)) => segment.strip_prefix(head).is_some_and(|rest| {
+            rest.char_indices()
+                .map(|(index, _)| index)
+                .chain(once(rest.len()))
+                .any(|index| matches_segment(tail, &rest[index..]))
+        }),
+        None => pattern == segment,
+    }
 }
 
 struct Match<'a, T> {
@@ -270,3 +383,28 @@ fn find_mount<'a, T>(
         })
         .ok_or_else(|| Error::NoSuchPath(path.clone()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_within_segments() {
+        assert!(matches_wildcard(
+            "main_outputs.*_obstacles",
+            "main_outputs.ball_obstacles"
+        ));
+        assert!(matches_wildcard("main_outputs.*", "main_outputs.ball"));
+        assert!(matches_wildcard("*.ball.*", "main_outputs.ball.position"));
+        assert!(matches_wildcard("main_outputs.b*l*", "main_outputs.ball"));
+        assert!(!matches_wildcard(
+            "main_outputs.*",
+            "main_outputs.ball.position"
+        ));
+        assert!(!matches_wildcard("main_outputs.*", "main_outputs"));
+        assert!(!matches_wildcard(
+            "main_outputs.*_obstacles",
+            "main_outputs.obstacles"
+        ));
+    }
+}
@@ -14,6 +14,12 @@ pub enum Error {
     Json(#[from] path_serde::deserialize::Error<serde_json::Error>),
     #[error(transparent)]
     Bincode(#[from] path_serde::deserialize::Error<bincode::Error>),
+    #[error("failed to write `{path}`")]
+    WriteMany {
+        path: Path,
+        #[source]
+        error: Box<Error>,
+    },
 }
 
 pub enum Event {
@@ -23,6 +29,11 @@ pub enum Event {
         value: TextOrBinary,
         return_sender: oneshot::Sender<Result<(), Error>>,
     },
+    WriteMany {
+        values: Vec<(Path, TextOrBinary)>,
+        timestamp: SystemTime,
+        return_sender: oneshot::Sender<Result<(), Error>>,
+    },
 }
 
 pub struct SinkHandle {
@@ -48,6 +59,23 @@ impl SinkHandle {
             .unwrap();
         return_receiver.await.unwrap()
     }
+
+    pub async fn write_many(
+        &self,
+        values: Vec<(Path, TextOrBinary)>,
+        timestamp: SystemTime,
+    ) -> Result<(), Error> {
+        let (return_sender, return_receiver) = oneshot::channel();
+        self.command_sender
+            .send(Event::WriteMany {
+                values,
+                timestamp,
+                return_sender,
+            })
+            .await
+            .unwrap();
+        return_receiver.await.unwrap()
+    }
 }
 
 pub struct Sink<T> {
@@ -81,6 +109,14 @@ where
                     let result = self.write(&path, timestamp, value);
                     let _ = return_sender.send(result);
                 }
+                Event::WriteMany {
+                    values,
+                    timestamp,
+                    return_sender,
+                } => {
+                    let result = self.write_many(values, timestamp);
+                    let _ = return_sender.send(result);
+                }
             }
         }
     }
@@ -92,40 +128,78 @@ where
         value: TextOrBinary,
     ) -> Result<(), Error> {
         let data = if path.is_empty() {
-            match value {
-                TextOrBinary::Text(text) => serde_json::from_value(text)
-                    .map_err(path_serde::deserialize::Error::DeserializationFailed)
-                    .map_err(Error::Json)?,
-                TextOrBinary::Binary(bytes) => bincode::deserialize(&bytes)
-                    .map_err(path_serde::deserialize::Error::DeserializationFailed)
-                    .map_err(Error::Bincode)?,
-            }
+            deserialize(value)?
         } else {
             let mut data = self.data_sender.borrow().1.clone();
-
-            match value {
-                TextOrBinary::Text(text) => {
-                    data.deserialize_path(path, text).map_err(Error::Json)?;
-                }
-                TextOrBinary::Binary(bytes) => {
-                    data.deserialize_path(
-                        path,
-                        &mut Deserializer::from_slice(
-                            &bytes,
-                            DefaultOptions::new()
-                                .with_fixint_encoding()
-                                .allow_trailing_bytes(),
-                        ),
-                    )
-                    .map_err(Error::Bincode)?;
-                }
-            };
+            deserialize_path(&mut data, path, value)?;
             data
         };
 
         *self.data_sender.borrow_mut() = (timestamp, data);
         Ok(())
     }
+
+    /// Applies all values to one copy of the data, which is only sent if all of them were valid
+    fn write_many(
+        &mut self,
+        values: Vec<(Path, TextOrBinary)>,
+        timestamp: SystemTime,
+    ) -> Result<(), Error> {
+        let mut data = self.data_sender.borrow().1.clone();
+        for (path, value) in values {
+            let result = if path.is_empty() {
+                deserialize(value).map(|value| data = value)
+            } else {
+                deserialize_path(&mut data, &path, value)
+            };
+            result.map_err(|error| Error::WriteMany {
+                path,
+                error: Box::new(error),
+            })?;
+        }
+
+        *self.data_sender.borrow_mut() = (timestamp, data);
+        Ok(())
+    }
+}
+
+fn deserialize<T>(value: TextOrBinary) -> Result<T, Error>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    let data = match value {
+        TextOrBinary::Text(text) => serde_json::from_value(text)
+            .map_err(path_serde::deserialize::Error::DeserializationFailed)
+            .map_err(Error::Json)?,
+        TextOrBinary::Binary(bytes) => bincode::deserialize(&bytes)
+            .map_err(path_serde::deserialize::Error::DeserializationFailed)
+            .map_err(Error::Bincode)?,
+    };
+    Ok(data)
+}
+
+fn deserialize_path<T>(data: &mut T, path: &str, value: TextOrBinary) -> Result<(), Error>
+where
+    T: PathDeserialize,
+{
+    match value {
+        TextOrBinary::Text(text) => {
+            data.deserialize_path(path, text).map_err(Error::Json)?;
+        }
+        TextOrBinary::Binary(bytes) => {
+            data.deserialize_path(
+                path,
+                &mut Deserializer::from_slice(
+                    &bytes,
+                    DefaultOptions::new()
+                        .with_fixint_encoding()
+                        .allow_trailing_bytes(),
+                ),
+            )
+            .map_err(Error::Bincode)?;
+        }
+    };
+    Ok(())
 }
 
 #[cfg(test)]
@@ -199,4 +273,34 @@ mod tests {
         drop(handle);
         task.await.unwrap();
     }
+
+    #[tokio::test]
+    async fn write_many_is_all_or_nothing() {
+        let (data_sender, mut data_receiver) =
+            buffered_watch::channel((UNIX_EPOCH, Data { foo: 42 }));
+        let (sink, handle) = Sink::new(data_sender);
+        let task = tokio::spawn(sink.run());
+
+        let values = vec![
+            (Path::from("foo"), TextOrBinary::Text(json!(1337))),
+            (Path::from("foo"), TextOrBinary::Text(json!("invalid"))),
+        ];
+        let result = handle.write_many(values, SystemTime::now()).await;
+        assert!(result.is_err());
+        assert_eq!(data_receiver.borrow().1.foo, 42);
+
+        let values = vec![
+            (Path::from(""), TextOrBinary::Text(json!({"foo": 1337}))),
+            (Path::from("foo"), TextOrBinary::Text(json!(1338))),
+        ];
+        let now = SystemTime::now();
+        handle.write_many(values, now).await.unwrap();
+
+        drop(handle);
+        task.await.unwrap();
+
+        let (timestamp, data) = &*data_receiver.borrow();
+        assert_eq!(timestamp, &now);
+        assert_eq!(data.foo, 1338);
+    }
 }
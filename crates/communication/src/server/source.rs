@@ -1,6 +1,9 @@
 use std::{
-    collections::{HashMap, HashSet},
-    time::SystemTime,
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    iter::once,
+    num::NonZeroUsize,
+    time::{Instant, SystemTime},
 };
 
 use bincode::{DefaultOptions, Options};
@@ -15,7 +18,7 @@ use tokio::{
     task::{yield_now, JoinSet},
 };
 
-use crate::messages::{Format, Path, RequestId, TextOrBinary};
+use crate::messages::{Format, Path, RequestId, SubscriptionOptions, TextOrBinary};
 
 use super::{acceptor::ClientId, connection::ConnectionHandle};
 
@@ -41,6 +44,26 @@ pub enum Error {
     BinarySerialization(#[source] path_serde::serialize::Error<bincode::Error>),
     #[error("duplicate subscription with id `{0}`")]
     DuplicateSubscription(RequestId),
+    #[error("wildcard subscriptions are only supported in text format")]
+    BinaryWildcard,
+}
+
+/// Paths of the data a subscription is serialized from
+#[derive(Debug)]
+pub enum Selection {
+    Path(Path),
+    /// Serialized as map from each key to the value at its path, paths that fail to serialize are
+    /// left out
+    Wildcard(BTreeMap<Path, Path>),
+}
+
+impl Selection {
+    fn paths(&self) -> Box<dyn Iterator<Item = &Path> + '_> {
+        match self {
+            Selection::Path(path) => Box::new(once(path)),
+            Selection::Wildcard(paths) => Box::new(paths.values()),
+        }
+    }
 }
 
 pub enum Event {
@@ -50,8 +73,9 @@ pub enum Event {
         return_sender: oneshot::Sender<Result<(SystemTime, TextOrBinary), Error>>,
     },
     Subscribe {
-        path: Path,
+        selection: Selection,
         format: Format,
+        options: SubscriptionOptions,
         client: ConnectionHandle,
         id: RequestId,
         return_sender:
@@ -91,14 +115,41 @@ impl SourceHandle {
         &self,
         path: impl Into<Path>,
         format: Format,
+        options: SubscriptionOptions,
+        client: ConnectionHandle,
+        id: RequestId,
+    ) -> Result<(SubscriptionHandle, SystemTime, TextOrBinary), Error> {
+        self.subscribe_selection(Selection::Path(path.into()), format, options, client, id)
+            .await
+    }
+
+    /// Subscribes to several paths at once, the keys of `paths` are the keys of the serialized map
+    pub async fn subscribe_wildcard(
+        &self,
+        paths: BTreeMap<Path, Path>,
+        format: Format,
+        options: SubscriptionOptions,
+        client: ConnectionHandle,
+        id: RequestId,
+    ) -> Result<(SubscriptionHandle, SystemTime, TextOrBinary), Error> {
+        self.subscribe_selection(Selection::Wildcard(paths), format, options, client, id)
+            .await
+    }
+
+    async fn subscribe_selection(
+        &self,
+        selection: Selection,
+        format: Format,
+        options: SubscriptionOptions,
         client: ConnectionHandle,
         id: RequestId,
     ) -> Result<(SubscriptionHandle, SystemTime, TextOrBinary), Error> {
         let (return_sender, return_receiver) = oneshot::channel();
         self.command_sender
             .send(Event::Subscribe {
-                path: path.into(),
+                selection,
                 format,
+                options,
                 client,
                 id,
                 return_sender,
@@ -111,8 +162,62 @@ impl SourceHandle {
 
 #[derive(Debug)]
 struct Subscription {
-    path: Path,
+    selection: Selection,
     format: Format,
+    options: SubscriptionOptions,
+    updates_since_sample: usize,
+    last_sample: Instant,
+    last_sent_hash: Option<u64>,
+}
+
+impl Subscription {
+    fn new(
+        selection: Selection,
+        format: Format,
+        options: SubscriptionOptions,
+        value: &TextOrBinary,
+    ) -> Self {
+        let last_sent_hash = options.only_on_change.then(|| match value {
+            TextOrBinary::Text(value) => hash_text(Ok(value)),
+            TextOrBinary::Binary(bytes) => hash_binary(Ok(bytes)),
+        });
+        Self {
+            selection,
+            format,
+            options,
+            updates_since_sample: 0,
+            last_sample: Instant::now(),
+            last_sent_hash,
+        }
+    }
+
+    /// Counts the update and returns whether it is sampled according to decimation and maximum rate
+    fn sample(&mut self, now: Instant) -> bool {
+        self.updates_since_sample += 1;
+        let decimation = self.options.decimation.map_or(1, NonZeroUsize::get);
+        let minimum_interval = self.options.minimum_interval.unwrap_or_default();
+        if self.updates_since_sample < decimation
+            || now.duration_since(self.last_sample) < minimum_interval
+        {
+            return false;
+        }
+        self.updates_since_sample = 0;
+        self.last_sample = now;
+        true
+    }
+
+    /// Returns whether a sampled value has to be sent, i.e. it changed if only changes are requested
+    fn is_sent(&mut self, hash: impl FnOnce() -> u64) -> bool {
+        if !self.options.only_on_change {
+            return true;
+        }
+        let hash = hash();
+        if self.last_sent_hash == Some(hash) {
+            return false;
+        }
+        self.last_sent_hash = Some(hash);
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -194,13 +299,14 @@ where
                 let _ = return_sender.send(response);
             }
             Event::Subscribe {
-                path,
+                selection,
                 format,
+                options,
                 client,
                 id,
                 return_sender,
             } => {
-                let response = self.subscribe(path, format, client, id);
+                let response = self.subscribe(selection, format, options, client, id);
                 let _ = return_sender.send(response);
             }
         }
@@ -226,8 +332,9 @@ where
 
     fn subscribe(
         &mut self,
-        path: String,
+        selection: Selection,
         format: Format,
+        options: SubscriptionOptions,
         client: ConnectionHandle,
         id: usize,
     ) -> Result<(SubscriptionHandle, SystemTime, TextOrBinary), Error> {
@@ -241,20 +348,30 @@ where
 
         let (timestamp, value) = {
             let (timestamp, data) = &*self.data.borrow();
-            let value = match format {
-                Format::Text => {
-                    let value = serialize_as_text(data, &path)?;
+            let value = match (&selection, format) {
+                (Selection::Path(path), Format::Text) => {
+                    let value = serialize_as_text(data, path)?;
                     TextOrBinary::Text(value)
                 }
-                Format::Binary => {
-                    let bytes = serialize_as_binary(data, &path)?;
+                (Selection::Path(path), Format::Binary) => {
+                    let bytes = serialize_as_binary(data, path)?;
                     TextOrBinary::Binary(bytes)
                 }
+                (Selection::Wildcard(paths), Format::Text) => {
+                    let values = paths
+                        .iter()
+                        .filter_map(|(key, path)| {
+                            Some((key.clone(), serialize_as_text(data, path).ok()?))
+                        })
+                        .collect();
+                    TextOrBinary::Text(Value::Object(values))
+                }
+                (Selection::Wildcard(_), Format::Binary) => return Err(Error::BinaryWildcard),
             };
             (*timestamp, value)
         };
 
-        let subscription = Subscription { path, format };
+        let subscription = Subscription::new(selection, format, options, &value);
         self.client_subscriptions
             .entry(client_id)
             .or_insert_with(|| ClientSubscriptions::new(client))
@@ -293,53 +410,105 @@ where
     }
 
     async fn handle_update(&mut self) {
-        let cache = self.serialize_subscribed();
-        for client_subscriptions in self.client_subscriptions.values() {
+        let now = Instant::now();
+        let mut sampled_subscriptions = HashSet::new();
+        for (client_id, client_subscriptions) in &mut self.client_subscriptions {
+            for (id, subscription) in &mut client_subscriptions.subscriptions {
+                if subscription.sample(now) {
+                    sampled_subscriptions.insert((*client_id, *id));
+                }
+            }
+        }
+        let cache = self.serialize_subscribed(&sampled_subscriptions);
+        for (client_id, client_subscriptions) in &mut self.client_subscriptions {
             let mut texts = HashMap::new();
             let mut binaries = HashMap::new();
-            for (id, subscription) in &client_subscriptions.subscriptions {
-                match subscription.format {
-                    Format::Text => {
-                        let value = cache.values[&subscription.path].clone();
-                        texts.insert(*id, value);
+            for (id, subscription) in &mut client_subscriptions.subscriptions {
+                if !sampled_subscriptions.contains(&(*client_id, *id)) {
+                    continue;
+                }
+                match (&subscription.selection, subscription.format) {
+                    (Selection::Path(path), Format::Text) => {
+                        let value = &cache.values[path];
+                        if subscription.is_sent(|| hash_text(value.as_ref())) {
+                            texts.insert(*id, value.clone());
+                        }
+                    }
+                    (Selection::Path(path), Format::Binary) => {
+                        let bytes = &cache.bytes[path];
+                        if subscription.is_sent(|| hash_binary(bytes.as_ref())) {
+                            binaries.insert(*id, bytes.clone());
+                        }
                     }
-                    Format::Binary => {
-                        let bytes = cache.bytes[&subscription.path].clone();
-                        binaries.insert(*id, bytes);
+                    (Selection::Wildcard(paths), Format::Text) => {
+                        let values = paths
+                            .iter()
+                            .filter_map(|(key, path)| {
+                                Some((key.clone(), cache.values[path].clone().ok()?))
+                            })
+                            .collect();
+                        let value = Ok(Value::Object(values));
+                        if subscription.is_sent(|| hash_text(value.as_ref())) {
+                            texts.insert(*id, value);
+                        }
                     }
+                    (Selection::Wildcard(_), Format::Binary) => {}
                 };
             }
+            if texts.is_empty() && binaries.is_empty() {
+                continue;
+            }
+            let sent_ids: Vec<_> = texts.keys().chain(binaries.keys()).copied().collect();
             let update = Update {
                 timestamp: cache.timestamp,
                 texts,
                 binaries,
             };
-            client_subscriptions.client.try_send_update(update);
+            if !client_subscriptions.client.try_send_update(update) {
+                // the dropped values have to be sent with the next update, even if they do not change
+                for id in sent_ids {
+                    if let Some(subscription) = client_subscriptions.subscriptions.get_mut(&id) {
+                        subscription.last_sent_hash = None;
+                    }
+                }
+            }
         }
         yield_now().await;
     }
 
-    fn serialize_subscribed(&mut self) -> SerializationCache {
+    fn serialize_subscribed(
+        &mut self,
+        sampled_subscriptions: &HashSet<(ClientId, RequestId)>,
+    ) -> SerializationCache {
         let mut serialized_values = HashMap::new();
         let mut serialized_bytes = HashMap::new();
 
         let (timestamp, data) = &*self.data.borrow_and_mark_as_seen();
 
-        for subscription in self
-            .client_subscriptions
-            .values()
-            .flat_map(|map| map.subscriptions.values())
-        {
-            match subscription.format {
-                Format::Text => {
-                    let value = serialize_as_text(data, &subscription.path)
-                        .map_err(|error| error.to_string());
-                    serialized_values.insert(subscription.path.clone(), value);
+        for (client_id, client_subscriptions) in &self.client_subscriptions {
+            for (id, subscription) in &client_subscriptions.subscriptions {
+                if !sampled_subscriptions.contains(&(*client_id, *id)) {
+                    continue;
                 }
-                Format::Binary => {
-                    let bytes = serialize_as_binary(data, &subscription.path)
-                        .map_err(|error| error.to_string());
-                    serialized_bytes.insert(subscription.path.clone(), bytes);
+                for path in subscription.selection.paths() {
+                    match subscription.format {
+                        Format::Text => {
+                            if serialized_values.contains_key(path) {
+                                continue;
+                            }
+                            let value =
+                                serialize_as_text(data, path).map_err(|error| error.to_string());
+                            serialized_values.insert(path.clone(), value);
+                        }
+                        Format::Binary => {
+                            if serialized_bytes.contains_key(path) {
+                                continue;
+                            }
+                            let bytes =
+                                serialize_as_binary(data, path).map_err(|error| error.to_string());
+                            serialized_bytes.insert(path.clone(), bytes);
+                        }
+                    }
                 }
             }
         }
@@ -354,11 +523,23 @@ where
         self.client_subscriptions
             .values()
             .flat_map(|map| map.subscriptions.values())
-            .map(|subscription| subscription.path.clone())
+            .flat_map(|subscription| subscription.selection.paths().cloned())
             .collect()
     }
 }
 
+fn hash_text(value: Result<&Value, &String>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.map(Value::to_string).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn hash_binary(bytes: Result<&Vec<u8>, &String>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
 fn serialize_as_text<T>(data: &T, path: &Path) -> Result<Value, Error>
 where
     T: Serialize + PathSerialize,
@@ -393,3 +574,64 @@ where
     }
     Ok(bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn subscription(options: SubscriptionOptions) -> Subscription {
+        Subscription::new(
+            Selection::Path(Path::from("field")),
+            Format::Text,
+            options,
+            &TextOrBinary::Text(json!(42)),
+        )
+    }
+
+    #[test]
+    fn sample_every_nth_update_with_decimation() {
+        let mut subscription = subscription(SubscriptionOptions {
+            decimation: NonZeroUsize::new(3),
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        let sampled: Vec<_> = (0..7).map(|_| subscription.sample(now)).collect();
+
+        assert_eq!(sampled, [false, false, true, false, false, true, false]);
+    }
+
+    #[test]
+    fn drop_updates_within_minimum_interval() {
+        let mut subscription = subscription(SubscriptionOptions {
+            minimum_interval: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        let start = subscription.last_sample;
+
+        assert!(!subscription.sample(start + Duration::from_millis(50)));
+        assert!(subscription.sample(start + Duration::from_millis(100)));
+        assert!(!subscription.sample(start + Duration::from_millis(150)));
+        assert!(subscription.sample(start + Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn decimation_counts_updates_within_minimum_interval() {
+        let mut subscription = subscription(SubscriptionOptions {
+            minimum_interval: Some(Duration::from_millis(100)),
+            decimation: NonZeroUsize::new(2),
+            ..Default::default()
+        });
+        let start = subscription.last_sample;
+
+        assert!(!subscription.sample(start + Duration::from_millis(50)));
+        assert!(!subscription.sample(start + Duration::from_millis(60)));
+        assert!(subscription.sample(start + Duration::from_millis(100)));
+        assert!(!subscription.sample(start + Duration::from_millis(210)));
+        assert!(subscription.sample(start + Duration::from_millis(220)));
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// Permissions of a communication client, converted into `communication::server::Role`
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+pub enum Role {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Access control of the communication server as configured in the framework parameters, converted
+/// into `communication::server::AccessControl` when the server is started
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessControl {
+    /// Pre-shared tokens and the role they grant
+    #[serde(default)]
+    pub tokens: HashMap<String, Role>,
+    /// Role of clients without a known token, these clients are rejected if `None`
+    #[serde(default = "default_unauthenticated_role")]
+    pub unauthenticated_role: Option<Role>,
+    /// Prefixes of writable paths, every path is writable if `None`
+    #[serde(default)]
+    pub writable_paths: Option<Vec<String>>,
+}
+
+impl Default for AccessControl {
+    fn default() -> Self {
+        Self {
+            tokens: HashMap::new(),
+            unauthenticated_role: default_unauthenticated_role(),
+            writable_paths: None,
+        }
+    }
+}
+
+fn default_unauthenticated_role() -> Option<Role> {
+    Some(Role::ReadWrite)
+}
@@ -0,0 +1,70 @@
+use std::{collections::VecDeque, time::Duration};
+
+use path_serde::{PathIntrospect, PathSerialize};
+use serde::{Deserialize, Serialize};
+
+/// Number of cycles the statistics are computed over
+const WINDOW_SIZE: usize = 1000;
+
+/// Statistics in seconds over the durations of the last cycles of a node
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PathSerialize, PathIntrospect, PartialEq,
+)]
+pub struct TimingStatistics {
+    pub minimum: f32,
+    pub mean: f32,
+    pub maximum: f32,
+    pub p99: f32,
+}
+
+/// Collects the durations of the last cycles of a node
+#[derive(Clone, Debug, Default)]
+pub struct DurationStatistics {
+    durations: VecDeque<Duration>,
+}
+
+impl DurationStatistics {
+    pub fn update(&mut self, duration: Duration) {
+        if self.durations.len() == WINDOW_SIZE {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+    }
+
+    /// Sorts the collected durations, therefore only call it if the statistics are needed
+    pub fn statistics(&self) -> TimingStatistics {
+        if self.durations.is_empty() {
+            return TimingStatistics::default();
+        }
+        let mut durations: Vec<_> = self.durations.iter().copied().collect();
+        durations.sort_unstable();
+        let p99_index = (durations.len() * 99).div_ceil(100) - 1;
+        TimingStatistics {
+            minimum: durations[0].as_secs_f32(),
+            mean: (durations.iter().sum::<Duration>() / durations.len() as u32).as_secs_f32(),
+            maximum: durations[durations.len() - 1].as_secs_f32(),
+            p99: durations[p99_index].as_secs_f32(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_cover_the_last_cycles() {
+        let mut statistics = DurationStatistics::default();
+        assert_eq!(statistics.statistics(), TimingStatistics::default());
+
+        for milliseconds in 0..WINDOW_SIZE as u64 + 100 {
+            statistics.update(Duration::from_millis(milliseconds));
+        }
+        let statistics = statistics.statistics();
+
+        assert!((statistics.minimum - 0.1).abs() < 1e-6);
+        assert!((statistics.mean - 0.5995).abs() < 1e-6);
+        assert!((statistics.maximum - 1.099).abs() < 1e-6);
+        assert!((statistics.p99 - 1.089).abs() < 1e-6);
+    }
+}
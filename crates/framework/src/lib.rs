@@ -1,23 +1,37 @@
+mod access_control;
 mod additional_output;
+mod duration_statistics;
 mod future_queue;
 mod historic_databases;
 mod historic_input;
 mod main_output;
+mod node_failure;
 mod panic;
 mod parameters;
 mod perception_databases;
 mod perception_input;
+mod recording_header;
 mod recording_index;
 mod recording_trigger;
+mod recording_writer;
+mod replay_verification;
 
+pub use access_control::{AccessControl, Role};
 pub use additional_output::{should_be_filled, AdditionalOutput};
+pub use duration_statistics::{DurationStatistics, TimingStatistics};
 pub use future_queue::{future_queue, Consumer, Item, Producer, Update, Updates};
 pub use historic_databases::HistoricDatabases;
 pub use historic_input::HistoricInput;
 pub use main_output::MainOutput;
+pub use node_failure::{NodeFailureHandler, NodeFailurePolicy};
 pub use panic::deserialize_not_implemented;
 pub use parameters::Parameters;
 pub use perception_databases::PerceptionDatabases;
 pub use perception_input::PerceptionInput;
+pub use recording_header::{
+    schema_hash, RecordingHeader, RECORDING_FORMAT_VERSION, RECORDING_MAGIC,
+};
 pub use recording_index::{RecordingFrame, RecordingIndex, Timing};
-pub use recording_trigger::RecordingTrigger;
+pub use recording_trigger::{EventTrigger, RecordingTrigger, TriggerCondition};
+pub use recording_writer::{BudgetMode, RecordingStorage, RecordingWriter};
+pub use replay_verification::{find_divergence, Divergence, Tolerances};
@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::{Report, Result};
+use serde::Deserialize;
+
+/// Minimum time between two logged failures of a node
+const LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What happens to the cycler if a node fails
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum NodeFailurePolicy {
+    /// Terminate the cycler, which shuts down the whole process
+    #[default]
+    Abort,
+    /// Keep the main outputs of the last successful cycle of the node
+    ReuseLastMainOutputs,
+    /// Write default main outputs as if the required inputs of the node were missing
+    FallBackToDefaults,
+}
+
+/// Counts the failures of a node and logs them at most once per second
+#[derive(Debug, Default)]
+pub struct NodeFailureHandler {
+    policy: NodeFailurePolicy,
+    number_of_failures: usize,
+    number_of_unlogged_failures: usize,
+    last_log: Option<Instant>,
+}
+
+impl NodeFailureHandler {
+    pub fn new(policy: NodeFailurePolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn policy(&self) -> NodeFailurePolicy {
+        self.policy
+    }
+
+    pub fn number_of_failures(&self) -> usize {
+        self.number_of_failures
+    }
+
+    /// Returns the error if the cycler has to be aborted, otherwise the policy to continue with
+    pub fn handle(&mut self, node_name: &str, error: Report) -> Result<NodeFailurePolicy> {
+        if self.policy == NodeFailurePolicy::Abort {
+            return Err(error);
+        }
+        self.number_of_failures += 1;
+        self.number_of_unlogged_failures += 1;
+        let now = Instant::now();
+        if self.last_log.map_or(true, |last_log| {
+            now.duration_since(last_log) >= LOG_INTERVAL
+        }) {
+            log::error!(
+                "node `{node_name}` failed {} time(s) since the last message ({} in total), continuing with {:?}: {error:?}",
+                self.number_of_unlogged_failures,
+                self.number_of_failures,
+                self.policy,
+            );
+            self.number_of_unlogged_failures = 0;
+            self.last_log = Some(now);
+        }
+        Ok(self.policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::eyre;
+
+    use super::*;
+
+    #[test]
+    fn abort_returns_the_error() {
+        let mut handler = NodeFailureHandler::new(NodeFailurePolicy::Abort);
+
+        assert!(handler.handle("Node", eyre!("failure")).is_err());
+    }
+
+    #[test]
+    fn other_policies_count_failures() {
+        let mut handler = NodeFailureHandler::new(NodeFailurePolicy::FallBackToDefaults);
+
+        for _ in 0..3 {
+            assert_eq!(
+                handler.handle("Node", eyre!("failure")).unwrap(),
+                NodeFailurePolicy::FallBackToDefaults
+            );
+        }
+        assert_eq!(handler.number_of_failures(), 3);
+    }
+}
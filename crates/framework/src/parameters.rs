@@ -2,10 +2,24 @@ use std::{collections::HashMap, path::PathBuf};
 
 use serde::Deserialize;
 
+use crate::{AccessControl, EventTrigger, NodeFailurePolicy, RecordingStorage};
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Parameters {
     pub communication_addresses: Option<String>,
+    #[serde(default)]
+    pub communication_access_control: AccessControl,
     pub recording_intervals: HashMap<String, usize>,
+    #[serde(default)]
+    pub recording_storage: HashMap<String, RecordingStorage>,
+    #[serde(default)]
+    pub recording_triggers: HashMap<String, Vec<EventTrigger>>,
+    #[serde(default)]
+    pub recorded_additional_outputs: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub node_failure_policies: HashMap<String, NodeFailurePolicy>,
+    #[serde(default)]
+    pub verify_main_outputs: HashMap<String, bool>,
     pub hardware_parameters: PathBuf,
     pub parameters_directory: PathBuf,
 }
@@ -0,0 +1,88 @@
+use std::io::{Read, Write};
+
+use bincode::{deserialize_from, serialize_into, serialized_size};
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+
+/// Marks recording files starting with a [`RecordingHeader`], files without it are headerless
+/// recordings of format version 0
+pub const RECORDING_MAGIC: [u8; 8] = *b"HULKSREC";
+pub const RECORDING_FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RecordingHeader {
+    pub format_version: u32,
+    /// Git commit of the source the recording framework was generated from, if known
+    pub commit_hash: Option<String>,
+    pub cycler_instance: String,
+    /// All cycler instances of the framework which wrote the recording
+    pub cycler_instances: Vec<String>,
+    /// Hash over the nodes and the names and types of the recorded data
+    pub schema_hash: u64,
+    /// Whether frames contain the main outputs of cycle nodes, which are recomputed during
+    /// replay and only recorded to verify the replay against them
+    pub has_main_outputs: bool,
+    /// Whether the data of each frame is compressed with zstd
+    pub is_compressed: bool,
+}
+
+impl RecordingHeader {
+    /// Returns the number of written bytes
+    pub fn write_to(&self, mut writer: impl Write) -> Result<u64> {
+        writer
+            .write_all(&RECORDING_MAGIC)
+            .wrap_err("failed to write magic bytes")?;
+        serialize_into(writer, self).wrap_err("failed to serialize recording header")?;
+        let header_length =
+            serialized_size(self).wrap_err("failed to compute size of recording header")?;
+        Ok(RECORDING_MAGIC.len() as u64 + header_length)
+    }
+
+    pub fn read_from(reader: impl Read) -> Result<Self> {
+        deserialize_from(reader).wrap_err("failed to deserialize recording header")
+    }
+
+    /// Fails if frames written with this header cannot be decoded by a framework with the
+    /// `expected` header
+    pub fn ensure_compatible_with(&self, expected: &Self) -> Result<()> {
+        if self.format_version > RECORDING_FORMAT_VERSION {
+            bail!(
+                "recording has format version {}, but only versions up to {RECORDING_FORMAT_VERSION} are supported",
+                self.format_version
+            );
+        }
+        if self.cycler_instance != expected.cycler_instance {
+            bail!(
+                "recording belongs to cycler instance `{}` instead of `{}`",
+                self.cycler_instance,
+                expected.cycler_instance
+            );
+        }
+        if self.schema_hash != expected.schema_hash {
+            bail!(
+                "recording of `{}` was written with a different schema (recorded at commit {}, replaying at commit {})",
+                self.cycler_instance,
+                self.commit_hash.as_deref().unwrap_or("unknown"),
+                expected.commit_hash.as_deref().unwrap_or("unknown")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Hashes the given parts with 64 bit FNV-1a, which is stable across builds and platforms
+pub fn schema_hash(parts: impl IntoIterator<Item = impl AsRef<str>>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    parts.into_iter().fold(OFFSET_BASIS, |hash, part| {
+        // terminate each part to distinguish e.g. ["ab", "c"] from ["a", "bc"]
+        part.as_ref()
+            .bytes()
+            .chain([0])
+            .fold(hash, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+    })
+}
@@ -1,89 +1,100 @@
 use std::{
-    fs::File,
-    io::{self, Read, Seek, SeekFrom},
-    path::Path,
+    fs::{read_dir, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
     time::{Duration, SystemTime},
 };
 
-use bincode::{deserialize_from, Error};
-use color_eyre::eyre::WrapErr;
+use bincode::{deserialize_from, serialize_into, Error};
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use serde::{Deserialize, Serialize};
+
+use crate::recording_header::{RecordingHeader, RECORDING_MAGIC};
 
 #[derive(Debug)]
 pub struct RecordingIndex {
-    file: File,
+    chunks: Vec<RecordingChunk>,
+    header: Option<RecordingHeader>,
     frames: Vec<RecordingFrameMetadata>,
 }
 
+#[derive(Debug)]
+struct RecordingChunk {
+    file: File,
+    is_compressed: bool,
+}
+
 impl RecordingIndex {
-    pub fn read_from(recording_file: impl AsRef<Path>) -> color_eyre::Result<Self> {
-        let file = File::open(&recording_file)
-            .wrap_err_with(|| format!("failed to open {}", recording_file.as_ref().display()))?;
-        Self::collect_frames(file).wrap_err("failed to collect frames")
+    /// Opens a recording and fails if it was not written by a framework compatible with the
+    /// `expected` header. Headerless recordings are accepted without verification.
+    ///
+    /// If the recording file does not exist, its chunks `<stem>.<number>.<extension>` are read
+    /// instead.
+    pub fn read_from(
+        recording_file: impl AsRef<Path>,
+        expected: &RecordingHeader,
+    ) -> color_eyre::Result<Self> {
+        let mut index = Self {
+            chunks: Vec::new(),
+            header: None,
+            frames: Vec::new(),
+        };
+        for path in recording_file_paths(recording_file.as_ref())? {
+            index
+                .add_chunk(&path, expected)
+                .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        }
+        // frames are looked up by binary search
+        index.frames.sort_by_key(|frame| frame.timing.timestamp);
+        Ok(index)
     }
 
-    fn collect_frames(mut recording_file: File) -> color_eyre::Result<Self> {
-        let mut frames = Vec::new();
+    fn add_chunk(&mut self, path: &Path, expected: &RecordingHeader) -> color_eyre::Result<()> {
+        let mut file =
+            File::open(path).wrap_err_with(|| format!("failed to open {}", path.display()))?;
+        let header = read_header(&mut file).wrap_err("failed to read header")?;
+        match &header {
+            Some(header) => header
+                .ensure_compatible_with(expected)
+                .wrap_err("incompatible recording")?,
+            None => eprintln!(
+                "{} has no header, its compatibility cannot be verified",
+                path.display()
+            ),
+        }
 
-        recording_file
-            .seek(SeekFrom::End(0))
-            .wrap_err("failed to seek to end of file")?;
-        let file_length = recording_file
+        let data_start = file
             .stream_position()
-            .wrap_err("failed to get stream position of end of file")?;
-        recording_file.rewind().wrap_err("failed to rewind file")?;
-
-        let mut offset = 0;
-        while offset < file_length {
-            let Some(timestamp) =
-                end_of_file_error_as_option(deserialize_from(&mut recording_file))
-                    .wrap_err("failed to deserialize timestamp")?
-            else {
-                eprintln!("unexpected end of file of recording file while deserializing timestamp");
-                break;
-            };
-            let Some(duration) = end_of_file_error_as_option(deserialize_from(&mut recording_file))
-                .wrap_err("failed to deserialize duration")?
-            else {
-                eprintln!("unexpected end of file of recording file while deserializing duration");
-                break;
-            };
-            let Some(length) = end_of_file_error_as_option(deserialize_from(&mut recording_file))
-                .wrap_err("failed to deserialize data length")?
-            else {
-                eprintln!("unexpected end of file of recording file while deserializing length");
-                break;
-            };
-            let header_length = recording_file
-                .stream_position()
-                .wrap_err("failed to get stream position")?
-                - offset;
-            recording_file
-                .seek(SeekFrom::Current(length as i64))
-                .wrap_err("failed to seek to end of data")?;
-            if offset + header_length + length as u64 > file_length {
-                eprintln!("unexpected end of file of recording file");
-                break;
-            }
-            frames.push(RecordingFrameMetadata {
-                timing: Timing {
-                    timestamp,
-                    duration,
-                },
-                offset: offset.try_into().unwrap(),
-                header_offset: header_length.try_into().unwrap(),
-                length,
-            });
-            offset = recording_file
-                .stream_position()
-                .wrap_err("failed to get stream position")?;
-        }
+            .wrap_err("failed to get stream position of start of data")?;
+        let file_length = file
+            .metadata()
+            .wrap_err("failed to get metadata of recording file")?
+            .len();
+        let frames = match read_index_file(&index_file_path(path), data_start, file_length) {
+            Some(frames) => frames,
+            None => collect_frames(&mut file, data_start, file_length)
+                .wrap_err("failed to collect frames")?,
+        };
 
-        recording_file.rewind().wrap_err("failed to rewind file")?;
+        let chunk = self.chunks.len();
+        self.frames.extend(
+            frames
+                .into_iter()
+                .map(|frame| RecordingFrameMetadata { chunk, ..frame }),
+        );
+        self.chunks.push(RecordingChunk {
+            file,
+            is_compressed: header.as_ref().is_some_and(|header| header.is_compressed),
+        });
+        if self.header.is_none() {
+            self.header = header;
+        }
+        Ok(())
+    }
 
-        Ok(Self {
-            file: recording_file,
-            frames,
-        })
+    /// The header of the (first chunk of the) recording, or `None` for headerless recordings
+    pub fn header(&self) -> Option<&RecordingHeader> {
+        self.header.as_ref()
     }
 
     pub fn number_of_frames(&self) -> usize {
@@ -94,25 +105,31 @@ impl RecordingIndex {
         &mut self,
         timestamp: SystemTime,
     ) -> color_eyre::Result<Option<RecordingFrame>> {
-        let frame = match self
+        let number_of_frames_up_to = self
             .frames
-            .iter()
-            .rev()
-            .find(|frame| frame.timing.timestamp <= timestamp)
-        {
-            Some(frame) => frame,
-            None => return Ok(None),
+            .partition_point(|frame| frame.timing.timestamp <= timestamp);
+        let Some(frame) = number_of_frames_up_to
+            .checked_sub(1)
+            .map(|index| &self.frames[index])
+        else {
+            return Ok(None);
         };
-        self.file
+        let chunk = &mut self.chunks[frame.chunk];
+        chunk
+            .file
             .seek(SeekFrom::Start(
                 (frame.offset + frame.header_offset).try_into().unwrap(),
             ))
             .wrap_err("failed to seek to frame")?;
         let mut data = Vec::new();
         data.resize_with(frame.length, Default::default);
-        self.file
+        chunk
+            .file
             .read_exact(&mut data)
             .wrap_err("failed to read from recording file")?;
+        if chunk.is_compressed {
+            data = zstd::decode_all(data.as_slice()).wrap_err("failed to decompress frame")?;
+        }
         Ok(Some(RecordingFrame {
             timing: frame.timing,
             data,
@@ -132,8 +149,206 @@ impl RecordingIndex {
     }
 }
 
+/// Collects the metadata of written frames to store it as index file next to the recording
 #[derive(Debug)]
+pub(crate) struct RecordingIndexWriter {
+    index: IndexFile,
+}
+
+impl RecordingIndexWriter {
+    /// `data_start` is the offset of the first frame, i.e. the length of the recording header
+    pub fn new(data_start: u64) -> Self {
+        Self {
+            index: IndexFile {
+                data_start,
+                recording_length: data_start,
+                frames: Vec::new(),
+            },
+        }
+    }
+
+    pub fn add_frame(&mut self, timing: Timing, header_length: usize, length: usize) {
+        self.index.frames.push(RecordingFrameMetadata {
+            timing,
+            offset: self.index.recording_length.try_into().unwrap(),
+            header_offset: header_length,
+            length,
+            chunk: 0,
+        });
+        self.index.recording_length += (header_length + length) as u64;
+    }
+
+    /// Length of the recording file including all added frames
+    pub fn recording_length(&self) -> u64 {
+        self.index.recording_length
+    }
+
+    pub fn number_of_frames(&self) -> usize {
+        self.index.frames.len()
+    }
+
+    /// Writes the index file belonging to the given recording file
+    pub fn write(&self, recording_file: impl AsRef<Path>) -> color_eyre::Result<()> {
+        let path = index_file_path(recording_file.as_ref());
+        let file =
+            File::create(&path).wrap_err_with(|| format!("failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serialize_into(&mut writer, &self.index).wrap_err("failed to serialize index")?;
+        writer.flush().wrap_err("failed to write index")
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct IndexFile {
+    data_start: u64,
+    /// Length of the recording file the index was written for
+    recording_length: u64,
+    frames: Vec<RecordingFrameMetadata>,
+}
+
+/// Returns the recording file if it exists, otherwise its chunks ordered by their number
+fn recording_file_paths(recording_file: &Path) -> color_eyre::Result<Vec<PathBuf>> {
+    if recording_file.exists() {
+        return Ok(vec![recording_file.to_path_buf()]);
+    }
+    let directory = recording_file
+        .parent()
+        .ok_or_else(|| eyre!("{} has no parent directory", recording_file.display()))?;
+    let (Some(stem), Some(extension)) = (
+        recording_file.file_stem().and_then(|stem| stem.to_str()),
+        recording_file
+            .extension()
+            .and_then(|extension| extension.to_str()),
+    ) else {
+        bail!(
+            "{} is no valid recording file name",
+            recording_file.display()
+        );
+    };
+
+    let mut chunks: Vec<(usize, PathBuf)> = read_dir(directory)
+        .wrap_err_with(|| format!("failed to read directory {}", directory.display()))?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let number = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix(stem)?
+                .strip_prefix('.')?
+                .strip_suffix(extension)?
+                .strip_suffix('.')?
+                .parse()
+                .ok()?;
+            Some((number, path))
+        })
+        .collect();
+    if chunks.is_empty() {
+        bail!(
+            "neither {} nor any of its chunks exist",
+            recording_file.display()
+        );
+    }
+    chunks.sort();
+    Ok(chunks.into_iter().map(|(_, path)| path).collect())
+}
+
+pub(crate) fn index_file_path(recording_file: &Path) -> PathBuf {
+    recording_file.with_extension("index")
+}
+
+/// Reads the index file if it exists and matches the recording, i.e. the recording was not
+/// truncated or extended after the index was written
+fn read_index_file(
+    path: &Path,
+    data_start: u64,
+    recording_length: u64,
+) -> Option<Vec<RecordingFrameMetadata>> {
+    let file = File::open(path).ok()?;
+    let index: IndexFile = match deserialize_from(BufReader::new(file)) {
+        Ok(index) => index,
+        Err(error) => {
+            eprintln!(
+                "failed to read {}, rebuilding index: {error}",
+                path.display()
+            );
+            return None;
+        }
+    };
+    if index.data_start != data_start || index.recording_length != recording_length {
+        eprintln!(
+            "{} does not match the recording, rebuilding index",
+            path.display()
+        );
+        return None;
+    }
+    Some(index.frames)
+}
+
+/// Collects the frames by reading the whole recording from the start of the data
+fn collect_frames(
+    recording_file: &mut File,
+    data_start: u64,
+    file_length: u64,
+) -> color_eyre::Result<Vec<RecordingFrameMetadata>> {
+    let mut frames = Vec::new();
+    recording_file
+        .seek(SeekFrom::Start(data_start))
+        .wrap_err("failed to seek to start of data")?;
+
+    let mut offset = data_start;
+    while offset < file_length {
+        let Some(timestamp) = end_of_file_error_as_option(deserialize_from(&mut *recording_file))
+            .wrap_err("failed to deserialize timestamp")?
+        else {
+            eprintln!("unexpected end of file of recording file while deserializing timestamp");
+            break;
+        };
+        let Some(duration) = end_of_file_error_as_option(deserialize_from(&mut *recording_file))
+            .wrap_err("failed to deserialize duration")?
+        else {
+            eprintln!("unexpected end of file of recording file while deserializing duration");
+            break;
+        };
+        let Some(length) = end_of_file_error_as_option(deserialize_from(&mut *recording_file))
+            .wrap_err("failed to deserialize data length")?
+        else {
+            eprintln!("unexpected end of file of recording file while deserializing length");
+            break;
+        };
+        let header_length = recording_file
+            .stream_position()
+            .wrap_err("failed to get stream position")?
+            - offset;
+        recording_file
+            .seek(SeekFrom::Current(length as i64))
+            .wrap_err("failed to seek to end of data")?;
+        if offset + header_length + length as u64 > file_length {
+            eprintln!("unexpected end of file of recording file");
+            break;
+        }
+        frames.push(RecordingFrameMetadata {
+            timing: Timing {
+                timestamp,
+                duration,
+            },
+            offset: offset.try_into().unwrap(),
+            header_offset: header_length.try_into().unwrap(),
+            length,
+            chunk: 0,
+        });
+        offset = recording_file
+            .stream_position()
+            .wrap_err("failed to get stream position")?;
+    }
+
+    Ok(frames)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 struct RecordingFrameMetadata {
+    /// Index of the chunk file containing the frame
+    #[serde(skip)]
+    chunk: usize,
     timing: Timing,
     offset: usize,
     header_offset: usize,
@@ -146,12 +361,27 @@ pub struct RecordingFrame {
     pub data: Vec<u8>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub struct Timing {
     pub timestamp: SystemTime,
     pub duration: Duration,
 }
 
+/// Reads the header if the file starts with one, otherwise rewinds to the start of the file
+fn read_header(file: &mut File) -> color_eyre::Result<Option<RecordingHeader>> {
+    let mut magic = [0; RECORDING_MAGIC.len()];
+    let has_magic = match file.read_exact(&mut magic) {
+        Ok(()) => magic == RECORDING_MAGIC,
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => false,
+        Err(error) => return Err(error).wrap_err("failed to read magic bytes"),
+    };
+    if !has_magic {
+        file.rewind().wrap_err("failed to rewind file")?;
+        return Ok(None);
+    }
+    RecordingHeader::read_from(file).map(Some)
+}
+
 fn end_of_file_error_as_option<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
     result.map(Some).or_else(|error| {
         if let bincode::ErrorKind::Io(ref error) = *error {
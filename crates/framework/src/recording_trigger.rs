@@ -1,14 +1,116 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    mem::take,
+    time::{Duration, Instant, SystemTime},
+};
+
+use color_eyre::{eyre::bail, Result};
+use path_serde::PathSerialize;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::Timing;
+
+/// Minimum time between two logged drops of recording frames
+const LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum TriggerCondition {
+    /// The output at `path` of the cycler database, e.g. `main_outputs.primary_state`, equals
+    /// `value` for at least `duration` seconds. A string `value` also matches enum variants with
+    /// fields by their name, e.g. `"Falling"`.
+    Output {
+        path: String,
+        value: Value,
+        #[serde(default)]
+        duration: f32,
+    },
+    /// The cycle took longer than `maximum_duration` seconds
+    CycleOverrun { maximum_duration: f32 },
+    /// A node of the cycler failed, either terminating the cycler or handled by its failure policy
+    NodeError,
+}
+
+/// Records the frames around an event, independent of the recording interval
+#[derive(Clone, Debug, Deserialize)]
+pub struct EventTrigger {
+    pub condition: TriggerCondition,
+    /// Seconds of frames before the event to record
+    #[serde(default)]
+    pub pre_trigger_duration: f32,
+    /// Seconds of frames after the event to record
+    #[serde(default)]
+    pub post_trigger_duration: f32,
+}
+
+impl EventTrigger {
+    fn pre_trigger_duration(&self) -> Duration {
+        Duration::from_secs_f32(self.pre_trigger_duration.max(0.0))
+    }
+
+    fn post_trigger_duration(&self) -> Duration {
+        Duration::from_secs_f32(self.post_trigger_duration.max(0.0))
+    }
+}
+
+struct Event {
+    trigger: EventTrigger,
+    condition_met_since: Option<SystemTime>,
+    has_fired: bool,
+}
+
 pub struct RecordingTrigger {
     recording_interval: usize,
     counter: usize,
+    events: Vec<Event>,
+    history: VecDeque<(Timing, Vec<u8>)>,
+    history_duration: Duration,
+    recording_until: Option<SystemTime>,
+    has_node_error: bool,
+    number_of_dropped_frames: usize,
+    number_of_unlogged_dropped_frames: usize,
+    last_log: Option<Instant>,
 }
 
 impl RecordingTrigger {
-    pub fn new(recording_interval: usize) -> Self {
-        Self {
+    /// Fails if an `Output` trigger refers to a path which is not in the `fields` of the database,
+    /// see `PathIntrospect`
+    pub fn new(
+        recording_interval: usize,
+        event_triggers: Vec<EventTrigger>,
+        fields: &HashSet<String>,
+    ) -> Result<Self> {
+        for trigger in &event_triggers {
+            if let TriggerCondition::Output { path, .. } = &trigger.condition {
+                if !fields.contains(path) {
+                    bail!("recording trigger output `{path}` does not exist");
+                }
+            }
+        }
+        let history_duration = event_triggers
+            .iter()
+            .map(EventTrigger::pre_trigger_duration)
+            .max()
+            .unwrap_or_default();
+        Ok(Self {
             recording_interval,
             counter: 0,
-        }
+            events: event_triggers
+                .into_iter()
+                .map(|trigger| Event {
+                    trigger,
+                    condition_met_since: None,
+                    has_fired: false,
+                })
+                .collect(),
+            history: VecDeque::new(),
+            history_duration,
+            recording_until: None,
+            has_node_error: false,
+            number_of_dropped_frames: 0,
+            number_of_unlogged_dropped_frames: 0,
+            last_log: None,
+        })
     }
 
     pub fn update(&mut self) {
@@ -17,7 +119,398 @@ impl RecordingTrigger {
         }
     }
 
+    /// Whether the current cycle is recorded because of the recording interval
     pub fn should_record(&self) -> bool {
         self.recording_interval != 0 && self.counter == 0
     }
+
+    /// Whether every cycle needs to be serialized to be able to record the history before an event
+    pub fn has_event_triggers(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    /// Evaluates the event triggers after a cycle and returns the frames to be written, i.e. the
+    /// history before fired events and the frame of this cycle if it is recorded
+    pub fn record_frame(
+        &mut self,
+        timing: Timing,
+        data: Vec<u8>,
+        is_recorded_by_interval: bool,
+        database: &impl PathSerialize,
+    ) -> Vec<(Timing, Vec<u8>)> {
+        let now = timing.timestamp;
+        let mut fired_pre_trigger_duration = None;
+        let has_node_error = take(&mut self.has_node_error);
+        for event in &mut self.events {
+            let is_met = match &event.trigger.condition {
+                // paths are checked on construction, the output may still be absent, e.g. within
+                // an optional value that is `None`
+                TriggerCondition::Output { path, value, .. } => database
+                    .serialize_path(path, serde_json::value::Serializer)
+                    .is_ok_and(|output| matches(&output, value)),
+                TriggerCondition::CycleOverrun { maximum_duration } => {
+                    timing.duration.as_secs_f32() > *maximum_duration
+                }
+                TriggerCondition::NodeError => has_node_error,
+            };
+            if event.fire_if_met(is_met, now) {
+                fired_pre_trigger_duration =
+                    fired_pre_trigger_duration.max(Some(event.trigger.pre_trigger_duration()));
+                self.recording_until = self
+                    .recording_until
+                    .max(Some(now + event.trigger.post_trigger_duration()));
+            }
+        }
+
+        let mut frames = match fired_pre_trigger_duration {
+            Some(pre_trigger_duration) => self.take_history(now, pre_trigger_duration),
+            None => Vec::new(),
+        };
+        let is_recorded_by_event = self.recording_until.is_some_and(|until| now <= until);
+        if is_recorded_by_interval || is_recorded_by_event {
+            frames.push((timing, data));
+        } else if self.has_event_triggers() {
+            self.history.push_back((timing, data));
+        }
+        while self.history.front().is_some_and(|(timing, _data)| {
+            now.duration_since(timing.timestamp).unwrap_or_default() > self.history_duration
+        }) {
+            self.history.pop_front();
+        }
+        frames
+    }
+
+    /// Returns the history to be written if a node of the cycler failed, if the cycler continues,
+    /// the next [`Self::record_frame`] records the failed cycle and the frames after it
+    pub fn record_node_error(&mut self) -> Vec<(Timing, Vec<u8>)> {
+        self.has_node_error = true;
+        let Some(pre_trigger_duration) = self
+            .events
+            .iter()
+            .filter(|event| matches!(event.trigger.condition, TriggerCondition::NodeError))
+            .map(|event| event.trigger.pre_trigger_duration())
+            .max()
+        else {
+            return Vec::new();
+        };
+        let Some(now) = self.history.back().map(|(timing, _data)| timing.timestamp) else {
+            return Vec::new();
+        };
+        self.take_history(now, pre_trigger_duration)
+    }
+
+    pub fn number_of_dropped_frames(&self) -> usize {
+        self.number_of_dropped_frames
+    }
+
+    /// Counts frames which were dropped because the recording thread could not keep up and logs
+    /// them at most once per second
+    pub fn record_dropped_frames(&mut self, instance_name: &str, number_of_frames: usize) {
+        self.number_of_dropped_frames += number_of_frames;
+        self.number_of_unlogged_dropped_frames += number_of_frames;
+        let now = Instant::now();
+        if self.last_log.map_or(true, |last_log| {
+            now.duration_since(last_log) >= LOG_INTERVAL
+        }) {
+            log::warn!(
+                "dropped {} recording frame(s) of {instance_name} since the last message ({} in total), the recording thread cannot keep up",
+                self.number_of_unlogged_dropped_frames,
+                self.number_of_dropped_frames,
+            );
+            self.number_of_unlogged_dropped_frames = 0;
+            self.last_log = Some(now);
+        }
+    }
+
+    fn take_history(
+        &mut self,
+        now: SystemTime,
+        pre_trigger_duration: Duration,
+    ) -> Vec<(Timing, Vec<u8>)> {
+        let first_recorded = self.history.partition_point(|(timing, _data)| {
+            now.duration_since(timing.timestamp).unwrap_or_default() > pre_trigger_duration
+        });
+        self.history.drain(first_recorded..).collect()
+    }
+}
+
+impl Event {
+    /// Returns whether the event fires, i.e. its condition just started to hold long enough
+    fn fire_if_met(&mut self, is_met: bool, now: SystemTime) -> bool {
+        if !is_met {
+            self.condition_met_since = None;
+            self.has_fired = false;
+            return false;
+        }
+        let since = *self.condition_met_since.get_or_insert(now);
+        let minimum_duration = match self.trigger.condition {
+            TriggerCondition::Output { duration, .. } => Duration::from_secs_f32(duration.max(0.0)),
+            _ => Duration::ZERO,
+        };
+        if self.has_fired || now.duration_since(since).unwrap_or_default() < minimum_duration {
+            return false;
+        }
+        self.has_fired = true;
+        true
+    }
+}
+
+fn matches(output: &Value, value: &Value) -> bool {
+    match (output, value) {
+        (Value::Object(fields), Value::String(variant)) if fields.len() == 1 => {
+            fields.contains_key(variant)
+        }
+        _ => output == value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use path_serde::serialize;
+    use serde::{Serialize, Serializer};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Database {
+        primary_state: Value,
+    }
+
+    impl PathSerialize for Database {
+        fn serialize_path<S>(
+            &self,
+            path: &str,
+            serializer: S,
+        ) -> Result<S::Ok, serialize::Error<S::Error>>
+        where
+            S: Serializer,
+        {
+            match path {
+                "primary_state" => self
+                    .primary_state
+                    .serialize(serializer)
+                    .map_err(serialize::Error::SerializationFailed),
+                _ => Err(serialize::Error::PathDoesNotExist {
+                    path: path.to_string(),
+                }),
+            }
+        }
+    }
+
+    /// Fails to serialize every path, like an output within an optional value that is `None`
+    struct AbsentDatabase;
+
+    impl PathSerialize for AbsentDatabase {
+        fn serialize_path<S>(
+            &self,
+            path: &str,
+            _serializer: S,
+        ) -> Result<S::Ok, serialize::Error<S::Error>>
+        where
+            S: Serializer,
+        {
+            Err(serialize::Error::PathDoesNotExist {
+                path: path.to_string(),
+            })
+        }
+    }
+
+    fn recording_trigger(
+        recording_interval: usize,
+        event_triggers: Vec<EventTrigger>,
+    ) -> RecordingTrigger {
+        let fields = HashSet::from(["primary_state".to_string()]);
+        RecordingTrigger::new(recording_interval, event_triggers, &fields).unwrap()
+    }
+
+    fn timing(seconds: u64, duration: f32) -> Timing {
+        Timing {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+            duration: Duration::from_secs_f32(duration),
+        }
+    }
+
+    fn recorded_seconds(frames: &[(Timing, Vec<u8>)]) -> Vec<u64> {
+        frames
+            .iter()
+            .map(|(timing, _data)| {
+                timing
+                    .timestamp
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interval_records_every_nth_cycle() {
+        let mut trigger = recording_trigger(3, Vec::new());
+        let recorded: Vec<_> = (0..7)
+            .map(|_| {
+                let should_record = trigger.should_record();
+                trigger.update();
+                should_record
+            })
+            .collect();
+
+        assert_eq!(recorded, [true, false, false, true, false, false, true]);
+        assert!(!trigger.has_event_triggers());
+    }
+
+    #[test]
+    fn output_event_records_history_and_following_frames() {
+        let mut trigger = recording_trigger(
+            0,
+            vec![EventTrigger {
+                condition: TriggerCondition::Output {
+                    path: "primary_state".to_string(),
+                    value: Value::from("Penalized"),
+                    duration: 0.0,
+                },
+                pre_trigger_duration: 2.0,
+                post_trigger_duration: 1.0,
+            }],
+        );
+        let mut database = Database {
+            primary_state: Value::from("Playing"),
+        };
+        let mut recorded = Vec::new();
+        for second in 0..10 {
+            if second == 5 {
+                database.primary_state = Value::from("Penalized");
+            }
+            recorded.extend(trigger.record_frame(timing(second, 0.01), vec![], false, &database));
+        }
+
+        assert_eq!(recorded_seconds(&recorded), [3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn output_event_matches_enum_variants_after_duration() {
+        let mut trigger = recording_trigger(
+            0,
+            vec![EventTrigger {
+                condition: TriggerCondition::Output {
+                    path: "primary_state".to_string(),
+                    value: Value::from("Falling"),
+                    duration: 2.0,
+                },
+                pre_trigger_duration: 0.0,
+                post_trigger_duration: 0.0,
+            }],
+        );
+        let database = Database {
+            primary_state: serde_json::json!({ "Falling": { "direction": "Forward" } }),
+        };
+        let recorded: Vec<_> = (0..5)
+            .flat_map(|second| trigger.record_frame(timing(second, 0.01), vec![], false, &database))
+            .collect();
+
+        assert_eq!(recorded_seconds(&recorded), [2]);
+    }
+
+    #[test]
+    fn cycle_overrun_and_node_error_record_history() {
+        let mut trigger = recording_trigger(
+            0,
+            vec![
+                EventTrigger {
+                    condition: TriggerCondition::CycleOverrun {
+                        maximum_duration: 0.1,
+                    },
+                    pre_trigger_duration: 1.0,
+                    post_trigger_duration: 0.0,
+                },
+                EventTrigger {
+                    condition: TriggerCondition::NodeError,
+                    pre_trigger_duration: 10.0,
+                    post_trigger_duration: 0.0,
+                },
+            ],
+        );
+        let database = Database::default();
+        let mut recorded = Vec::new();
+        for second in 0..8 {
+            let duration = if second == 4 { 0.2 } else { 0.01 };
+            recorded.extend(trigger.record_frame(
+                timing(second, duration),
+                vec![],
+                false,
+                &database,
+            ));
+        }
+        assert_eq!(recorded_seconds(&recorded), [3, 4]);
+
+        let history = trigger.record_node_error();
+        assert_eq!(recorded_seconds(&history), [0, 1, 2, 5, 6, 7]);
+    }
+
+    #[test]
+    fn handled_node_error_records_history_and_following_frames() {
+        let mut trigger = recording_trigger(
+            0,
+            vec![EventTrigger {
+                condition: TriggerCondition::NodeError,
+                pre_trigger_duration: 2.0,
+                post_trigger_duration: 1.0,
+            }],
+        );
+        let database = Database::default();
+        let mut recorded = Vec::new();
+        for second in 0..10 {
+            if second == 5 {
+                recorded.extend(trigger.record_node_error());
+            }
+            recorded.extend(trigger.record_frame(timing(second, 0.01), vec![], false, &database));
+        }
+
+        assert_eq!(recorded_seconds(&recorded), [2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn dropped_frames_are_counted() {
+        let mut trigger = recording_trigger(1, Vec::new());
+
+        trigger.record_dropped_frames("Control", 3);
+        trigger.record_dropped_frames("Control", 1);
+
+        assert_eq!(trigger.number_of_dropped_frames(), 4);
+    }
+
+    #[test]
+    fn unknown_output_fails_on_construction() {
+        let fields = HashSet::from(["primary_state".to_string()]);
+        let event_triggers = vec![EventTrigger {
+            condition: TriggerCondition::Output {
+                path: "fall_state".to_string(),
+                value: Value::from("Fallen"),
+                duration: 0.0,
+            },
+            pre_trigger_duration: 0.0,
+            post_trigger_duration: 0.0,
+        }];
+
+        assert!(RecordingTrigger::new(0, event_triggers, &fields).is_err());
+    }
+
+    #[test]
+    fn absent_output_is_not_met() {
+        let mut trigger = recording_trigger(
+            0,
+            vec![EventTrigger {
+                condition: TriggerCondition::Output {
+                    path: "primary_state".to_string(),
+                    value: Value::from("Fallen"),
+                    duration: 0.0,
+                },
+                pre_trigger_duration: 0.0,
+                post_trigger_duration: 0.0,
+            }],
+        );
+
+        assert!(trigger
+            .record_frame(timing(0, 0.01), vec![], false, &AbsentDatabase)
+            .is_empty());
+    }
 }
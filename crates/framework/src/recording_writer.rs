@@ -0,0 +1,293 @@
+use std::{
+    collections::VecDeque,
+    fs::{remove_file, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bincode::serialize_into;
+use color_eyre::{eyre::WrapErr, Result};
+use serde::Deserialize;
+
+use crate::{
+    recording_header::RecordingHeader,
+    recording_index::{index_file_path, RecordingIndexWriter},
+    Timing,
+};
+
+/// Chunk size of chunked recordings without disk budget
+const DEFAULT_CHUNK_SIZE: u64 = 100_000_000;
+/// Number of chunks a disk budget is divided into if no chunk size is given
+const DEFAULT_CHUNKS_PER_BUDGET: u64 = 10;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum BudgetMode {
+    /// Write a single file and stop recording when the disk budget is exhausted
+    #[default]
+    Stop,
+    /// Split the recording into chunks and stop recording when the disk budget is exhausted
+    Rotate,
+    /// Split the recording into chunks and delete the oldest chunks to stay within the disk
+    /// budget and ring buffer duration
+    RingBuffer,
+}
+
+/// How the recording of a cycler instance is stored on disk
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RecordingStorage {
+    /// zstd compression level of each frame, frames are stored uncompressed if unset
+    pub compression_level: Option<i32>,
+    /// Maximum number of bytes of all recording files of the cycler instance
+    pub disk_budget: Option<u64>,
+    pub mode: BudgetMode,
+    /// Number of bytes after which a new chunk is started
+    pub chunk_size: Option<u64>,
+    /// Seconds of recording to keep in ring buffer mode
+    pub ring_buffer_duration: Option<f32>,
+}
+
+impl RecordingStorage {
+    fn is_chunked(&self) -> bool {
+        self.mode != BudgetMode::Stop
+    }
+
+    fn chunk_size(&self) -> u64 {
+        self.chunk_size.unwrap_or_else(|| {
+            self.disk_budget.map_or(DEFAULT_CHUNK_SIZE, |budget| {
+                budget / DEFAULT_CHUNKS_PER_BUDGET
+            })
+        })
+    }
+}
+
+struct OpenChunk {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    index: RecordingIndexWriter,
+}
+
+struct ClosedChunk {
+    path: PathBuf,
+    size: u64,
+    last_timestamp: Option<SystemTime>,
+}
+
+/// Writes the recording frames of one cycler instance into `<instance>.bincode` or its chunks
+/// `<instance>.<number>.bincode` together with their index files.
+pub struct RecordingWriter {
+    directory: PathBuf,
+    cycler_instance: String,
+    header: RecordingHeader,
+    storage: RecordingStorage,
+    current_chunk: OpenChunk,
+    closed_chunks: VecDeque<ClosedChunk>,
+    closed_chunks_size: u64,
+    next_chunk_number: usize,
+    last_timestamp: Option<SystemTime>,
+    is_budget_exhausted: bool,
+}
+
+impl RecordingWriter {
+    pub fn new(
+        directory: impl AsRef<Path>,
+        cycler_instance: &str,
+        mut header: RecordingHeader,
+        storage: RecordingStorage,
+    ) -> Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(&directory).wrap_err("failed to create logs folder")?;
+        header.is_compressed = storage.compression_level.is_some();
+        let current_chunk = open_chunk(
+            &chunk_path(&directory, cycler_instance, &storage, 0),
+            &header,
+        )?;
+
+        Ok(Self {
+            directory,
+            cycler_instance: cycler_instance.to_string(),
+            header,
+            storage,
+            current_chunk,
+            closed_chunks: VecDeque::new(),
+            closed_chunks_size: 0,
+            next_chunk_number: 1,
+            last_timestamp: None,
+            is_budget_exhausted: false,
+        })
+    }
+
+    pub fn write_frame(&mut self, timing: Timing, data: &[u8]) -> Result<()> {
+        if self.is_budget_exhausted {
+            return Ok(());
+        }
+        let compressed_data;
+        let data = match self.storage.compression_level {
+            Some(level) => {
+                compressed_data =
+                    zstd::bulk::compress(data, level).wrap_err("failed to compress frame")?;
+                compressed_data.as_slice()
+            }
+            None => data,
+        };
+        let mut frame_header = Vec::new();
+        serialize_into(&mut frame_header, &timing.timestamp)
+            .wrap_err("failed to serialize timestamp")?;
+        serialize_into(&mut frame_header, &timing.duration)
+            .wrap_err("failed to serialize duration")?;
+        serialize_into(&mut frame_header, &data.len())
+            .wrap_err("failed to serialize data length")?;
+        let frame_size = (frame_header.len() + data.len()) as u64;
+
+        let is_chunk_full =
+            self.current_chunk.index.recording_length() + frame_size > self.storage.chunk_size();
+        if self.storage.is_chunked()
+            && is_chunk_full
+            && self.current_chunk.index.number_of_frames() > 0
+        {
+            self.rotate()?;
+        }
+        if self.storage.mode == BudgetMode::RingBuffer {
+            self.delete_expired_chunks(timing.timestamp, frame_size)?;
+        }
+        if self
+            .storage
+            .disk_budget
+            .is_some_and(|budget| self.size() + frame_size > budget)
+        {
+            if self.storage.mode == BudgetMode::RingBuffer {
+                eprintln!(
+                    "frame of {} exceeds the disk budget, skipping it",
+                    self.cycler_instance
+                );
+                return Ok(());
+            }
+            eprintln!(
+                "disk budget of {} exhausted, stopping recording",
+                self.cycler_instance
+            );
+            self.is_budget_exhausted = true;
+            return Ok(());
+        }
+
+        let writer = &mut self.current_chunk.writer;
+        writer
+            .write_all(&frame_header)
+            .wrap_err("failed to write frame header")?;
+        writer.write_all(data).wrap_err("failed to write frame")?;
+        self.current_chunk
+            .index
+            .add_frame(timing, frame_header.len(), data.len());
+        self.last_timestamp = Some(timing.timestamp);
+        Ok(())
+    }
+
+    /// Flushes the recording and writes the index of the current chunk
+    pub fn finish(mut self) -> Result<()> {
+        finish_chunk(&mut self.current_chunk)
+    }
+
+    fn size(&self) -> u64 {
+        self.closed_chunks_size + self.current_chunk.index.recording_length()
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        let path = chunk_path(
+            &self.directory,
+            &self.cycler_instance,
+            &self.storage,
+            self.next_chunk_number,
+        );
+        self.next_chunk_number += 1;
+        let mut closed_chunk =
+            std::mem::replace(&mut self.current_chunk, open_chunk(&path, &self.header)?);
+        finish_chunk(&mut closed_chunk)?;
+        let size = closed_chunk.index.recording_length();
+        self.closed_chunks_size += size;
+        self.closed_chunks.push_back(ClosedChunk {
+            path: closed_chunk.path,
+            size,
+            last_timestamp: self.last_timestamp,
+        });
+        Ok(())
+    }
+
+    fn delete_expired_chunks(&mut self, now: SystemTime, frame_size: u64) -> Result<()> {
+        let maximum_age = self
+            .storage
+            .ring_buffer_duration
+            .map(|duration| Duration::from_secs_f32(duration.max(0.0)));
+        loop {
+            let exceeds_budget = self
+                .storage
+                .disk_budget
+                .is_some_and(|budget| self.size() + frame_size > budget);
+            let oldest_is_expired = self.closed_chunks.front().is_some_and(|chunk| {
+                chunk.last_timestamp.zip(maximum_age).is_some_and(
+                    |(last_timestamp, maximum_age)| {
+                        now.duration_since(last_timestamp).unwrap_or_default() > maximum_age
+                    },
+                )
+            });
+            if !exceeds_budget && !oldest_is_expired {
+                return Ok(());
+            }
+            if self.closed_chunks.is_empty() {
+                if self.current_chunk.index.number_of_frames() == 0 {
+                    // a single frame exceeds the budget
+                    return Ok(());
+                }
+                self.rotate()?;
+            }
+            let chunk = self
+                .closed_chunks
+                .pop_front()
+                .expect("there should be a closed chunk after rotating");
+            self.closed_chunks_size -= chunk.size;
+            remove_file(&chunk.path)
+                .wrap_err_with(|| format!("failed to delete {}", chunk.path.display()))?;
+            // the index may not exist if writing it failed
+            let _ = remove_file(index_file_path(&chunk.path));
+        }
+    }
+}
+
+fn chunk_path(
+    directory: &Path,
+    cycler_instance: &str,
+    storage: &RecordingStorage,
+    number: usize,
+) -> PathBuf {
+    if storage.is_chunked() {
+        directory.join(format!("{cycler_instance}.{number}.bincode"))
+    } else {
+        directory.join(format!("{cycler_instance}.bincode"))
+    }
+}
+
+fn open_chunk(path: &Path, header: &RecordingHeader) -> Result<OpenChunk> {
+    let file =
+        File::create(path).wrap_err_with(|| format!("failed to create {}", path.display()))?;
+    // TODO: possible optimization: buffer size
+    let mut writer = BufWriter::new(file);
+    let data_start = header
+        .write_to(&mut writer)
+        .wrap_err("failed to write recording header")?;
+    Ok(OpenChunk {
+        path: path.to_path_buf(),
+        writer,
+        index: RecordingIndexWriter::new(data_start),
+    })
+}
+
+fn finish_chunk(chunk: &mut OpenChunk) -> Result<()> {
+    chunk
+        .writer
+        .flush()
+        .wrap_err("failed to flush recording file")?;
+    chunk
+        .index
+        .write(&chunk.path)
+        .wrap_err("failed to write recording index")
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use color_eyre::{eyre::WrapErr, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Maximum absolute deviations of numbers in recomputed main outputs from the recorded ones
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tolerances {
+    #[serde(default = "default_tolerance")]
+    pub default: f64,
+    /// Tolerances per main output, keyed by `<CyclerInstance>.<main_output>`
+    #[serde(default)]
+    pub main_outputs: HashMap<String, f64>,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self {
+            default: default_tolerance(),
+            main_outputs: HashMap::new(),
+        }
+    }
+}
+
+fn default_tolerance() -> f64 {
+    1e-5
+}
+
+impl Tolerances {
+    pub fn get(&self, cycler_instance: &str, main_output: &str) -> f64 {
+        self.main_outputs
+            .get(&format!("{cycler_instance}.{main_output}"))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// A recomputed main output which differs from the recorded one
+#[derive(Clone, Debug, Serialize)]
+pub struct Divergence {
+    pub node: String,
+    pub main_output: String,
+    /// Path of the first differing value within the main output
+    pub path: String,
+    pub recorded: Value,
+    pub recomputed: Value,
+}
+
+/// Compares a recomputed main output with the recorded one, numbers may differ by at most the
+/// tolerance
+pub fn find_divergence<T: Serialize>(
+    node: &str,
+    main_output: &str,
+    recorded: &T,
+    recomputed: &T,
+    tolerance: f64,
+) -> Result<Option<Divergence>> {
+    let recorded = serde_json::to_value(recorded)
+        .wrap_err_with(|| format!("failed to serialize recorded `{main_output}`"))?;
+    let recomputed = serde_json::to_value(recomputed)
+        .wrap_err_with(|| format!("failed to serialize recomputed `{main_output}`"))?;
+    Ok(
+        find_differing_value(&recorded, &recomputed, main_output, tolerance).map(
+            |(path, recorded, recomputed)| Divergence {
+                node: node.to_string(),
+                main_output: main_output.to_string(),
+                path,
+                recorded: recorded.clone(),
+                recomputed: recomputed.clone(),
+            },
+        ),
+    )
+}
+
+static NULL: Value = Value::Null;
+
+fn find_differing_value<'value>(
+    recorded: &'value Value,
+    recomputed: &'value Value,
+    path: &str,
+    tolerance: f64,
+) -> Option<(String, &'value Value, &'value Value)> {
+    match (recorded, recomputed) {
+        (Value::Number(recorded_number), Value::Number(recomputed_number)) => {
+            let recorded_number = recorded_number.as_f64().unwrap_or(f64::NAN);
+            let recomputed_number = recomputed_number.as_f64().unwrap_or(f64::NAN);
+            let difference = (recorded_number - recomputed_number).abs();
+            (difference > tolerance).then(|| (path.to_string(), recorded, recomputed))
+        }
+        (Value::Array(recorded_items), Value::Array(recomputed_items))
+            if recorded_items.len() == recomputed_items.len() =>
+        {
+            recorded_items
+                .iter()
+                .zip(recomputed_items)
+                .enumerate()
+                .find_map(|(index, (recorded, recomputed))| {
+                    find_differing_value(
+                        recorded,
+                        recomputed,
+                        &format!("{path}.{index}"),
+                        tolerance,
+                    )
+                })
+        }
+        (Value::Object(recorded_fields), Value::Object(recomputed_fields))
+            if recorded_fields.len() == recomputed_fields.len() =>
+        {
+            recorded_fields.iter().find_map(|(key, recorded)| {
+                let path = format!("{path}.{key}");
+                match recomputed_fields.get(key) {
+                    Some(recomputed) => {
+                        find_differing_value(recorded, recomputed, &path, tolerance)
+                    }
+                    None => Some((path, recorded, &NULL)),
+                }
+            })
+        }
+        _ => (recorded != recomputed).then(|| (path.to_string(), recorded, recomputed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn numbers_within_tolerance_are_equal() {
+        let recorded = json!({"position": [1.0, 2.0], "is_valid": true});
+        let recomputed = json!({"position": [1.0, 2.000001], "is_valid": true});
+
+        assert_eq!(
+            find_differing_value(&recorded, &recomputed, "ball", 1e-5),
+            None
+        );
+    }
+
+    #[test]
+    fn first_differing_value_is_reported() {
+        let recorded = json!({"position": [1.0, 2.0], "is_valid": true});
+        let recomputed = json!({"position": [1.0, 2.1], "is_valid": true});
+
+        assert_eq!(
+            find_differing_value(&recorded, &recomputed, "ball", 1e-5),
+            Some(("ball.position.1".to_string(), &json!(2.0), &json!(2.1)))
+        );
+    }
+
+    #[test]
+    fn tolerance_is_absolute() {
+        let recorded = json!({"timestamp": 1000.0});
+        let recomputed = json!({"timestamp": 1000.001});
+
+        assert_eq!(
+            find_differing_value(&recorded, &recomputed, "cycle_time", 1e-5),
+            Some((
+                "cycle_time.timestamp".to_string(),
+                &json!(1000.0),
+                &json!(1000.001)
+            ))
+        );
+    }
+
+    #[test]
+    fn missing_values_differ() {
+        let recorded = json!({"position": [1.0, 2.0]});
+        let recomputed = json!(null);
+
+        assert_eq!(
+            find_differing_value(&recorded, &recomputed, "ball", 1e-5),
+            Some(("ball".to_string(), &recorded, &recomputed))
+        );
+    }
+}
@@ -1,13 +1,19 @@
-use code_generation::{generate, write_to_file::WriteToFile, ExecutionMode};
+use code_generation::{
+    cyclers::commit_hash_watch_paths, generate, write_to_file::WriteToFile, ExecutionMode,
+};
 use color_eyre::eyre::{Result, WrapErr};
-use hulk_manifest::collect_hulk_cyclers;
+use hulk_manifest::{collect_hulk_cyclers, MANIFEST_PATH};
 use source_analyzer::{pretty::to_string_pretty, structs::Structs};
 
 fn main() -> Result<()> {
     let cyclers = collect_hulk_cyclers()?;
+    println!("cargo:rerun-if-changed={MANIFEST_PATH}");
     for path in cyclers.watch_paths() {
         println!("cargo:rerun-if-changed={}", path.display());
     }
+    for path in commit_hash_watch_paths() {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
 
     println!();
     println!("{}", to_string_pretty(&cyclers)?);
@@ -0,0 +1,21 @@
+use communication::server::{AccessControl, Role};
+
+/// Converts the access control of the framework parameters for the communication server
+pub fn communication_access_control(access_control: framework::AccessControl) -> AccessControl {
+    AccessControl {
+        tokens: access_control
+            .tokens
+            .into_iter()
+            .map(|(token, role)| (token, communication_role(role)))
+            .collect(),
+        unauthenticated_role: access_control.unauthenticated_role.map(communication_role),
+        writable_paths: access_control.writable_paths,
+    }
+}
+
+fn communication_role(role: framework::Role) -> Role {
+    match role {
+        framework::Role::ReadOnly => Role::ReadOnly,
+        framework::Role::ReadWrite => Role::ReadWrite,
+    }
+}
@@ -5,6 +5,8 @@ use hardware::{
     PathsInterface, RecordingInterface, SensorInterface, SpeakerInterface, TimeInterface,
 };
 
+mod access_control;
+
 pub trait HardwareInterface:
     ActuatorInterface
     + CameraInterface
@@ -1,56 +1,27 @@
-use code_generation::{generate, write_to_file::WriteToFile, ExecutionMode};
+use code_generation::{
+    cyclers::commit_hash_watch_paths, generate, write_to_file::WriteToFile, ExecutionMode,
+};
 use color_eyre::eyre::{Result, WrapErr};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 use source_analyzer::{
-    cyclers::{CyclerKind, Cyclers},
-    manifest::{CyclerManifest, FrameworkManifest},
-    pretty::to_string_pretty,
-    structs::Structs,
+    cyclers::Cyclers, manifest::FrameworkManifest, pretty::to_string_pretty, structs::Structs,
 };
 use std::fs::read_dir;
 
 fn main() -> Result<()> {
-    let manifest = FrameworkManifest {
-        cyclers: vec![
-            CyclerManifest {
-                name: "Control",
-                kind: CyclerKind::RealTime,
-                instances: vec![""],
-                setup_nodes: vec!["crate::fake_data"],
-                nodes: vec![
-                    "control::active_vision",
-                    "control::ball_state_composer",
-                    "control::behavior::node",
-                    "control::game_controller_state_filter",
-                    "control::kick_selector",
-                    "control::filtered_game_controller_state_timer",
-                    "control::primary_state_filter",
-                    "control::motion::look_around",
-                    "control::motion::motion_selector",
-                    "control::referee_position_provider",
-                    "control::role_assignment",
-                    "control::rule_obstacle_composer",
-                    "control::search_suggestor",
-                    "control::time_to_reach_kick_position",
-                    "control::world_state_composer",
-                ],
-            },
-            CyclerManifest {
-                name: "SplNetwork",
-                kind: CyclerKind::Perception,
-                instances: vec![""],
-                setup_nodes: vec!["spl_network::message_receiver"],
-                nodes: vec!["spl_network::message_filter"],
-            },
-        ],
-    };
+    let manifest_path = "manifest.toml";
+    let manifest = FrameworkManifest::try_from_toml_file(manifest_path)?;
+    println!("cargo:rerun-if-changed={manifest_path}");
     let root = "../../crates/";
 
     let cyclers = Cyclers::try_from_manifest(manifest, root)?;
     for path in cyclers.watch_paths() {
         println!("cargo:rerun-if-changed={}", path.display());
     }
+    for path in commit_hash_watch_paths() {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
 
     println!();
     println!("{}", to_string_pretty(&cyclers)?);
@@ -0,0 +1,254 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::Field;
+use linear_algebra::{point, Point2, Vector2};
+use types::{ball_position::SimulatorBallState, field_dimensions::FieldDimensions};
+
+use crate::state::Event;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct BallPhysics {
+    /// Exponential velocity decay of a rolling ball in 1/s
+    pub rolling_friction: f32,
+    /// Fraction of the normal velocity kept when bouncing off goal posts or robots
+    pub restitution: f32,
+    /// Radius around a robot's center in which it collides with the ball
+    pub robot_radius: f32,
+}
+
+impl Default for BallPhysics {
+    fn default() -> Self {
+        Self {
+            rolling_friction: 1.68,
+            restitution: 0.5,
+            robot_radius: 0.08,
+        }
+    }
+}
+
+pub fn simulate_ball(
+    ball: &mut SimulatorBallState,
+    time_step: Duration,
+    robot_positions: &[Point2<Field>],
+    physics: &BallPhysics,
+    field_dimensions: &FieldDimensions,
+) -> Option<Event> {
+    let was_in_field = is_in_field(ball.position, field_dimensions);
+
+    ball.position += ball.velocity * time_step.as_secs_f32();
+    ball.velocity *= (-physics.rolling_friction * time_step.as_secs_f32()).exp();
+
+    let goal_post_radius = field_dimensions.goal_post_diameter / 2.0;
+    for goal_post in goal_posts(field_dimensions) {
+        bounce_off_circle(
+            ball,
+            goal_post,
+            goal_post_radius + field_dimensions.ball_radius,
+            physics.restitution,
+        );
+    }
+    for robot_position in robot_positions {
+        bounce_off_circle(
+            ball,
+            *robot_position,
+            physics.robot_radius + field_dimensions.ball_radius,
+            physics.restitution,
+        );
+    }
+    stop_at_back_of_goal(ball, field_dimensions);
+
+    if !was_in_field || is_in_field(ball.position, field_dimensions) {
+        return None;
+    }
+    let crossed_goal_line = ball.position.x().abs() > goal_line_x(field_dimensions);
+    let is_between_goal_posts = ball.position.y().abs() < field_dimensions.goal_inner_width / 2.0;
+    if !(crossed_goal_line && is_between_goal_posts) {
+        return Some(Event::BallOut);
+    }
+    Some(if ball.position.x() < 0.0 {
+        Event::OwnGoal
+    } else {
+        Event::OpponentGoal
+    })
+}
+
+fn goal_line_x(field_dimensions: &FieldDimensions) -> f32 {
+    field_dimensions.length / 2.0 + field_dimensions.line_width / 2.0 + field_dimensions.ball_radius
+}
+
+fn is_in_field(position: Point2<Field>, field_dimensions: &FieldDimensions) -> bool {
+    let touch_line_y = field_dimensions.width / 2.0
+        + field_dimensions.line_width / 2.0
+        + field_dimensions.ball_radius;
+    position.x().abs() <= goal_line_x(field_dimensions) && position.y().abs() <= touch_line_y
+}
+
+fn goal_posts(field_dimensions: &FieldDimensions) -> [Point2<Field>; 4] {
+    let x = field_dimensions.length / 2.0;
+    let y = field_dimensions.goal_inner_width / 2.0 + field_dimensions.goal_post_diameter / 2.0;
+    [point![x, y], point![x, -y], point![-x, y], point![-x, -y]]
+}
+
+fn stop_at_back_of_goal(ball: &mut SimulatorBallState, field_dimensions: &FieldDimensions) {
+    let back_of_goal_x =
+        field_dimensions.length / 2.0 + field_dimensions.goal_depth - field_dimensions.ball_radius;
+    let is_inside_goal = ball.position.y().abs() < field_dimensions.goal_inner_width / 2.0;
+    if is_inside_goal && ball.position.x().abs() > back_of_goal_x {
+        ball.position = point![
+            back_of_goal_x.copysign(ball.position.x()),
+            ball.position.y()
+        ];
+        ball.velocity = Vector2::zeros();
+    }
+}
+
+fn bounce_off_circle(
+    ball: &mut SimulatorBallState,
+    center: Point2<Field>,
+    radius: f32,
+    restitution: f32,
+) {
+    let to_ball = ball.position - center;
+    let distance = to_ball.norm();
+    if distance >= radius || distance < f32::EPSILON {
+        return;
+    }
+
+    let normal = to_ball.normalize();
+    ball.position = center + normal * radius;
+    let normal_velocity = ball.velocity.dot(normal);
+    if normal_velocity < 0.0 {
+        ball.velocity -= normal * ((1.0 + restitution) * normal_velocity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use linear_algebra::{distance, vector};
+
+    use super::*;
+
+    fn field_dimensions() -> FieldDimensions {
+        FieldDimensions {
+            ball_radius: 0.05,
+            length: 9.0,
+            width: 6.0,
+            line_width: 0.05,
+            goal_inner_width: 1.5,
+            goal_post_diameter: 0.1,
+            goal_depth: 0.5,
+            ..Default::default()
+        }
+    }
+
+    fn simulate(
+        position: Point2<Field>,
+        velocity: Vector2<Field>,
+    ) -> (SimulatorBallState, Option<Event>) {
+        let mut ball = SimulatorBallState { position, velocity };
+        let event = simulate_ball(
+            &mut ball,
+            Duration::from_millis(100),
+            &[],
+            &BallPhysics::default(),
+            &field_dimensions(),
+        );
+        (ball, event)
+    }
+
+    #[test]
+    fn rolling_ball_slows_down() {
+        let (ball, event) = simulate(Point2::origin(), vector![1.0, 0.0]);
+
+        assert!(event.is_none());
+        assert_relative_eq!(ball.position, point![0.1, 0.0]);
+        assert_relative_eq!(
+            ball.velocity,
+            vector![(-BallPhysics::default().rolling_friction * 0.1).exp(), 0.0]
+        );
+    }
+
+    #[test]
+    fn ball_bounces_off_goal_posts() {
+        let physics = BallPhysics::default();
+        let field_dimensions = field_dimensions();
+        let goal_post = point![4.5, 0.8];
+        let mut ball = SimulatorBallState {
+            position: point![4.32, 0.8],
+            velocity: vector![1.0, 0.0],
+        };
+
+        simulate_ball(
+            &mut ball,
+            Duration::from_millis(100),
+            &[],
+            &physics,
+            &field_dimensions,
+        );
+
+        let collision_distance =
+            field_dimensions.goal_post_diameter / 2.0 + field_dimensions.ball_radius;
+        assert_relative_eq!(
+            distance(ball.position, goal_post),
+            collision_distance,
+            epsilon = 1e-5
+        );
+        let velocity_before_bounce = (-physics.rolling_friction * 0.1).exp();
+        assert_relative_eq!(
+            ball.velocity,
+            vector![-physics.restitution * velocity_before_bounce, 0.0],
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn ball_bounces_off_robots() {
+        let mut ball = SimulatorBallState {
+            position: point![0.9, 0.0],
+            velocity: vector![1.0, 0.0],
+        };
+
+        simulate_ball(
+            &mut ball,
+            Duration::from_millis(100),
+            &[point![1.1, 0.0]],
+            &BallPhysics::default(),
+            &field_dimensions(),
+        );
+
+        assert!(ball.position.x() < 1.1 - BallPhysics::default().robot_radius);
+        assert!(ball.velocity.x() < 0.0);
+    }
+
+    #[test]
+    fn ball_crossing_goal_line_between_posts_is_a_goal() {
+        let (_, event) = simulate(point![4.5, 0.0], vector![2.0, 0.0]);
+        assert!(matches!(event, Some(Event::OpponentGoal)));
+
+        let (_, event) = simulate(point![-4.5, 0.0], vector![-2.0, 0.0]);
+        assert!(matches!(event, Some(Event::OwnGoal)));
+    }
+
+    #[test]
+    fn ball_crossing_lines_outside_of_goal_is_out() {
+        let (_, event) = simulate(point![4.5, 1.5], vector![2.0, 0.0]);
+        assert!(matches!(event, Some(Event::BallOut)));
+
+        let (_, event) = simulate(point![0.0, 3.0], vector![0.0, 2.0]);
+        assert!(matches!(event, Some(Event::BallOut)));
+    }
+
+    #[test]
+    fn ball_stops_at_back_of_goal() {
+        let (ball, event) = simulate(point![4.9, 0.0], vector![1.0, 0.0]);
+
+        // the goal was already detected when the ball left the field
+        assert!(event.is_none());
+        assert_relative_eq!(ball.position, point![4.95, 0.0]);
+        assert_relative_eq!(ball.velocity, Vector2::zeros());
+    }
+}
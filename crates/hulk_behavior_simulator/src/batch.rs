@@ -0,0 +1,307 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+use linear_algebra::{vector, Isometry2};
+
+use crate::{metrics::Metrics, simulator::Simulator};
+
+pub struct BatchConfiguration {
+    pub runs: usize,
+    pub seed: u64,
+    /// Maximum distance in meters by which initial robot positions are varied
+    pub initial_position_jitter: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunReport {
+    pub scenario: PathBuf,
+    pub run: usize,
+    pub seed: u64,
+    pub error: Option<String>,
+    pub metrics: Metrics,
+}
+
+pub fn run_batch(
+    scenario_files: &[PathBuf],
+    configuration: &BatchConfiguration,
+) -> Result<Vec<RunReport>> {
+    let mut reports = Vec::new();
+    for scenario_file in scenario_files {
+        for run in 0..configuration.runs {
+            let seed = configuration.seed.wrapping_add(run as u64);
+            reports.push(run_once(scenario_file, run, seed, configuration)?);
+        }
+    }
+    Ok(reports)
+}
+
+fn run_once(
+    scenario_file: &Path,
+    run: usize,
+    seed: u64,
+    configuration: &BatchConfiguration,
+) -> Result<RunReport> {
+    let mut simulator = Simulator::try_new()?;
+    simulator.set_seed(seed)?;
+    simulator
+        .execute_script(scenario_file)
+        .wrap_err_with(|| format!("failed to execute {}", scenario_file.display()))?;
+    vary_initial_positions(&simulator, seed, configuration.initial_position_jitter);
+
+    let error = simulator.run().err().map(|error| format!("{error:#}"));
+
+    Ok(RunReport {
+        scenario: scenario_file.to_path_buf(),
+        run,
+        seed,
+        error,
+        metrics: simulator.metrics.metrics().clone(),
+    })
+}
+
+fn vary_initial_positions(simulator: &Simulator, seed: u64, jitter: f32) {
+    if jitter <= 0.0 {
+        return;
+    }
+    let mut random_number_generator = StdRng::seed_from_u64(seed);
+    let mut state = simulator.state.lock();
+    let mut player_numbers: Vec<_> = state.robots.keys().copied().collect();
+    player_numbers.sort();
+    for player_number in player_numbers {
+        let ground_to_field = &mut state
+            .robots
+            .get_mut(&player_number)
+            .unwrap()
+            .database
+            .main_outputs
+            .ground_to_field;
+        if let Some(pose) = ground_to_field.as_mut() {
+            let offset = vector![
+                random_number_generator.gen_range(-jitter..=jitter),
+                random_number_generator.gen_range(-jitter..=jitter)
+            ];
+            *pose = Isometry2::from_parts(
+                pose.translation().coords() + offset,
+                pose.orientation().angle(),
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    AtMost,
+    AtLeast,
+}
+
+/// Pass/fail criterion on the mean of a metric over all runs of a scenario, e.g. `goals_for>=1`
+#[derive(Clone, Debug)]
+pub struct Threshold {
+    metric: String,
+    comparison: Comparison,
+    value: f32,
+}
+
+impl FromStr for Threshold {
+    type Err = color_eyre::Report;
+
+    fn from_str(threshold: &str) -> Result<Self> {
+        let (metric, comparison, value) = if let Some((metric, value)) = threshold.split_once("<=")
+        {
+            (metric, Comparison::AtMost, value)
+        } else if let Some((metric, value)) = threshold.split_once(">=") {
+            (metric, Comparison::AtLeast, value)
+        } else {
+            bail!("expected threshold of the form `metric<=value` or `metric>=value`");
+        };
+        let metric = metric.trim();
+        if !Metrics::NAMES.contains(&metric) {
+            bail!(
+                "unknown metric `{metric}`, expected one of {}",
+                Metrics::NAMES.join(", ")
+            );
+        }
+        let value = value
+            .trim()
+            .parse()
+            .wrap_err_with(|| format!("failed to parse threshold value of `{metric}`"))?;
+
+        Ok(Self {
+            metric: metric.to_string(),
+            comparison,
+            value,
+        })
+    }
+}
+
+impl Display for Threshold {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let comparison = match self.comparison {
+            Comparison::AtMost => "<=",
+            Comparison::AtLeast => ">=",
+        };
+        write!(formatter, "{}{comparison}{}", self.metric, self.value)
+    }
+}
+
+/// Returns a description of every violated threshold and every failed run
+pub fn evaluate_thresholds(reports: &[RunReport], thresholds: &[Threshold]) -> Vec<String> {
+    let mut violations: Vec<String> = reports
+        .iter()
+        .filter_map(|report| {
+            report.error.as_ref().map(|error| {
+                format!(
+                    "{} run {} failed: {error}",
+                    report.scenario.display(),
+                    report.run
+                )
+            })
+        })
+        .collect();
+
+    let mut scenarios: Vec<&PathBuf> = reports.iter().map(|report| &report.scenario).collect();
+    scenarios.dedup();
+    for scenario in scenarios {
+        for threshold in thresholds {
+            let values: Vec<f32> = reports
+                .iter()
+                .filter(|report| &report.scenario == scenario)
+                .filter_map(|report| report.metrics.get(&threshold.metric))
+                .collect();
+            let mean =
+                (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32);
+            let is_satisfied = mean.is_some_and(|mean| match threshold.comparison {
+                Comparison::AtMost => mean <= threshold.value,
+                Comparison::AtLeast => mean >= threshold.value,
+            });
+            if !is_satisfied {
+                violations.push(format!(
+                    "{}: threshold {threshold} violated, mean is {}",
+                    scenario.display(),
+                    mean.map_or("undefined".to_string(), |mean| mean.to_string())
+                ));
+            }
+        }
+    }
+    violations
+}
+
+/// Writes the reports as CSV if the file extension is `csv`, otherwise as JSON
+pub fn write_report(path: impl AsRef<Path>, reports: &[RunReport]) -> Result<()> {
+    let path = path.as_ref();
+    let file =
+        File::create(path).wrap_err_with(|| format!("failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    if path.extension().is_some_and(|extension| extension == "csv") {
+        write!(writer, "scenario,run,seed,error")?;
+        for name in Metrics::NAMES {
+            write!(writer, ",{name}")?;
+        }
+        writeln!(writer)?;
+        for report in reports {
+            write!(
+                writer,
+                "{},{},{},{}",
+                escape_csv(&report.scenario.display().to_string()),
+                report.run,
+                report.seed,
+                escape_csv(report.error.as_deref().unwrap_or_default())
+            )?;
+            for name in Metrics::NAMES {
+                match report.metrics.get(name) {
+                    Some(value) => write!(writer, ",{value}")?,
+                    None => write!(writer, ",")?,
+                }
+            }
+            writeln!(writer)?;
+        }
+    } else {
+        serde_json::to_writer_pretty(&mut writer, reports)
+            .wrap_err("failed to serialize report")?;
+    }
+
+    writer.flush().wrap_err("failed to write report")
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(scenario: &str, run: usize, goals_for: usize, error: Option<&str>) -> RunReport {
+        RunReport {
+            scenario: PathBuf::from(scenario),
+            run,
+            seed: run as u64,
+            error: error.map(ToString::to_string),
+            metrics: Metrics {
+                goals_for,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn thresholds_are_parsed() {
+        let threshold: Threshold = " goals_for >= 1.5".parse().unwrap();
+        assert_eq!(threshold.to_string(), "goals_for>=1.5");
+        let threshold: Threshold = "time_fallen<=3".parse().unwrap();
+        assert_eq!(threshold.to_string(), "time_fallen<=3");
+
+        assert!("goals_for=1".parse::<Threshold>().is_err());
+        assert!("goals>=1".parse::<Threshold>().is_err());
+        assert!("goals_for>=many".parse::<Threshold>().is_err());
+    }
+
+    #[test]
+    fn thresholds_are_evaluated_on_mean_per_scenario() {
+        let reports = [
+            report("a.lua", 0, 0, None),
+            report("a.lua", 1, 2, None),
+            report("b.lua", 0, 0, None),
+            report("b.lua", 1, 1, None),
+        ];
+        let thresholds = ["goals_for>=1".parse().unwrap()];
+
+        let violations = evaluate_thresholds(&reports, &thresholds);
+
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].starts_with("b.lua"));
+    }
+
+    #[test]
+    fn failed_runs_and_undefined_metrics_are_violations() {
+        let reports = [report("a.lua", 0, 1, Some("assertion failed"))];
+        let thresholds = ["time_to_first_touch<=10".parse().unwrap()];
+
+        let violations = evaluate_thresholds(&reports, &thresholds);
+
+        assert_eq!(
+            violations,
+            [
+                "a.lua run 0 failed: assertion failed",
+                "a.lua: threshold time_to_first_touch<=10 violated, mean is undefined",
+            ]
+        );
+    }
+}
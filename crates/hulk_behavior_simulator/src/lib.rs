@@ -3,8 +3,15 @@
 use hardware::{NetworkInterface, RecordingInterface, SpeakerInterface, TimeInterface};
 use interfake::FakeDataInterface;
 
+pub mod ball;
+pub mod batch;
 pub mod fake_data;
 pub mod interfake;
+pub mod metrics;
+pub mod network;
+pub mod opponent;
+pub mod perception;
+pub mod referee;
 pub mod robot;
 pub mod server;
 pub mod simulator;
@@ -2,17 +2,25 @@ use std::{io::stdout, path::PathBuf, time::Instant};
 
 use chrono::Local;
 use clap::Parser;
-use color_eyre::{eyre::Context, install, Result};
+use color_eyre::{
+    eyre::{bail, Context},
+    install, Result,
+};
 use fern::{Dispatch, InitError};
 use log::LevelFilter;
 use tokio_util::sync::CancellationToken;
 
-use hulk_behavior_simulator::{server, simulator::Simulator};
+use hulk_behavior_simulator::{
+    batch::{evaluate_thresholds, run_batch, write_report, BatchConfiguration, Threshold},
+    server,
+    simulator::Simulator,
+};
 
 #[derive(Parser)]
 enum Arguments {
     Run(RunArguments),
     Serve(ServeArguments),
+    Batch(BatchArguments),
 }
 
 #[derive(Parser)]
@@ -27,6 +35,27 @@ struct ServeArguments {
     scenario_file: PathBuf,
 }
 
+#[derive(Parser)]
+struct BatchArguments {
+    /// Number of runs per scenario
+    #[arg(short, long, default_value_t = 10)]
+    runs: usize,
+    /// Seed of the first run, each following run increments it
+    #[arg(short, long, default_value_t = 0)]
+    seed: u64,
+    /// Maximum distance in meters by which initial robot positions are varied
+    #[arg(long, default_value_t = 0.0)]
+    initial_position_jitter: f32,
+    /// Report file, written as CSV for a `.csv` extension and as JSON otherwise
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Threshold on the mean of a metric over all runs, e.g. `goals_for>=1` (repeatable)
+    #[arg(short, long)]
+    threshold: Vec<Threshold>,
+    #[arg(required = true)]
+    scenario_files: Vec<PathBuf>,
+}
+
 fn setup_logger(is_verbose: bool) -> Result<(), InitError> {
     Dispatch::new()
         .format(|out, message, record| {
@@ -56,6 +85,7 @@ fn main() -> Result<()> {
     match arguments {
         Arguments::Run(arguments) => run(arguments),
         Arguments::Serve(arguments) => serve(arguments),
+        Arguments::Batch(arguments) => batch(arguments),
     }
 }
 
@@ -67,6 +97,11 @@ fn run(arguments: RunArguments) -> Result<()> {
     simulator.run().wrap_err("failed to run simulation")?;
     let duration = Instant::now() - start;
     println!("Took {:.2} seconds", duration.as_secs_f32());
+    println!(
+        "{}",
+        serde_json::to_string_pretty(simulator.metrics.metrics())
+            .wrap_err("failed to serialize metrics")?
+    );
 
     Ok(())
 }
@@ -87,3 +122,27 @@ fn serve(arguments: ServeArguments) -> Result<()> {
         arguments.scenario_file,
     )
 }
+
+fn batch(arguments: BatchArguments) -> Result<()> {
+    let configuration = BatchConfiguration {
+        runs: arguments.runs,
+        seed: arguments.seed,
+        initial_position_jitter: arguments.initial_position_jitter,
+    };
+    let reports = run_batch(&arguments.scenario_files, &configuration)?;
+
+    if let Some(output) = arguments.output {
+        write_report(output, &reports)?;
+    }
+
+    let violations = evaluate_thresholds(&reports, &arguments.threshold);
+    for violation in &violations {
+        eprintln!("{violation}");
+    }
+    if !violations.is_empty() {
+        bail!("{} of the batch criteria failed", violations.len());
+    }
+    println!("All {} runs passed", reports.len());
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::Serialize;
+
+use coordinate_systems::Field;
+use linear_algebra::{distance, Point2};
+use spl_network_messages::PlayerNumber;
+use types::{fall_state::FallState, roles::Role};
+
+use crate::state::{Event, State};
+
+const TOUCH_MARGIN: f32 = 0.05;
+/// Larger movements within one cycle are teleports by scripts or the referee
+const MAXIMUM_WALKED_DISTANCE_PER_CYCLE: f32 = 0.1;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Metrics {
+    /// Simulated time in seconds
+    pub duration: f32,
+    /// Simulated time in seconds until one of our robots touched the ball for the first time
+    pub time_to_first_touch: Option<f32>,
+    pub goals_for: usize,
+    pub goals_against: usize,
+    /// Distance in meters walked by all our robots together
+    pub distance_walked: f32,
+    pub role_changes: usize,
+    /// Time in seconds all our robots together spent not being upright
+    pub time_fallen: f32,
+    pub spl_messages: usize,
+}
+
+impl Metrics {
+    pub const NAMES: [&'static str; 8] = [
+        "duration",
+        "time_to_first_touch",
+        "goals_for",
+        "goals_against",
+        "distance_walked",
+        "role_changes",
+        "time_fallen",
+        "spl_messages",
+    ];
+
+    pub fn get(&self, name: &str) -> Option<f32> {
+        match name {
+            "duration" => Some(self.duration),
+            "time_to_first_touch" => self.time_to_first_touch,
+            "goals_for" => Some(self.goals_for as f32),
+            "goals_against" => Some(self.goals_against as f32),
+            "distance_walked" => Some(self.distance_walked),
+            "role_changes" => Some(self.role_changes as f32),
+            "time_fallen" => Some(self.time_fallen),
+            "spl_messages" => Some(self.spl_messages as f32),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MetricsCollector {
+    metrics: Metrics,
+    last_positions: HashMap<PlayerNumber, Point2<Field>>,
+    last_roles: HashMap<PlayerNumber, Role>,
+}
+
+impl MetricsCollector {
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn update(&mut self, state: &State, events: &[Event], time_step: Duration) {
+        self.metrics.duration = state.time_elapsed.as_secs_f32();
+        self.metrics.spl_messages += state.messages.len();
+
+        for event in events {
+            match event {
+                Event::OpponentGoal => self.metrics.goals_for += 1,
+                Event::OwnGoal => self.metrics.goals_against += 1,
+                Event::Cycle | Event::BallOut => {}
+            }
+        }
+
+        let touch_distance =
+            state.ball_physics.robot_radius + state.field_dimensions.ball_radius + TOUCH_MARGIN;
+        for (player_number, robot) in &state.robots {
+            let main_outputs = &robot.database.main_outputs;
+
+            if let Some(ground_to_field) = main_outputs.ground_to_field {
+                let position = ground_to_field.translation();
+                if let Some(last_position) = self.last_positions.insert(*player_number, position) {
+                    let walked_distance = distance(last_position, position);
+                    if walked_distance < MAXIMUM_WALKED_DISTANCE_PER_CYCLE {
+                        self.metrics.distance_walked += walked_distance;
+                    }
+                }
+                let is_touching_ball = state
+                    .ball
+                    .is_some_and(|ball| distance(ball.position, position) < touch_distance);
+                if is_touching_ball && self.metrics.time_to_first_touch.is_none() {
+                    self.metrics.time_to_first_touch = Some(self.metrics.duration);
+                }
+            }
+
+            if let Some(last_role) = self.last_roles.insert(*player_number, main_outputs.role) {
+                if last_role != main_outputs.role {
+                    self.metrics.role_changes += 1;
+                }
+            }
+
+            if !matches!(main_outputs.fall_state, FallState::Upright) {
+                self.metrics.time_fallen += time_step.as_secs_f32();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::field_dimensions::FieldDimensions;
+
+    use super::*;
+
+    #[test]
+    fn every_metric_name_is_gettable() {
+        let metrics = Metrics {
+            time_to_first_touch: Some(1.0),
+            ..Default::default()
+        };
+
+        for name in Metrics::NAMES {
+            assert!(metrics.get(name).is_some(), "{name}");
+        }
+        assert_eq!(metrics.get("unknown"), None);
+    }
+
+    #[test]
+    fn goals_and_messages_are_counted() {
+        let mut collector = MetricsCollector::default();
+        let mut state = State::new(FieldDimensions::default());
+        state.messages = vec![(PlayerNumber::One, Default::default())];
+        let time_step = Duration::from_millis(100);
+
+        state.time_elapsed = time_step;
+        collector.update(
+            &state,
+            &[Event::Cycle, Event::OpponentGoal, Event::BallOut],
+            time_step,
+        );
+        state.time_elapsed += time_step;
+        collector.update(&state, &[Event::Cycle, Event::OwnGoal], time_step);
+
+        let metrics = collector.metrics();
+        assert_eq!(metrics.duration, (time_step * 2).as_secs_f32());
+        assert_eq!(metrics.goals_for, 1);
+        assert_eq!(metrics.goals_against, 1);
+        assert_eq!(metrics.spl_messages, 2);
+        assert_eq!(metrics.time_to_first_touch, None);
+    }
+}
@@ -0,0 +1,326 @@
+use std::{mem::take, time::Duration};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use spl_network_messages::{HulkMessage, PlayerNumber};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct LinkParameters {
+    /// Probability in [0, 1] that a message is lost on this link
+    pub packet_loss: f32,
+    /// Mean delivery delay in seconds
+    pub latency: f32,
+    /// Maximum deviation in seconds from the mean delivery delay
+    pub jitter: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Link {
+    pub from: PlayerNumber,
+    pub to: PlayerNumber,
+    pub parameters: LinkParameters,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NetworkParameters {
+    /// Parameters of every link without an entry in `links`
+    pub default_link: LinkParameters,
+    pub links: Vec<Link>,
+    /// Expected number of burst outages per second, during which all messages are lost
+    pub outage_rate: f32,
+    /// Duration of a burst outage in seconds
+    pub outage_duration: f32,
+    /// Bytes per second shared by all robots, messages exceeding it are lost
+    pub bandwidth: Option<f32>,
+}
+
+struct InFlightMessage {
+    delivery_time: Duration,
+    sender: PlayerNumber,
+    receiver: PlayerNumber,
+    message: HulkMessage,
+}
+
+/// Simulated team communication between our robots.
+pub struct Network {
+    pub parameters: NetworkParameters,
+    in_flight: Vec<InFlightMessage>,
+    random_number_generator: StdRng,
+    outage_end: Option<Duration>,
+    available_bytes: f32,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            parameters: NetworkParameters::default(),
+            in_flight: Vec::new(),
+            random_number_generator: StdRng::seed_from_u64(0),
+            outage_end: None,
+            available_bytes: 0.0,
+        }
+    }
+}
+
+impl Network {
+    pub fn seed(&mut self, seed: u64) {
+        self.random_number_generator = StdRng::seed_from_u64(seed);
+    }
+
+    /// Broadcasts the messages sent by the robots to all receivers
+    pub fn send(
+        &mut self,
+        messages: &[(PlayerNumber, HulkMessage)],
+        mut receivers: Vec<PlayerNumber>,
+        now: Duration,
+        time_step: Duration,
+    ) {
+        self.update_outage(now, time_step);
+        if let Some(bandwidth) = self.parameters.bandwidth {
+            self.available_bytes =
+                (self.available_bytes + bandwidth * time_step.as_secs_f32()).min(bandwidth);
+        }
+
+        // sort to draw random numbers in the same order for the same seed
+        let mut messages = messages.to_vec();
+        messages.sort_by_key(|(sender, _)| *sender);
+        receivers.sort();
+
+        for (sender, message) in messages {
+            self.in_flight.push(InFlightMessage {
+                delivery_time: now,
+                sender,
+                receiver: sender,
+                message,
+            });
+            if self.outage_end.is_some() || !self.consume_bandwidth(&message) {
+                continue;
+            }
+
+            for &receiver in receivers.iter().filter(|&&receiver| receiver != sender) {
+                let link = self.link_parameters(sender, receiver);
+                if self.random_number_generator.gen::<f32>() < link.packet_loss {
+                    continue;
+                }
+                let jitter = if link.jitter > 0.0 {
+                    self.random_number_generator
+                        .gen_range(-link.jitter..=link.jitter)
+                } else {
+                    0.0
+                };
+                let delay = Duration::from_secs_f32((link.latency + jitter).max(0.0));
+                self.in_flight.push(InFlightMessage {
+                    delivery_time: now + delay,
+                    sender,
+                    receiver,
+                    message,
+                });
+            }
+        }
+    }
+
+    /// Takes all messages which arrived at the receiver until now, including its own ones
+    pub fn receive(
+        &mut self,
+        receiver: PlayerNumber,
+        now: Duration,
+    ) -> Vec<(PlayerNumber, HulkMessage)> {
+        let (mut arrived, in_flight): (Vec<_>, Vec<_>) = take(&mut self.in_flight)
+            .into_iter()
+            .partition(|message| message.receiver == receiver && message.delivery_time <= now);
+        self.in_flight = in_flight;
+        arrived.sort_by_key(|message| message.delivery_time);
+        arrived
+            .into_iter()
+            .map(|message| (message.sender, message.message))
+            .collect()
+    }
+
+    fn update_outage(&mut self, now: Duration, time_step: Duration) {
+        if self.outage_end.is_some_and(|outage_end| now >= outage_end) {
+            self.outage_end = None;
+        }
+        let outage_probability = self.parameters.outage_rate * time_step.as_secs_f32();
+        if self.outage_end.is_none()
+            && outage_probability > 0.0
+            && self.random_number_generator.gen::<f32>() < outage_probability
+        {
+            self.outage_end =
+                Some(now + Duration::from_secs_f32(self.parameters.outage_duration.max(0.0)));
+        }
+    }
+
+    fn consume_bandwidth(&mut self, message: &HulkMessage) -> bool {
+        if self.parameters.bandwidth.is_none() {
+            return true;
+        }
+        let size = bincode::serialized_size(message).unwrap_or_default() as f32;
+        if size > self.available_bytes {
+            return false;
+        }
+        self.available_bytes -= size;
+        true
+    }
+
+    fn link_parameters(&self, from: PlayerNumber, to: PlayerNumber) -> LinkParameters {
+        self.parameters
+            .links
+            .iter()
+            .find(|link| link.from == from && link.to == to)
+            .map_or(self.parameters.default_link, |link| link.parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIME_STEP: Duration = Duration::from_millis(100);
+
+    fn receivers() -> Vec<PlayerNumber> {
+        vec![PlayerNumber::One, PlayerNumber::Two, PlayerNumber::Three]
+    }
+
+    fn senders(messages: &[(PlayerNumber, HulkMessage)]) -> Vec<PlayerNumber> {
+        messages.iter().map(|(sender, _)| *sender).collect()
+    }
+
+    #[test]
+    fn perfect_network_delivers_immediately_to_everyone() {
+        let mut network = Network::default();
+
+        network.send(
+            &[(PlayerNumber::One, HulkMessage::default())],
+            receivers(),
+            Duration::ZERO,
+            TIME_STEP,
+        );
+
+        for receiver in receivers() {
+            assert_eq!(
+                senders(&network.receive(receiver, Duration::ZERO)),
+                [PlayerNumber::One]
+            );
+        }
+        assert!(network.receive(PlayerNumber::Two, TIME_STEP).is_empty());
+    }
+
+    #[test]
+    fn messages_arrive_after_latency() {
+        let mut network = Network::default();
+        network.parameters.default_link.latency = 0.25;
+
+        network.send(
+            &[(PlayerNumber::One, HulkMessage::default())],
+            receivers(),
+            Duration::ZERO,
+            TIME_STEP,
+        );
+
+        assert!(network
+            .receive(PlayerNumber::Two, Duration::from_millis(200))
+            .is_empty());
+        assert_eq!(
+            senders(&network.receive(PlayerNumber::Two, Duration::from_millis(300))),
+            [PlayerNumber::One]
+        );
+    }
+
+    fn delivered_messages(seed: u64, packet_loss: f32) -> Vec<usize> {
+        let mut network = Network::default();
+        network.seed(seed);
+        network.parameters.default_link.packet_loss = packet_loss;
+
+        (0..200)
+            .map(|cycle| {
+                let now = TIME_STEP * cycle;
+                network.send(
+                    &[
+                        (PlayerNumber::One, HulkMessage::default()),
+                        (PlayerNumber::Two, HulkMessage::default()),
+                    ],
+                    receivers(),
+                    now,
+                    TIME_STEP,
+                );
+                network.receive(PlayerNumber::Three, now).len()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn packet_loss_is_reproducible_with_seed() {
+        let delivered = delivered_messages(42, 0.5);
+
+        assert_eq!(delivered, delivered_messages(42, 0.5));
+        assert_ne!(delivered, delivered_messages(43, 0.5));
+        let delivery_rate = delivered.iter().sum::<usize>() as f32 / 400.0;
+        assert!((0.4..0.6).contains(&delivery_rate), "{delivery_rate}");
+    }
+
+    #[test]
+    fn link_parameters_override_default_link() {
+        let mut network = Network::default();
+        network.parameters.links = vec![Link {
+            from: PlayerNumber::One,
+            to: PlayerNumber::Two,
+            parameters: LinkParameters {
+                packet_loss: 1.0,
+                ..Default::default()
+            },
+        }];
+
+        network.send(
+            &[(PlayerNumber::One, HulkMessage::default())],
+            receivers(),
+            Duration::ZERO,
+            TIME_STEP,
+        );
+
+        assert!(network
+            .receive(PlayerNumber::Two, Duration::ZERO)
+            .is_empty());
+        assert_eq!(
+            senders(&network.receive(PlayerNumber::Three, Duration::ZERO)),
+            [PlayerNumber::One]
+        );
+    }
+
+    #[test]
+    fn outages_and_exceeded_bandwidth_drop_messages() {
+        let mut network = Network::default();
+        network.parameters.outage_rate = 100.0;
+        network.parameters.outage_duration = 1.0;
+
+        network.send(
+            &[(PlayerNumber::One, HulkMessage::default())],
+            receivers(),
+            Duration::ZERO,
+            TIME_STEP,
+        );
+
+        assert!(network
+            .receive(PlayerNumber::Two, Duration::ZERO)
+            .is_empty());
+
+        let mut network = Network::default();
+        network.parameters.bandwidth = Some(1.0);
+
+        network.send(
+            &[(PlayerNumber::One, HulkMessage::default())],
+            receivers(),
+            Duration::ZERO,
+            TIME_STEP,
+        );
+
+        assert!(network
+            .receive(PlayerNumber::Two, Duration::ZERO)
+            .is_empty());
+        // senders always receive their own messages
+        assert_eq!(
+            senders(&network.receive(PlayerNumber::One, Duration::ZERO)),
+            [PlayerNumber::One]
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use std::{f32::consts::FRAC_PI_2, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::Field;
+use linear_algebra::{distance, point, vector, Point2};
+use spl_network_messages::PlayerNumber;
+use types::{ball_position::SimulatorBallState, field_dimensions::FieldDimensions};
+
+pub const ROBOT_RADIUS_AT_FOOT_HEIGHT: f32 = 0.2;
+pub const ROBOT_RADIUS_AT_HIP_HEIGHT: f32 = 0.15;
+
+const WALKING_SPEED: f32 = 0.3;
+const TURNING_SPEED: f32 = FRAC_PI_2;
+const KICK_RANGE: f32 = 0.3;
+const KICK_STRENGTH: f32 = 2.5;
+const KICK_COOLDOWN: Duration = Duration::from_secs(1);
+const BLOCK_DISTANCE: f32 = 1.0;
+const MARK_DISTANCE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub enum Policy {
+    /// Stand still, scripts may still teleport the opponent.
+    #[default]
+    Idle,
+    /// Walk towards a fixed target, scripts may update the target every cycle.
+    WalkTo { target: Point2<Field> },
+    /// Walk to the ball and kick it towards our goal.
+    ChaseBall,
+    /// Stand between the ball and the opponent goal.
+    Block,
+    /// Stand between one of our robots and the ball.
+    Mark { player_number: PlayerNumber },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Opponent {
+    pub position: Point2<Field>,
+    pub orientation: f32,
+    pub policy: Policy,
+    pub last_kick_time: Duration,
+}
+
+impl Opponent {
+    pub fn new(position: Point2<Field>, policy: Policy) -> Self {
+        Self {
+            position,
+            orientation: 0.0,
+            policy,
+            last_kick_time: Duration::ZERO,
+        }
+    }
+
+    pub fn step(
+        &mut self,
+        time_step: Duration,
+        ball: Option<&SimulatorBallState>,
+        robot_positions: impl Fn(PlayerNumber) -> Option<Point2<Field>>,
+        field_dimensions: &FieldDimensions,
+    ) {
+        let ball_position = ball.map(|ball| ball.position);
+        let target = match self.policy {
+            Policy::Idle => None,
+            Policy::WalkTo { target } => Some(target),
+            Policy::ChaseBall => ball_position,
+            Policy::Block => ball_position.map(|ball_position| {
+                let opponent_goal = point![field_dimensions.length / 2.0, 0.0];
+                let to_goal = opponent_goal - ball_position;
+                let distance_to_goal = to_goal.norm();
+                ball_position + to_goal.normalize() * BLOCK_DISTANCE.min(distance_to_goal / 2.0)
+            }),
+            Policy::Mark { player_number } => {
+                robot_positions(player_number).map(|robot_position| {
+                    let to_ball = ball_position.map_or(vector![-1.0, 0.0], |ball_position| {
+                        ball_position - robot_position
+                    });
+                    robot_position + to_ball.cap_magnitude(MARK_DISTANCE)
+                })
+            }
+        };
+
+        let Some(target) = target else {
+            return;
+        };
+        let to_target = target - self.position;
+        self.position += to_target.cap_magnitude(WALKING_SPEED * time_step.as_secs_f32());
+
+        let look_direction = ball_position
+            .map(|ball_position| ball_position - self.position)
+            .unwrap_or(to_target);
+        if look_direction.norm_squared() > f32::EPSILON {
+            let desired_orientation = look_direction.y().atan2(look_direction.x());
+            let maximum_rotation = TURNING_SPEED * time_step.as_secs_f32();
+            let difference = normalize_angle(desired_orientation - self.orientation);
+            self.orientation = normalize_angle(
+                self.orientation + difference.clamp(-maximum_rotation, maximum_rotation),
+            );
+        }
+    }
+
+    pub fn kick_ball(
+        &mut self,
+        ball: &mut SimulatorBallState,
+        now: Duration,
+        field_dimensions: &FieldDimensions,
+    ) -> bool {
+        let is_in_kick_range = distance(self.position, ball.position) < KICK_RANGE;
+        if !matches!(self.policy, Policy::ChaseBall)
+            || !is_in_kick_range
+            || now.saturating_sub(self.last_kick_time) <= KICK_COOLDOWN
+        {
+            return false;
+        }
+
+        let own_goal = point![-field_dimensions.length / 2.0, 0.0];
+        let kick_direction = (own_goal - ball.position).normalize();
+        ball.velocity = kick_direction * KICK_STRENGTH;
+        self.last_kick_time = now;
+        true
+    }
+}
+
+fn normalize_angle(angle: f32) -> f32 {
+    angle.sin().atan2(angle.cos())
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use linear_algebra::Vector2;
+
+    use super::*;
+
+    fn field_dimensions() -> FieldDimensions {
+        FieldDimensions {
+            length: 9.0,
+            width: 6.0,
+            ..Default::default()
+        }
+    }
+
+    fn ball_at(position: Point2<Field>) -> SimulatorBallState {
+        SimulatorBallState {
+            position,
+            velocity: Vector2::zeros(),
+        }
+    }
+
+    #[test]
+    fn chasing_opponent_walks_towards_ball_with_limited_speed() {
+        let mut opponent = Opponent::new(Point2::origin(), Policy::ChaseBall);
+        opponent.orientation = FRAC_PI_2;
+        let ball = ball_at(point![2.0, 0.0]);
+
+        opponent.step(
+            Duration::from_secs(1),
+            Some(&ball),
+            |_| None,
+            &field_dimensions(),
+        );
+
+        assert_relative_eq!(opponent.position, point![WALKING_SPEED, 0.0]);
+        assert_relative_eq!(opponent.orientation, 0.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn blocking_opponent_stands_between_ball_and_goal() {
+        let mut opponent = Opponent::new(point![0.0, 2.0], Policy::Block);
+        let ball = ball_at(Point2::origin());
+
+        for _ in 0..100 {
+            opponent.step(
+                Duration::from_millis(100),
+                Some(&ball),
+                |_| None,
+                &field_dimensions(),
+            );
+        }
+
+        assert_relative_eq!(
+            opponent.position,
+            point![BLOCK_DISTANCE, 0.0],
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn marking_opponent_stands_between_robot_and_ball() {
+        let mut opponent = Opponent::new(
+            point![1.0, 1.0],
+            Policy::Mark {
+                player_number: PlayerNumber::Two,
+            },
+        );
+        let ball = ball_at(point![3.0, 0.0]);
+        let robot_positions =
+            |player_number| (player_number == PlayerNumber::Two).then_some(point![1.0, 0.0]);
+
+        opponent.step(
+            Duration::from_secs(10),
+            Some(&ball),
+            robot_positions,
+            &field_dimensions(),
+        );
+
+        assert_relative_eq!(opponent.position, point![1.0 + MARK_DISTANCE, 0.0]);
+    }
+
+    #[test]
+    fn only_chasing_opponents_kick_in_range_after_cooldown() {
+        let field_dimensions = field_dimensions();
+        let mut opponent = Opponent::new(Point2::origin(), Policy::ChaseBall);
+        let mut ball = ball_at(point![0.1, 0.0]);
+
+        assert!(opponent.kick_ball(&mut ball, Duration::from_secs(2), &field_dimensions));
+        assert_relative_eq!(ball.velocity, vector![-KICK_STRENGTH, 0.0], epsilon = 1e-4);
+        assert!(!opponent.kick_ball(&mut ball, Duration::from_millis(2500), &field_dimensions));
+        assert!(opponent.kick_ball(&mut ball, Duration::from_secs(4), &field_dimensions));
+
+        let mut out_of_range_ball = ball_at(point![1.0, 0.0]);
+        assert!(!opponent.kick_ball(
+            &mut out_of_range_ball,
+            Duration::from_secs(10),
+            &field_dimensions
+        ));
+
+        let mut idle_opponent = Opponent::new(Point2::origin(), Policy::Idle);
+        assert!(!idle_opponent.kick_ball(&mut ball, Duration::from_secs(10), &field_dimensions));
+    }
+}
@@ -0,0 +1,250 @@
+use std::{f32::consts::PI, time::Duration};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::{Field, Ground, Head};
+use geometry::line_segment::LineSegment;
+use linear_algebra::{distance, point, vector, Isometry2, Point2, Vector2};
+
+use crate::opponent::ROBOT_RADIUS_AT_HIP_HEIGHT;
+
+pub const MAXIMUM_DETECTION_DISTANCE: f32 = 3.0;
+const MINIMUM_PHANTOM_BALL_DISTANCE: f32 = 0.3;
+
+/// The default parameters describe a perfect perception.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct PerceptionParameters {
+    /// Standard deviation in meters of the perceived ball position per meter distance to the ball
+    pub ball_noise_per_meter: f32,
+    /// Probability in [0, 1] that a ball in view is not detected in a cycle
+    pub ball_dropout_probability: f32,
+    /// Expected number of phantom balls per second detected at random positions in view
+    pub phantom_ball_rate: f32,
+    /// Whether balls and opponents behind other robots are hidden
+    pub occlusion: bool,
+    /// Standard deviation in meters of the localization drift per square root of a second
+    pub localization_drift: f32,
+    /// Standard deviation in radians of the localization drift per square root of a second
+    pub localization_angle_drift: f32,
+    /// Expected number of times per second the localization is mirrored to the other field half
+    pub localization_mirror_rate: f32,
+    /// Expected number of times per second the localization recovers the true pose
+    pub localization_recovery_rate: f32,
+}
+
+/// Degrades what simulated robots perceive of the ground truth.
+pub struct Perception {
+    pub parameters: PerceptionParameters,
+    random_number_generator: StdRng,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Self {
+            parameters: PerceptionParameters::default(),
+            random_number_generator: StdRng::seed_from_u64(0),
+        }
+    }
+}
+
+impl Perception {
+    pub fn seed(&mut self, seed: u64) {
+        self.random_number_generator = StdRng::seed_from_u64(seed);
+    }
+
+    /// Whether a ball in view is detected in this cycle
+    pub fn detects_ball(&mut self) -> bool {
+        !self.happens(self.parameters.ball_dropout_probability)
+    }
+
+    /// Samples the error of a ball detection at the given distance
+    pub fn ball_measurement_error(&mut self, distance: f32) -> Vector2<Field> {
+        let standard_deviation = self.parameters.ball_noise_per_meter * distance;
+        vector![
+            self.sample_normal(standard_deviation),
+            self.sample_normal(standard_deviation)
+        ]
+    }
+
+    /// Samples a falsely detected ball somewhere in view
+    pub fn phantom_ball(
+        &mut self,
+        time_step: Duration,
+        field_of_view: f32,
+    ) -> Option<Point2<Head>> {
+        if !self.happens(self.parameters.phantom_ball_rate * time_step.as_secs_f32()) {
+            return None;
+        }
+        let angle = self
+            .random_number_generator
+            .gen_range(-field_of_view / 2.0..=field_of_view / 2.0);
+        let distance = self
+            .random_number_generator
+            .gen_range(MINIMUM_PHANTOM_BALL_DISTANCE..=MAXIMUM_DETECTION_DISTANCE);
+        Some(point![distance * angle.cos(), distance * angle.sin()])
+    }
+
+    /// Advances the error between the true and the believed pose of a robot
+    pub fn update_localization_error(
+        &mut self,
+        error: &mut Isometry2<Field, Field>,
+        ground_to_field: Isometry2<Ground, Field>,
+        time_step: Duration,
+    ) {
+        let seconds = time_step.as_secs_f32();
+        if self.happens(self.parameters.localization_recovery_rate * seconds) {
+            *error = Isometry2::identity();
+        }
+        if self.happens(self.parameters.localization_mirror_rate * seconds) {
+            *error = Isometry2::rotation(PI) * *error;
+        }
+
+        let translation_deviation = self.parameters.localization_drift * seconds.sqrt();
+        let angle_deviation = self.parameters.localization_angle_drift * seconds.sqrt();
+        let translation = vector![
+            self.sample_normal(translation_deviation),
+            self.sample_normal(translation_deviation)
+        ];
+        let angle = self.sample_normal(angle_deviation);
+
+        // rotate around the believed position instead of the field origin
+        let believed_position = (*error * ground_to_field).translation();
+        let rotation = Isometry2::<Field, Field>::rotation(angle);
+        let rotation_offset = believed_position.coords() - (rotation * believed_position).coords();
+        *error = Isometry2::from_parts(rotation_offset + translation, angle) * *error;
+    }
+
+    fn happens(&mut self, probability: f32) -> bool {
+        probability > 0.0 && self.random_number_generator.gen::<f32>() < probability
+    }
+
+    fn sample_normal(&mut self, standard_deviation: f32) -> f32 {
+        if standard_deviation <= 0.0 {
+            return 0.0;
+        }
+        Normal::new(0.0, standard_deviation).map_or(0.0, |normal| {
+            normal.sample(&mut self.random_number_generator)
+        })
+    }
+}
+
+/// Whether one of the robots stands between the viewer and the target
+pub fn is_occluded(
+    viewer: Point2<Field>,
+    target: Point2<Field>,
+    robot_positions: &[Point2<Field>],
+) -> bool {
+    let line_of_sight = LineSegment::new(viewer, target);
+    let target_distance = distance(viewer, target);
+    robot_positions.iter().any(|&position| {
+        let distance_to_viewer = distance(viewer, position);
+        distance_to_viewer > f32::EPSILON
+            && distance_to_viewer < target_distance
+            && distance(position, target) > f32::EPSILON
+            && distance(line_of_sight.closest_point(position), position)
+                < ROBOT_RADIUS_AT_HIP_HEIGHT
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    const TIME_STEP: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn default_perception_is_perfect() {
+        let mut perception = Perception::default();
+        let mut localization_error = Isometry2::identity();
+
+        for _ in 0..100 {
+            assert!(perception.detects_ball());
+            assert_eq!(perception.ball_measurement_error(2.0), Vector2::zeros());
+            assert!(perception.phantom_ball(TIME_STEP, FRAC_PI_2).is_none());
+            perception.update_localization_error(
+                &mut localization_error,
+                Isometry2::from_parts(vector![1.0, 2.0], 0.5),
+                TIME_STEP,
+            );
+        }
+        assert_relative_eq!(localization_error, Isometry2::identity());
+    }
+
+    #[test]
+    fn ball_noise_grows_with_distance() {
+        let mut perception = Perception::default();
+        perception.parameters.ball_noise_per_meter = 0.1;
+
+        let mean_error = |perception: &mut Perception, distance: f32| {
+            (0..1000)
+                .map(|_| perception.ball_measurement_error(distance).norm())
+                .sum::<f32>()
+                / 1000.0
+        };
+        let near = mean_error(&mut perception, 1.0);
+        let far = mean_error(&mut perception, 3.0);
+
+        assert!(near > 0.0);
+        assert_relative_eq!(far / near, 3.0, epsilon = 0.3);
+    }
+
+    #[test]
+    fn dropped_out_balls_are_not_detected() {
+        let mut perception = Perception::default();
+        perception.parameters.ball_dropout_probability = 1.0;
+
+        assert!((0..100).all(|_| !perception.detects_ball()));
+    }
+
+    #[test]
+    fn phantom_balls_appear_in_view() {
+        let mut perception = Perception::default();
+        perception.parameters.phantom_ball_rate = 1000.0;
+        let field_of_view = FRAC_PI_2;
+
+        for _ in 0..100 {
+            let phantom_ball = perception.phantom_ball(TIME_STEP, field_of_view).unwrap();
+            let distance = phantom_ball.coords().norm();
+            let angle = phantom_ball.y().atan2(phantom_ball.x());
+            assert!(
+                (MINIMUM_PHANTOM_BALL_DISTANCE - 1e-4..=MAXIMUM_DETECTION_DISTANCE + 1e-4)
+                    .contains(&distance)
+            );
+            assert!(angle.abs() <= field_of_view / 2.0 + 1e-4);
+        }
+    }
+
+    #[test]
+    fn mirrored_localization_believes_opposite_half() {
+        let mut perception = Perception::default();
+        perception.parameters.localization_mirror_rate = 1000.0;
+        let mut localization_error = Isometry2::identity();
+        let ground_to_field = Isometry2::<Ground, Field>::from_parts(vector![1.0, 2.0], 0.0);
+
+        perception.update_localization_error(&mut localization_error, ground_to_field, TIME_STEP);
+
+        assert_relative_eq!(
+            (localization_error * ground_to_field).translation(),
+            point![-1.0, -2.0],
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn robots_on_line_of_sight_occlude() {
+        let viewer = Point2::origin();
+        let target = point![2.0, 0.0];
+
+        assert!(is_occluded(viewer, target, &[point![1.0, 0.1]]));
+        assert!(!is_occluded(viewer, target, &[point![1.0, 0.5]]));
+        assert!(!is_occluded(viewer, target, &[point![3.0, 0.0]]));
+        // neither the viewer nor the target occlude themselves
+        assert!(!is_occluded(viewer, target, &[viewer, target]));
+    }
+}
@@ -0,0 +1,469 @@
+use std::{
+    f32::consts::FRAC_PI_2,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::Field;
+use linear_algebra::{distance, point, vector, Isometry2, Point2, Vector2};
+use spl_network_messages::{GameState, Penalty, SubState, Team};
+use types::ball_position::SimulatorBallState;
+
+use crate::state::{Event, State};
+
+const SET_PLAY_BALL_MOVED_DISTANCE: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct RefereeParameters {
+    pub enabled: bool,
+    /// Time in seconds until the referee switches from Initial to Ready
+    pub initial_duration: f32,
+    /// Time in seconds until the referee switches from Ready to Set
+    pub ready_duration: f32,
+    /// Time in seconds until the referee switches from Set to Playing
+    pub set_duration: f32,
+    /// Playing time in seconds of one half
+    pub half_duration: f32,
+    /// Time in seconds between the end of the first half and the Initial state of the second half
+    pub half_time_break_duration: f32,
+    /// Time in seconds after which a set play ends even if the ball was not touched
+    pub set_play_duration: f32,
+    /// Time in seconds a robot is penalized for an illegal position
+    pub penalty_duration: f32,
+}
+
+impl Default for RefereeParameters {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            initial_duration: 1.0,
+            ready_duration: 45.0,
+            set_duration: 5.0,
+            half_duration: 600.0,
+            half_time_break_duration: 10.0,
+            set_play_duration: 30.0,
+            penalty_duration: 45.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Half {
+    #[default]
+    First,
+    Second,
+}
+
+struct SetPlay {
+    start_time: Duration,
+    ball_position: Point2<Field>,
+}
+
+/// Advances the game controller state according to the SPL rules.
+#[derive(Default)]
+pub struct Referee {
+    pub parameters: RefereeParameters,
+    half: Half,
+    last_game_state: Option<GameState>,
+    state_entered_at: Duration,
+    playing_time: Duration,
+    first_half_kicking_team: Option<Team>,
+    set_play: Option<SetPlay>,
+}
+
+impl Referee {
+    pub fn cycle(&mut self, state: &mut State, time_step: Duration, events: &[Event]) {
+        if !self.parameters.enabled {
+            return;
+        }
+
+        let now = state.time_elapsed;
+        let game_state = state.game_controller_state.game_state;
+        if self.last_game_state != Some(game_state) {
+            // scripts may have changed the game state themselves
+            self.last_game_state = Some(game_state);
+            self.state_entered_at = now;
+        }
+        let time_in_state = (now - self.state_entered_at).as_secs_f32();
+
+        match game_state {
+            GameState::Initial | GameState::Standby
+                if time_in_state > self.parameters.initial_duration =>
+            {
+                self.first_half_kicking_team
+                    .get_or_insert(state.game_controller_state.kicking_team);
+                self.transition(state, GameState::Ready);
+            }
+            GameState::Ready if time_in_state > self.parameters.ready_duration => {
+                self.penalize_illegal_positions(state);
+                self.transition(state, GameState::Set);
+            }
+            GameState::Set if time_in_state > self.parameters.set_duration => {
+                self.transition(state, GameState::Playing);
+            }
+            GameState::Playing => {
+                self.playing_time += time_step;
+                self.handle_ball_events(state, events);
+                self.end_finished_set_play(state);
+                if self.playing_time.as_secs_f32() > self.parameters.half_duration {
+                    self.playing_time = Duration::ZERO;
+                    self.set_play = None;
+                    state.game_controller_state.sub_state = None;
+                    self.transition(state, GameState::Finished);
+                }
+            }
+            GameState::Finished
+                if self.half == Half::First
+                    && time_in_state > self.parameters.half_time_break_duration =>
+            {
+                self.half = Half::Second;
+                if let Some(team) = self.first_half_kicking_team {
+                    state.game_controller_state.kicking_team = other_team(team);
+                }
+                state.ball = Some(ball_at(Point2::origin()));
+                self.transition(state, GameState::Initial);
+            }
+            _ => {}
+        }
+
+        count_down_penalties(state, time_step);
+    }
+
+    fn transition(&mut self, state: &mut State, game_state: GameState) {
+        state.game_controller_state.game_state = game_state;
+        state.game_controller_state.last_game_state_change = UNIX_EPOCH + state.time_elapsed;
+        self.last_game_state = Some(game_state);
+        self.state_entered_at = state.time_elapsed;
+    }
+
+    fn handle_ball_events(&mut self, state: &mut State, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::Cycle => {}
+                Event::BallOut => self.award_set_play(state),
+                Event::OwnGoal => {
+                    self.award_kickoff(state, Team::Hulks);
+                    return;
+                }
+                Event::OpponentGoal => {
+                    self.award_kickoff(state, Team::Opponent);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn award_kickoff(&mut self, state: &mut State, kicking_team: Team) {
+        state.game_controller_state.kicking_team = kicking_team;
+        state.game_controller_state.sub_state = None;
+        state.ball = Some(ball_at(Point2::origin()));
+        state.last_ball_contact = None;
+        self.set_play = None;
+        self.transition(state, GameState::Ready);
+    }
+
+    fn award_set_play(&mut self, state: &mut State) {
+        let Some(ball) = state.ball else {
+            return;
+        };
+        let field_dimensions = state.field_dimensions;
+        let half_length = field_dimensions.length / 2.0;
+        let half_width = field_dimensions.width / 2.0;
+        // without known contact, the ball is assumed to be last touched by us
+        let last_contact = state.last_ball_contact.unwrap_or(Team::Hulks);
+        let side = ball.position.y().signum();
+
+        let (sub_state, ball_position) = if ball.position.x().abs() > half_length {
+            let end = ball.position.x().signum();
+            let defending_team = if end < 0.0 {
+                Team::Hulks
+            } else {
+                Team::Opponent
+            };
+            if last_contact == defending_team {
+                (
+                    SubState::CornerKick,
+                    point![end * half_length, side * half_width],
+                )
+            } else {
+                (
+                    SubState::GoalKick,
+                    point![
+                        end * (half_length - field_dimensions.goal_box_area_length),
+                        side * field_dimensions.goal_box_area_width / 2.0
+                    ],
+                )
+            }
+        } else {
+            (
+                SubState::KickIn,
+                point![ball.position.x(), side * half_width],
+            )
+        };
+
+        state.ball = Some(ball_at(ball_position));
+        state.game_controller_state.sub_state = Some(sub_state);
+        state.game_controller_state.kicking_team = other_team(last_contact);
+        state.last_ball_contact = None;
+        self.set_play = Some(SetPlay {
+            start_time: state.time_elapsed,
+            ball_position,
+        });
+    }
+
+    fn end_finished_set_play(&mut self, state: &mut State) {
+        let Some(set_play) = &self.set_play else {
+            return;
+        };
+        let is_timed_out = (state.time_elapsed - set_play.start_time).as_secs_f32()
+            > self.parameters.set_play_duration;
+        let ball_was_moved = state.ball.map_or(true, |ball| {
+            distance(ball.position, set_play.ball_position) > SET_PLAY_BALL_MOVED_DISTANCE
+        });
+        if is_timed_out || ball_was_moved {
+            state.game_controller_state.sub_state = None;
+            self.set_play = None;
+        }
+    }
+
+    fn penalize_illegal_positions(&self, state: &mut State) {
+        let is_kicking_team = state.game_controller_state.kicking_team == Team::Hulks;
+        let center_circle_radius = state.field_dimensions.center_circle_diameter / 2.0;
+        let reentry_x =
+            -state.field_dimensions.length / 2.0 + state.field_dimensions.penalty_marker_distance;
+        let half_width = state.field_dimensions.width / 2.0;
+
+        for (player_number, robot) in state.robots.iter_mut() {
+            if state.game_controller_state.penalties[*player_number].is_some() {
+                continue;
+            }
+            let Some(ground_to_field) = robot.database.main_outputs.ground_to_field else {
+                continue;
+            };
+            let position = ground_to_field.translation();
+            let is_in_opponent_half = position.x() > 0.0;
+            let is_in_center_circle = position.coords().norm() < center_circle_radius;
+            if !is_in_opponent_half && (is_kicking_team || !is_in_center_circle) {
+                continue;
+            }
+
+            state.game_controller_state.penalties[*player_number] =
+                Some(Penalty::IllegalPositionInSet {
+                    remaining: Duration::from_secs_f32(self.parameters.penalty_duration),
+                });
+            let side = if position.y() < 0.0 { -1.0 } else { 1.0 };
+            robot.database.main_outputs.ground_to_field = Some(Isometry2::from_parts(
+                vector![reentry_x, side * half_width],
+                -side * FRAC_PI_2,
+            ));
+        }
+    }
+}
+
+fn count_down_penalties(state: &mut State, time_step: Duration) {
+    for player_number in state.robots.keys() {
+        let penalty = &mut state.game_controller_state.penalties[*player_number];
+        let Some(remaining) = penalty.as_mut().map(remaining_mut) else {
+            continue;
+        };
+        if remaining.is_zero() {
+            // penalties without a timer are released by scripts
+            continue;
+        }
+        *remaining = remaining.saturating_sub(time_step);
+        if remaining.is_zero() {
+            *penalty = None;
+        }
+    }
+}
+
+fn remaining_mut(penalty: &mut Penalty) -> &mut Duration {
+    match penalty {
+        Penalty::IllegalBallContact { remaining }
+        | Penalty::PlayerPushing { remaining }
+        | Penalty::IllegalMotionInStandby { remaining }
+        | Penalty::IllegalMotionInSet { remaining }
+        | Penalty::InactivePlayer { remaining }
+        | Penalty::IllegalPosition { remaining }
+        | Penalty::LeavingTheField { remaining }
+        | Penalty::RequestForPickup { remaining }
+        | Penalty::LocalGameStuck { remaining }
+        | Penalty::IllegalPositionInSet { remaining }
+        | Penalty::PlayerStance { remaining }
+        | Penalty::Substitute { remaining }
+        | Penalty::Manual { remaining } => remaining,
+    }
+}
+
+fn other_team(team: Team) -> Team {
+    match team {
+        Team::Hulks => Team::Opponent,
+        Team::Opponent => Team::Hulks,
+    }
+}
+
+fn ball_at(position: Point2<Field>) -> SimulatorBallState {
+    SimulatorBallState {
+        position,
+        velocity: Vector2::zeros(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use types::field_dimensions::FieldDimensions;
+
+    use super::*;
+
+    const TIME_STEP: Duration = Duration::from_millis(100);
+
+    fn field_dimensions() -> FieldDimensions {
+        FieldDimensions {
+            length: 9.0,
+            width: 6.0,
+            goal_box_area_length: 0.6,
+            goal_box_area_width: 2.2,
+            ..Default::default()
+        }
+    }
+
+    fn referee() -> Referee {
+        Referee {
+            parameters: RefereeParameters {
+                enabled: true,
+                initial_duration: 1.0,
+                ready_duration: 1.0,
+                set_duration: 1.0,
+                half_duration: 2.0,
+                half_time_break_duration: 1.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn playing_state() -> State {
+        let mut state = State::new(field_dimensions());
+        state.game_controller_state.game_state = GameState::Playing;
+        state
+    }
+
+    fn run(referee: &mut Referee, state: &mut State, duration: Duration) {
+        let end = state.time_elapsed + duration;
+        while state.time_elapsed < end {
+            referee.cycle(state, TIME_STEP, &[Event::Cycle]);
+            state.time_elapsed += TIME_STEP;
+        }
+    }
+
+    #[test]
+    fn game_states_advance_after_their_durations() {
+        let mut referee = referee();
+        let mut state = State::new(field_dimensions());
+        assert_eq!(state.game_controller_state.game_state, GameState::Initial);
+
+        run(&mut referee, &mut state, Duration::from_millis(1200));
+        assert_eq!(state.game_controller_state.game_state, GameState::Ready);
+        run(&mut referee, &mut state, Duration::from_millis(1200));
+        assert_eq!(state.game_controller_state.game_state, GameState::Set);
+        run(&mut referee, &mut state, Duration::from_millis(1200));
+        assert_eq!(state.game_controller_state.game_state, GameState::Playing);
+        run(&mut referee, &mut state, Duration::from_millis(2200));
+        assert_eq!(state.game_controller_state.game_state, GameState::Finished);
+
+        // the other team kicks off the second half
+        run(&mut referee, &mut state, Duration::from_millis(1200));
+        assert_eq!(state.game_controller_state.game_state, GameState::Initial);
+        assert_eq!(state.game_controller_state.kicking_team, Team::Opponent);
+        run(&mut referee, &mut state, Duration::from_secs(10));
+        assert_eq!(state.game_controller_state.game_state, GameState::Finished);
+    }
+
+    #[test]
+    fn disabled_referee_keeps_game_state() {
+        let mut referee = Referee::default();
+        let mut state = State::new(field_dimensions());
+
+        run(&mut referee, &mut state, Duration::from_secs(10));
+
+        assert_eq!(state.game_controller_state.game_state, GameState::Initial);
+    }
+
+    #[test]
+    fn goals_lead_to_kickoff_of_conceding_team() {
+        let mut referee = referee();
+        let mut state = playing_state();
+        state.ball = Some(ball_at(point![4.7, 0.0]));
+
+        referee.cycle(&mut state, TIME_STEP, &[Event::Cycle, Event::OpponentGoal]);
+
+        assert_eq!(state.game_controller_state.game_state, GameState::Ready);
+        assert_eq!(state.game_controller_state.kicking_team, Team::Opponent);
+        assert_relative_eq!(state.ball.unwrap().position, Point2::origin());
+    }
+
+    #[test]
+    fn ball_over_touch_line_leads_to_kick_in_until_ball_is_moved() {
+        let mut referee = referee();
+        let mut state = playing_state();
+        state.ball = Some(ball_at(point![2.0, 3.2]));
+        state.last_ball_contact = Some(Team::Opponent);
+
+        referee.cycle(&mut state, TIME_STEP, &[Event::Cycle, Event::BallOut]);
+
+        assert_eq!(
+            state.game_controller_state.sub_state,
+            Some(SubState::KickIn)
+        );
+        assert_eq!(state.game_controller_state.kicking_team, Team::Hulks);
+        assert_relative_eq!(state.ball.unwrap().position, point![2.0, 3.0]);
+
+        run(&mut referee, &mut state, Duration::from_secs(1));
+        assert_eq!(
+            state.game_controller_state.sub_state,
+            Some(SubState::KickIn)
+        );
+
+        state.ball = Some(ball_at(point![2.5, 3.0]));
+        run(&mut referee, &mut state, TIME_STEP);
+        assert_eq!(state.game_controller_state.sub_state, None);
+    }
+
+    #[test]
+    fn ball_over_goal_line_leads_to_corner_or_goal_kick() {
+        let mut referee = referee();
+        let mut state = playing_state();
+        state.ball = Some(ball_at(point![-4.7, 1.0]));
+        state.last_ball_contact = Some(Team::Hulks);
+
+        referee.cycle(&mut state, TIME_STEP, &[Event::Cycle, Event::BallOut]);
+
+        assert_eq!(
+            state.game_controller_state.sub_state,
+            Some(SubState::CornerKick)
+        );
+        assert_eq!(state.game_controller_state.kicking_team, Team::Opponent);
+        assert_relative_eq!(state.ball.unwrap().position, point![-4.5, 3.0]);
+
+        let mut referee = self::referee();
+        let mut state = playing_state();
+        state.ball = Some(ball_at(point![4.7, -1.0]));
+        state.last_ball_contact = Some(Team::Hulks);
+
+        referee.cycle(&mut state, TIME_STEP, &[Event::Cycle, Event::BallOut]);
+
+        assert_eq!(
+            state.game_controller_state.sub_state,
+            Some(SubState::GoalKick)
+        );
+        assert_eq!(state.game_controller_state.kicking_team, Team::Opponent);
+        assert_relative_eq!(
+            state.ball.unwrap().position,
+            point![3.9, -1.1],
+            epsilon = 1e-5
+        );
+    }
+}
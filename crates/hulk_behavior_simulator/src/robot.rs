@@ -1,22 +1,24 @@
 use std::{
+    collections::{HashMap, HashSet},
     convert::Into,
     sync::{mpsc, Arc},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use buffered_watch::Receiver;
+use buffered_watch::{Receiver, Sender};
 use color_eyre::{eyre::WrapErr, Result};
 
 use control::localization::generate_initial_pose;
+use coordinate_systems::Field;
 use framework::{future_queue, Producer, RecordingTrigger};
-use linear_algebra::vector;
+use linear_algebra::{vector, Isometry2, Point2, Vector2};
 use parameters::directory::deserialize;
 use projection::camera_matrix::CameraMatrix;
 use spl_network_messages::{HulkMessage, PlayerNumber};
 use types::{hardware::Ids, messages::IncomingMessage, motion_selection::MotionSafeExits};
 
 use crate::{
-    cyclers::control::{Cycler, CyclerInstance, Database},
+    cyclers::control::{Cycler, CyclerInstance, Database, Overrides},
     interfake::{FakeDataInterface, Interfake},
     structs::Parameters,
 };
@@ -28,10 +30,17 @@ pub struct Robot {
     pub is_penalized: bool,
     pub last_kick_time: Duration,
     pub ball_last_seen: Option<SystemTime>,
+    pub ball_measurement_error: Vector2<Field>,
+    pub phantom_ball: Option<(Point2<Field>, SystemTime)>,
+    /// Maps the true field to the field the robot believes to be localized in
+    pub localization_error: Isometry2<Field, Field>,
+    /// Sent to the cycler before every cycle, set by scripts with `set_overrides`
+    pub overrides: Overrides,
 
     pub cycler: Cycler<Interfake>,
     control_receiver: Receiver<(SystemTime, Database)>,
     spl_network_sender: Producer<crate::structs::spl_network::MainOutputs>,
+    overrides_sender: Sender<(SystemTime, Overrides)>,
 }
 
 impl Robot {
@@ -53,6 +62,8 @@ impl Robot {
         let (mut parameters_sender, parameters_receiver) =
             buffered_watch::channel((UNIX_EPOCH, Default::default()));
         let (spl_network_sender, spl_network_consumer) = future_queue();
+        let (overrides_sender, overrides_receiver) =
+            buffered_watch::channel((UNIX_EPOCH, Default::default()));
         let (recording_sender, _recording_receiver) = mpsc::sync_channel(0);
         *parameters_sender.borrow_mut() = (SystemTime::now(), parameter.clone());
 
@@ -63,8 +74,12 @@ impl Robot {
             subscriptions_receiver,
             parameters_receiver,
             spl_network_consumer,
+            overrides_receiver,
             recording_sender,
-            RecordingTrigger::new(0),
+            RecordingTrigger::new(0, Vec::new(), &HashSet::new())?,
+            Vec::new(),
+            false,
+            &HashMap::new(),
         )?;
         cycler.cycler_state.motion_safe_exits = MotionSafeExits::fill(true);
 
@@ -92,10 +107,15 @@ impl Robot {
             is_penalized: false,
             last_kick_time: Duration::default(),
             ball_last_seen: None,
+            ball_measurement_error: Vector2::zeros(),
+            phantom_ball: None,
+            localization_error: Isometry2::identity(),
+            overrides: Overrides::default(),
 
             cycler,
             control_receiver,
             spl_network_sender,
+            overrides_sender,
         })
     }
 
@@ -114,6 +134,7 @@ impl Robot {
             &mut self.interface.get_last_database_sender().lock(),
         )
         .main_outputs = self.database.main_outputs.clone();
+        *self.overrides_sender.borrow_mut() = (SystemTime::now(), self.overrides.clone());
 
         self.cycler.cycle()?;
 
@@ -5,6 +5,7 @@ use std::{
 
 use crate::{
     cyclers::control::Database,
+    opponent::Opponent,
     robot::to_player_number,
     simulator::{Frame, Simulator},
 };
@@ -34,6 +35,7 @@ struct MainOutputs {
     frame_count: usize,
     ball: Option<SimulatorBallState>,
     databases: Players<Option<Database>>,
+    opponents: Vec<Opponent>,
 }
 
 #[derive(Clone, Default, Serialize, PathSerialize, PathIntrospect)]
@@ -70,6 +72,7 @@ async fn timeline_server(
             let frame = &frames[parameters.selected_frame];
             outputs.main_outputs.ball.clone_from(&frame.ball);
             outputs.main_outputs.databases = frame.robots.clone();
+            outputs.main_outputs.opponents.clone_from(&frame.opponents);
         }
 
         {
@@ -1,15 +1,19 @@
 use std::{
     fs::read_to_string,
+    mem::take,
     path::Path,
     sync::Arc,
     time::{Duration, UNIX_EPOCH},
 };
 
 use color_eyre::{
-    eyre::{eyre, WrapErr},
+    eyre::{bail, eyre, WrapErr},
     Result,
 };
-use mlua::{Error as LuaError, Function, Lua, LuaSerdeExt, SerializeOptions, Value, Variadic};
+use mlua::{
+    Error as LuaError, Function, Lua, LuaSerdeExt, RegistryKey, SerializeOptions, Table, Value,
+    Variadic,
+};
 use parking_lot::Mutex;
 
 use coordinate_systems::Field;
@@ -18,6 +22,8 @@ use types::{ball_position::SimulatorBallState, obstacles::Obstacle, players::Pla
 
 use crate::{
     cyclers::control::Database,
+    metrics::MetricsCollector,
+    opponent::{Opponent, Policy},
     robot::{to_player_number, Robot},
     state::{Event, LuaRobot, State},
 };
@@ -27,17 +33,36 @@ const SERIALIZE_OPTIONS: SerializeOptions = SerializeOptions::new().serialize_no
 pub struct Frame {
     pub ball: Option<SimulatorBallState>,
     pub robots: Players<Option<Database>>,
+    pub opponents: Vec<Opponent>,
+}
+
+#[derive(Clone, Copy)]
+enum AssertionKind {
+    /// The condition has to become true before the deadline
+    Eventually,
+    /// The condition has to stay true until the deadline
+    Always,
+}
+
+struct Assertion {
+    kind: AssertionKind,
+    condition: RegistryKey,
+    deadline: Duration,
+    message: String,
 }
 
 pub struct Simulator {
     pub state: Arc<Mutex<State>>,
     pub frames: Vec<Frame>,
+    pub metrics: MetricsCollector,
     lua: Lua,
+    robots_metatable: RegistryKey,
+    assertions: Arc<Mutex<Vec<Assertion>>>,
 }
 
 impl Simulator {
     pub fn try_new() -> Result<Self> {
-        let state = Arc::new(Mutex::new(State::default()));
+        let state = Arc::new(Mutex::new(State::try_new()?));
 
         let lua = Lua::new();
 
@@ -60,6 +85,19 @@ impl Simulator {
         lua.globals()
             .set("create_robot", create_robot)
             .wrap_err("failed to insert `create_robot` function")?;
+        let create_opponent = lua
+            .create_function(|lua, (position, policy): (Value, Option<Value>)| {
+                let position: Point2<Field> = lua.from_value(position)?;
+                let policy: Policy = policy
+                    .map(|policy| lua.from_value(policy))
+                    .transpose()?
+                    .unwrap_or_default();
+                lua.to_value(&Opponent::new(position, policy))
+            })
+            .wrap_err("failed to create function `create_opponent`")?;
+        lua.globals()
+            .set("create_opponent", create_opponent)
+            .wrap_err("failed to insert `create_opponent` function")?;
         let error = lua
             .create_function(|_lua, message: String| -> Result<(), LuaError> {
                 Err(LuaError::external(message))
@@ -69,13 +107,60 @@ impl Simulator {
             .set("error", error)
             .wrap_err("failed to insert `error` function")?;
 
+        let get_main_outputs = create_main_outputs_function(&lua, &state)
+            .wrap_err("failed to create function `get_main_outputs`")?;
+        lua.globals()
+            .set("get_main_outputs", get_main_outputs)
+            .wrap_err("failed to insert `get_main_outputs` function")?;
+        let get_overrides = create_get_overrides_function(&lua, &state)
+            .wrap_err("failed to create function `get_overrides`")?;
+        lua.globals()
+            .set("get_overrides", get_overrides)
+            .wrap_err("failed to insert `get_overrides` function")?;
+        let set_overrides = create_set_overrides_function(&lua, &state)
+            .wrap_err("failed to create function `set_overrides`")?;
+        lua.globals()
+            .set("set_overrides", set_overrides)
+            .wrap_err("failed to insert `set_overrides` function")?;
+        let robots_metatable = create_robots_metatable(&lua, &state)
+            .and_then(|metatable| lua.create_registry_value(metatable))
+            .wrap_err("failed to create metatable of `state.robots`")?;
+
+        let assertions = Arc::new(Mutex::new(Vec::new()));
+        for (name, kind) in [
+            ("assert_eventually", AssertionKind::Eventually),
+            ("assert_always", AssertionKind::Always),
+        ] {
+            let assert = create_assertion_function(&lua, &state, &assertions, kind)
+                .wrap_err_with(|| format!("failed to create function `{name}`"))?;
+            lua.globals()
+                .set(name, assert)
+                .wrap_err_with(|| format!("failed to insert `{name}` function"))?;
+        }
+
         Ok(Self {
             state,
             lua,
+            robots_metatable,
             frames: Vec::new(),
+            metrics: MetricsCollector::default(),
+            assertions,
         })
     }
 
+    /// Seeds the simulated network and the random number generator available to scripts via
+    /// `math.random`
+    pub fn set_seed(&mut self, seed: u64) -> Result<()> {
+        let mut state = self.state.lock();
+        state.network.seed(seed);
+        state.perception.seed(seed);
+        drop(state);
+        self.lua
+            .load(&format!("math.randomseed({seed})"))
+            .exec()
+            .wrap_err("failed to seed random number generator")
+    }
+
     pub fn execute_script(&mut self, file_name: impl AsRef<Path>) -> Result<()> {
         self.serialze_state()?;
 
@@ -107,6 +192,7 @@ impl Simulator {
             self.frames.push(Frame {
                 robots,
                 ball: state.ball,
+                opponents: state.opponents.clone(),
             });
 
             if state.finished {
@@ -114,13 +200,28 @@ impl Simulator {
             }
         }
 
+        if let Some(assertion) = self
+            .assertions
+            .lock()
+            .iter()
+            .find(|assertion| matches!(assertion.kind, AssertionKind::Eventually))
+        {
+            bail!(
+                "assertion failed before the scenario finished: {}",
+                assertion.message
+            );
+        }
+
         Ok(())
     }
 
     pub fn cycle(&mut self) -> Result<()> {
+        let time_step = Duration::from_millis(12);
         let events = {
             let mut state = self.state.lock();
-            state.cycle(Duration::from_millis(12))?
+            let events = state.cycle(time_step)?;
+            self.metrics.update(&state, &events, time_step);
+            events
         };
 
         self.serialze_state()?;
@@ -315,9 +416,18 @@ impl Simulator {
             for event in events {
                 match event {
                     Event::Cycle => self.execute_event_callback("on_cycle")?,
-                    Event::Goal => self.execute_event_callback("on_goal")?,
+                    Event::BallOut => self.execute_event_callback("on_ball_out")?,
+                    Event::OwnGoal => {
+                        self.execute_event_callback("on_own_goal")?;
+                        self.execute_event_callback("on_goal")?;
+                    }
+                    Event::OpponentGoal => {
+                        self.execute_event_callback("on_opponent_goal")?;
+                        self.execute_event_callback("on_goal")?;
+                    }
                 }
             }
+            self.check_assertions()?;
 
             Ok(())
         })?;
@@ -325,6 +435,37 @@ impl Simulator {
         self.deserialize_state()
     }
 
+    fn check_assertions(&self) -> Result<(), LuaError> {
+        let now = self.state.lock().time_elapsed;
+        // conditions may register new assertions, so the lock must not be held while calling them
+        let assertions = take(&mut *self.assertions.lock());
+        let mut pending = Vec::new();
+        for assertion in assertions {
+            let condition: Function = self.lua.registry_value(&assertion.condition)?;
+            let is_satisfied: bool = condition.call(())?;
+            let is_expired = now >= assertion.deadline;
+            match assertion.kind {
+                AssertionKind::Eventually if is_satisfied => continue,
+                AssertionKind::Eventually if is_expired => {
+                    return Err(LuaError::external(format!(
+                        "assertion failed: {} was not satisfied in time",
+                        assertion.message
+                    )))
+                }
+                AssertionKind::Always if !is_satisfied => {
+                    return Err(LuaError::external(format!(
+                        "assertion failed: {} was violated",
+                        assertion.message
+                    )))
+                }
+                AssertionKind::Always if is_expired => continue,
+                _ => pending.push(assertion),
+            }
+        }
+        self.assertions.lock().extend(pending);
+        Ok(())
+    }
+
     fn execute_event_callback(&self, name: &str) -> Result<(), LuaError> {
         let Ok(on_goal) = self.lua.globals().get::<_, Function>(name) else {
             return Ok(());
@@ -339,6 +480,13 @@ impl Simulator {
             .lua
             .to_value_with(&lua_state, SERIALIZE_OPTIONS)
             .wrap_err("failed to serialize lua state")?;
+        let Value::Table(table) = &value else {
+            bail!("lua state is not serialized to a table");
+        };
+        let robots: Table = table
+            .get("robots")
+            .wrap_err("failed to retrieve robots from lua state")?;
+        robots.set_metatable(Some(self.lua.registry_value(&self.robots_metatable)?));
         self.lua
             .globals()
             .set("state", value)
@@ -361,3 +509,93 @@ impl Simulator {
             .wrap_err("failed to load lua state")
     }
 }
+
+fn create_assertion_function(
+    lua: &Lua,
+    state: &Arc<Mutex<State>>,
+    assertions: &Arc<Mutex<Vec<Assertion>>>,
+    kind: AssertionKind,
+) -> mlua::Result<Function> {
+    let state = state.clone();
+    let assertions = assertions.clone();
+    lua.create_function(
+        move |lua, (condition, timeout, message): (Function, f32, Option<String>)| {
+            let deadline = state.lock().time_elapsed + Duration::from_secs_f32(timeout.max(0.0));
+            assertions.lock().push(Assertion {
+                kind,
+                condition: lua.create_registry_value(condition)?,
+                deadline,
+                message: message.unwrap_or_else(|| "condition".to_string()),
+            });
+            Ok(())
+        },
+    )
+}
+
+fn create_main_outputs_function(lua: &Lua, state: &Arc<Mutex<State>>) -> mlua::Result<Function> {
+    let state = state.clone();
+    lua.create_function(move |lua, player_number: usize| {
+        let player_number = to_player_number(player_number).map_err(LuaError::external)?;
+
+        let state = state.lock();
+        let robot = state
+            .robots
+            .get(&player_number)
+            .ok_or_else(|| LuaError::external(format!("robot {player_number:?} does not exist")))?;
+        lua.to_value_with(&robot.database.main_outputs, SERIALIZE_OPTIONS)
+    })
+}
+
+fn create_get_overrides_function(lua: &Lua, state: &Arc<Mutex<State>>) -> mlua::Result<Function> {
+    let state = state.clone();
+    lua.create_function(move |lua, player_number: usize| {
+        let player_number = to_player_number(player_number).map_err(LuaError::external)?;
+
+        let state = state.lock();
+        let robot = state
+            .robots
+            .get(&player_number)
+            .ok_or_else(|| LuaError::external(format!("robot {player_number:?} does not exist")))?;
+        lua.to_value_with(&robot.overrides, SERIALIZE_OPTIONS)
+    })
+}
+
+fn create_set_overrides_function(lua: &Lua, state: &Arc<Mutex<State>>) -> mlua::Result<Function> {
+    let state = state.clone();
+    lua.create_function(move |lua, (player_number, overrides): (usize, Value)| {
+        let player_number = to_player_number(player_number).map_err(LuaError::external)?;
+
+        let mut state = state.lock();
+        let robot = state
+            .robots
+            .get_mut(&player_number)
+            .ok_or_else(|| LuaError::external(format!("robot {player_number:?} does not exist")))?;
+        robot.overrides = lua.from_value(overrides)?;
+        Ok(())
+    })
+}
+
+/// `state.robots` stays empty so that scripts can insert robots to spawn them, existing robots
+/// are looked up by player number and their main outputs are only serialized when accessed
+fn create_robots_metatable(lua: &Lua, state: &Arc<Mutex<State>>) -> mlua::Result<Table> {
+    let state = state.clone();
+    let index = lua.create_function(move |lua, (_robots, player_number): (Table, usize)| {
+        let Ok(player_number) = to_player_number(player_number) else {
+            return Ok(Value::Nil);
+        };
+
+        let state = state.lock();
+        let Some(robot) = state.robots.get(&player_number) else {
+            return Ok(Value::Nil);
+        };
+        let lua_robot = lua.create_table()?;
+        lua_robot.set(
+            "main_outputs",
+            lua.to_value_with(&robot.database.main_outputs, SERIALIZE_OPTIONS)?,
+        )?;
+        Ok(Value::Table(lua_robot))
+    })?;
+    let metatable = lua.create_table()?;
+    metatable.set("__index", index)?;
+    Ok(metatable)
+}
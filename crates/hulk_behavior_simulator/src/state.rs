@@ -5,38 +5,60 @@ use std::{
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use color_eyre::Result;
+use color_eyre::{eyre::WrapErr, Result};
 use serde::{Deserialize, Serialize};
 
-use coordinate_systems::Head;
+use coordinate_systems::{Field, Ground, Head};
 use geometry::line_segment::LineSegment;
-use linear_algebra::{vector, Isometry2, Orientation2, Point2, Rotation2, Vector2};
+use linear_algebra::{distance, vector, Isometry2, Orientation2, Point2, Rotation2, Vector2};
+use parameters::directory::deserialize;
 use spl_network_messages::{GamePhase, GameState, HulkMessage, PlayerNumber, Team};
 use types::{
     ball_position::{BallPosition, SimulatorBallState},
+    field_dimensions::FieldDimensions,
     game_controller_state::GameControllerState,
+    hardware::Ids,
     messages::OutgoingMessage,
     motion_command::{HeadMotion, KickVariant, MotionCommand, OrientationMode},
+    obstacles::Obstacle,
     planned_path::PathSegment,
     players::Players,
     support_foot::Side,
 };
 
-use crate::{cyclers::control::Database, robot::Robot, structs::Parameters};
+use crate::{
+    ball::{simulate_ball, BallPhysics},
+    cyclers::control::Database,
+    network::{Network, NetworkParameters},
+    opponent::{Opponent, ROBOT_RADIUS_AT_FOOT_HEIGHT, ROBOT_RADIUS_AT_HIP_HEIGHT},
+    perception::{is_occluded, Perception, PerceptionParameters, MAXIMUM_DETECTION_DISTANCE},
+    referee::{Referee, RefereeParameters},
+    robot::Robot,
+    structs::Parameters,
+};
 
 pub enum Event {
     Cycle,
-    Goal,
+    BallOut,
+    OwnGoal,
+    OpponentGoal,
 }
 
 pub struct State {
     pub time_elapsed: Duration,
     pub cycle_count: usize,
     pub robots: HashMap<PlayerNumber, Robot>,
+    pub opponents: Vec<Opponent>,
     pub ball: Option<SimulatorBallState>,
     pub messages: Vec<(PlayerNumber, HulkMessage)>,
     pub finished: bool,
     pub game_controller_state: GameControllerState,
+    pub field_dimensions: FieldDimensions,
+    pub ball_physics: BallPhysics,
+    pub referee: Referee,
+    pub last_ball_contact: Option<Team>,
+    pub network: Network,
+    pub perception: Perception,
 }
 
 impl State {
@@ -46,9 +68,14 @@ impl State {
         let mut events = vec![Event::Cycle];
 
         self.move_robots(time_step);
-        self.cycle_robots(now)?;
+        self.move_opponents(time_step);
+        self.cycle_robots(now, time_step)?;
         events.extend(self.move_ball(time_step));
 
+        let mut referee = take(&mut self.referee);
+        referee.cycle(self, time_step, &events);
+        self.referee = referee;
+
         self.time_elapsed += time_step;
         self.cycle_count += 1;
 
@@ -132,6 +159,7 @@ impl State {
                             };
                             ball.velocity += *ground_to_field * direction * *strength * 2.5;
                             robot.last_kick_time = self.time_elapsed;
+                            self.last_ball_contact = Some(Team::Hulks);
                         };
                     }
                     head
@@ -167,8 +195,52 @@ impl State {
         }
     }
 
-    fn cycle_robots(&mut self, now: std::time::SystemTime) -> Result<()> {
+    fn move_opponents(&mut self, time_step: Duration) {
+        if !matches!(self.game_controller_state.game_state, GameState::Playing) {
+            return;
+        }
+
+        let robot_positions: HashMap<PlayerNumber, Point2<Field>> = self
+            .robots
+            .iter()
+            .filter_map(|(player_number, robot)| {
+                let ground_to_field = robot.database.main_outputs.ground_to_field?;
+                Some((*player_number, ground_to_field.translation()))
+            })
+            .collect();
+
+        for opponent in &mut self.opponents {
+            opponent.step(
+                time_step,
+                self.ball.as_ref(),
+                |player_number| robot_positions.get(&player_number).copied(),
+                &self.field_dimensions,
+            );
+            if let Some(ball) = self.ball.as_mut() {
+                if opponent.kick_ball(ball, self.time_elapsed, &self.field_dimensions) {
+                    self.last_ball_contact = Some(Team::Opponent);
+                }
+            }
+        }
+    }
+
+    fn cycle_robots(&mut self, now: std::time::SystemTime, time_step: Duration) -> Result<()> {
         let messages_sent_last_cycle = take(&mut self.messages);
+        self.network.send(
+            &messages_sent_last_cycle,
+            self.robots.keys().copied().collect(),
+            self.time_elapsed,
+            time_step,
+        );
+
+        let robot_positions: Vec<Point2<Field>> = self
+            .robots
+            .values()
+            .filter_map(|robot| robot.database.main_outputs.ground_to_field)
+            .map(|ground_to_field| ground_to_field.translation())
+            .chain(self.opponents.iter().map(|opponent| opponent.position))
+            .collect();
+        let occlusion = self.perception.parameters.occlusion;
 
         for (player_number, robot) in self.robots.iter_mut() {
             robot.database.main_outputs.cycle_time.start_time = now;
@@ -178,68 +250,138 @@ impl State {
                 .main_outputs
                 .ground_to_field
                 .expect("simulated robots should always have a known pose");
-            let ball_visible = self.ball.as_ref().is_some_and(|ball| {
-                let ball_in_ground = ground_to_field.inverse() * ball.position;
-                let head_to_ground =
-                    Rotation2::new(robot.database.main_outputs.sensor_data.positions.head.yaw);
-                let ball_in_head: Point2<Head> = head_to_ground.inverse() * ball_in_ground;
-                let field_of_view = robot.field_of_view();
-                let angle_to_ball = ball_in_head.coords().angle(Vector2::x_axis());
-
-                angle_to_ball.abs() < field_of_view / 2.0 && ball_in_head.coords().norm() < 3.0
-            });
-            if ball_visible {
-                robot.ball_last_seen = Some(now);
+            let head_to_ground: Rotation2<Head, Ground> =
+                Rotation2::new(robot.database.main_outputs.sensor_data.positions.head.yaw);
+            let field_of_view = robot.field_of_view();
+            let is_visible = |position: Point2<Field>| {
+                let position_in_ground = ground_to_field.inverse() * position;
+                let position_in_head: Point2<Head> = head_to_ground.inverse() * position_in_ground;
+                let angle_to_position = position_in_head.coords().angle(Vector2::x_axis());
+
+                angle_to_position.abs() < field_of_view / 2.0
+                    && position_in_head.coords().norm() < MAXIMUM_DETECTION_DISTANCE
+                    && !(occlusion
+                        && is_occluded(ground_to_field.translation(), position, &robot_positions))
+            };
+            if let Some(ball) = self.ball.as_ref().filter(|ball| is_visible(ball.position)) {
+                if self.perception.detects_ball() {
+                    robot.ball_last_seen = Some(now);
+                    robot.ball_measurement_error = self.perception.ball_measurement_error(
+                        distance(ground_to_field.translation(), ball.position),
+                    );
+                }
             }
-            robot.database.main_outputs.ball_position =
-                if robot.ball_last_seen.is_some_and(|last_seen| {
-                    now.duration_since(last_seen).expect("time ran backwards")
-                        < robot.parameters.ball_filter.hypothesis_timeout
-                }) {
-                    self.ball.as_ref().map(|ball| BallPosition {
-                        position: ground_to_field.inverse() * ball.position,
-                        velocity: ground_to_field.inverse() * ball.velocity,
-                        last_seen: now,
-                    })
-                } else {
-                    None
-                };
+            if let Some(phantom_ball) = self.perception.phantom_ball(time_step, field_of_view) {
+                robot.phantom_ball = Some((ground_to_field * (head_to_ground * phantom_ball), now));
+            }
+
+            let hypothesis_timeout = robot.parameters.ball_filter.hypothesis_timeout;
+            let is_recent = |last_seen: SystemTime| {
+                now.duration_since(last_seen).expect("time ran backwards") < hypothesis_timeout
+            };
+            let phantom_ball = robot.phantom_ball.filter(|(_, last_seen)| {
+                is_recent(*last_seen)
+                    && robot
+                        .ball_last_seen
+                        .map_or(true, |ball_last_seen| *last_seen > ball_last_seen)
+            });
+            robot.database.main_outputs.ball_position = if let Some((position, _)) = phantom_ball {
+                Some(BallPosition {
+                    position: ground_to_field.inverse() * position,
+                    velocity: Vector2::zeros(),
+                    last_seen: now,
+                })
+            } else if robot
+                .ball_last_seen
+                .is_some_and(|last_seen| is_recent(last_seen))
+            {
+                self.ball.as_ref().map(|ball| BallPosition {
+                    position: ground_to_field.inverse()
+                        * (ball.position + robot.ball_measurement_error),
+                    velocity: ground_to_field.inverse() * ball.velocity,
+                    last_seen: now,
+                })
+            } else {
+                None
+            };
             robot.database.main_outputs.game_controller_state = Some(self.game_controller_state);
-            robot.cycler.cycler_state.ground_to_field = ground_to_field;
-            robot.cycle(&messages_sent_last_cycle)?;
+
+            // the robot acts on its believed pose while the simulation keeps the true one
+            self.perception.update_localization_error(
+                &mut robot.localization_error,
+                ground_to_field,
+                time_step,
+            );
+            let believed_ground_to_field = robot.localization_error * ground_to_field;
+            robot.database.main_outputs.ground_to_field = Some(believed_ground_to_field);
+            robot.cycler.cycler_state.ground_to_field = believed_ground_to_field;
+
+            let scripted_obstacles = robot.database.main_outputs.obstacles.clone();
+            robot.database.main_outputs.obstacles.extend(
+                self.opponents
+                    .iter()
+                    .filter(|opponent| is_visible(opponent.position))
+                    .map(|opponent| {
+                        Obstacle::robot(
+                            ground_to_field.inverse() * opponent.position,
+                            ROBOT_RADIUS_AT_FOOT_HEIGHT,
+                            ROBOT_RADIUS_AT_HIP_HEIGHT,
+                        )
+                    }),
+            );
+            let incoming_messages = self.network.receive(*player_number, self.time_elapsed);
+            robot.cycle(&incoming_messages)?;
+            robot.database.main_outputs.obstacles = scripted_obstacles;
+            robot.database.main_outputs.ground_to_field = Some(ground_to_field);
 
             for message in robot.interface.take_outgoing_messages() {
-                if let OutgoingMessage::Spl(message) = message {
-                    self.messages.push((*player_number, message));
-                    self.game_controller_state.remaining_amount_of_messages -= 1
+                let OutgoingMessage::Spl(message) = message else {
+                    continue;
+                };
+                // messages exceeding the team's budget are not delivered
+                if self.game_controller_state.remaining_amount_of_messages == 0 {
+                    continue;
                 }
+                self.messages.push((*player_number, message));
+                self.game_controller_state.remaining_amount_of_messages -= 1
             }
         }
 
         Ok(())
     }
 
-    fn move_ball(&mut self, time_step: Duration) -> Vec<Event> {
-        let mut events = Vec::new();
-        if let Some(ball) = self.ball.as_mut() {
-            ball.position += ball.velocity * time_step.as_secs_f32();
-            ball.velocity *= 0.98;
-
-            if ball.position.x().abs() > 4.5 && ball.position.y() < 0.75 {
-                events.push(Event::Goal);
-            }
-        }
-        events
+    fn move_ball(&mut self, time_step: Duration) -> Option<Event> {
+        let ball = self.ball.as_mut()?;
+        let robot_positions: Vec<Point2<Field>> = self
+            .robots
+            .values()
+            .filter_map(|robot| robot.database.main_outputs.ground_to_field)
+            .map(|ground_to_field| ground_to_field.translation())
+            .chain(self.opponents.iter().map(|opponent| opponent.position))
+            .collect();
+
+        simulate_ball(
+            ball,
+            time_step,
+            &robot_positions,
+            &self.ball_physics,
+            &self.field_dimensions,
+        )
     }
 
     pub fn get_lua_state(&self) -> LuaState {
         LuaState {
             time_elapsed: self.time_elapsed.as_secs_f32(),
             cycle_count: self.cycle_count,
-            // TODO: Expose robot data to lua again
-            // robots: self.robots.iter().map(LuaRobot::new).collect(),
+            // scripts only insert robots to spawn them, round-tripping all databases every cycle
+            // is too slow, so they are read on demand with `get_main_outputs` instead
             robots: Default::default(),
+            opponents: self.opponents.clone(),
             ball: self.ball,
+            ball_physics: self.ball_physics,
+            referee: self.referee.parameters,
+            network: self.network.parameters.clone(),
+            perception: self.perception.parameters,
             messages: self.messages.clone(),
 
             finished: self.finished,
@@ -250,6 +392,10 @@ impl State {
 
     pub fn load_lua_state(&mut self, lua_state: LuaState) -> Result<()> {
         self.ball = lua_state.ball;
+        self.ball_physics = lua_state.ball_physics;
+        self.referee.parameters = lua_state.referee;
+        self.network.parameters = lua_state.network;
+        self.perception.parameters = lua_state.perception;
         self.cycle_count = lua_state.cycle_count;
         for lua_robot in lua_state.robots {
             let mut robot = Robot::try_new(lua_robot.parameters.player_number)
@@ -258,6 +404,7 @@ impl State {
             robot.parameters = lua_robot.parameters;
             self.robots.insert(robot.parameters.player_number, robot);
         }
+        self.opponents = lua_state.opponents;
 
         self.finished = lua_state.finished;
 
@@ -267,8 +414,19 @@ impl State {
     }
 }
 
-impl Default for State {
-    fn default() -> Self {
+impl State {
+    pub fn try_new() -> Result<Self> {
+        let ids = Ids {
+            body_id: "behavior_simulator".to_string(),
+            head_id: "behavior_simulator".to_string(),
+        };
+        let parameters: Parameters = deserialize("etc/parameters", &ids, true)
+            .wrap_err("could not load initial parameters")?;
+
+        Ok(Self::new(parameters.field_dimensions))
+    }
+
+    pub fn new(field_dimensions: FieldDimensions) -> Self {
         let robots = HashMap::new();
         let game_controller_state = GameControllerState {
             game_state: GameState::Initial,
@@ -302,10 +460,17 @@ impl Default for State {
             time_elapsed: Duration::ZERO,
             cycle_count: 0,
             robots,
+            opponents: Vec::new(),
             ball: None,
             messages: Vec::new(),
             finished: false,
             game_controller_state,
+            field_dimensions,
+            ball_physics: BallPhysics::default(),
+            referee: Referee::default(),
+            last_ball_contact: None,
+            network: Network::default(),
+            perception: Perception::default(),
         }
     }
 }
@@ -315,7 +480,12 @@ pub struct LuaState {
     pub time_elapsed: f32,
     pub cycle_count: usize,
     pub robots: Vec<LuaRobot>,
+    pub opponents: Vec<Opponent>,
     pub ball: Option<SimulatorBallState>,
+    pub ball_physics: BallPhysics,
+    pub referee: RefereeParameters,
+    pub network: NetworkParameters,
+    pub perception: PerceptionParameters,
     pub messages: Vec<(PlayerNumber, HulkMessage)>,
     pub finished: bool,
     pub game_controller_state: GameControllerState,
@@ -1,18 +1,23 @@
-use code_generation::{generate, write_to_file::WriteToFile, ExecutionMode};
+use code_generation::{
+    cyclers::commit_hash_watch_paths, generate, write_to_file::WriteToFile, ExecutionMode,
+};
 use color_eyre::eyre::{Result, WrapErr};
-use hulk_manifest::collect_hulk_cyclers;
+use hulk_manifest::{collect_hulk_cyclers, collect_hulk_cyclers_for_target, MANIFEST_PATH};
 use source_analyzer::{pretty::to_string_pretty, structs::Structs};
 
 fn main() -> Result<()> {
-    #[allow(unused_mut)] // must not be mut if "with_detection" feature is disabled
-    let mut cyclers = collect_hulk_cyclers()?;
-    #[cfg(not(feature = "with_object_detection"))]
-    cyclers
-        .cyclers
-        .retain(|cycler| cycler.name != "ObjectDetection");
+    let cyclers = if cfg!(feature = "with_object_detection") {
+        collect_hulk_cyclers()?
+    } else {
+        collect_hulk_cyclers_for_target("replayer_without_object_detection")?
+    };
+    println!("cargo:rerun-if-changed={MANIFEST_PATH}");
     for path in cyclers.watch_paths() {
         println!("cargo:rerun-if-changed={}", path.display());
     }
+    for path in commit_hash_watch_paths() {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
 
     println!();
     println!("{}", to_string_pretty(&cyclers)?);
@@ -0,0 +1,250 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{write, File},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use clap::Parser;
+use color_eyre::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+use parameters::json::{clone_nested_value, merge_json, nest_value_at_path};
+use serde::Serialize;
+use serde_json::{from_reader, from_str, to_string_pretty, Value};
+use tempfile::tempdir;
+
+use crate::replayer::headless_replayer;
+
+#[derive(Parser)]
+pub struct ExportArguments {
+    /// Directory containing the recording and its parameters
+    replay_path: PathBuf,
+    /// File to write the time series to, the format is chosen by its extension (`csv` or `json`)
+    output_file: PathBuf,
+    /// Path to export as `<CyclerInstance>.<path>`, e.g. `Control.main_outputs.ground_to_field`
+    #[arg(long = "output", short = 'o', required = true)]
+    outputs: Vec<String>,
+    /// Overrides a recorded parameter as `<path>=<JSON value>`, e.g. `localization.line_measurement_noise=[500.0,320.0]`
+    #[arg(long = "parameter", short = 'p')]
+    parameters: Vec<String>,
+    /// Additionally replays with the recorded parameters to diff the outputs against this baseline
+    #[arg(long)]
+    diff: bool,
+}
+
+#[derive(Serialize)]
+struct Sample {
+    timestamp: f64,
+    value: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    baseline: Option<Value>,
+}
+
+enum Format {
+    Csv,
+    Json,
+}
+
+/// Replays a recording with overridden parameters and exports the selected outputs as time series
+pub fn export(arguments: ExportArguments) -> Result<()> {
+    let format = match arguments
+        .output_file
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("csv") => Format::Csv,
+        Some("json") => Format::Json,
+        _ => bail!("expected output file with extension `csv` or `json`"),
+    };
+    let outputs = arguments
+        .outputs
+        .iter()
+        .map(|output| {
+            output
+                .split_once('.')
+                .ok_or_else(|| eyre!("expected `<CyclerInstance>.<path>`, got `{output}`"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let recorded_parameters_path = arguments.replay_path.join("default.json");
+    let file = File::open(&recorded_parameters_path)
+        .wrap_err_with(|| format!("failed to open {recorded_parameters_path:?}"))?;
+    let recorded_parameters: Value =
+        from_reader(file).wrap_err("failed to parse recorded parameters")?;
+    let mut parameters = recorded_parameters.clone();
+    for parameter in &arguments.parameters {
+        let (path, value) = parameter
+            .split_once('=')
+            .ok_or_else(|| eyre!("expected `<path>=<JSON value>`, got `{parameter}`"))?;
+        // unknown parameters would be ignored silently by the replayer
+        if clone_nested_value(&recorded_parameters, path).is_none() {
+            bail!("parameter `{path}` does not exist in the recorded parameters");
+        }
+        let value =
+            from_str(value).wrap_err_with(|| format!("failed to parse value of `{path}`"))?;
+        merge_json(&mut parameters, &nest_value_at_path(path, value));
+    }
+    let parameters_directory = tempdir().wrap_err("failed to create parameters directory")?;
+    write(
+        parameters_directory.path().join("default.json"),
+        to_string_pretty(&parameters)?,
+    )
+    .wrap_err("failed to write parameters")?;
+
+    let mut time_series = replay_outputs(
+        parameters_directory.path(),
+        &arguments.replay_path,
+        &outputs,
+    )
+    .wrap_err("failed to replay with overridden parameters")?;
+    if arguments.diff {
+        let baseline = replay_outputs(&arguments.replay_path, &arguments.replay_path, &outputs)
+            .wrap_err("failed to replay with recorded parameters")?;
+        for (output, samples) in &mut time_series {
+            for (sample, baseline_sample) in samples.iter_mut().zip(&baseline[output]) {
+                sample.baseline = Some(baseline_sample.value.clone());
+            }
+        }
+    }
+
+    let contents = match format {
+        Format::Csv => to_csv(&time_series, arguments.diff),
+        Format::Json => to_string_pretty(&time_series)?,
+    };
+    write(&arguments.output_file, contents)
+        .wrap_err_with(|| format!("failed to write {:?}", arguments.output_file))
+}
+
+fn replay_outputs(
+    parameters_directory: &Path,
+    replay_path: &Path,
+    outputs: &[(&str, &str)],
+) -> Result<BTreeMap<String, Vec<Sample>>> {
+    let mut replayer = headless_replayer(parameters_directory, replay_path)?;
+    let mut time_series: BTreeMap<_, Vec<_>> = outputs
+        .iter()
+        .map(|(instance_name, path)| (format!("{instance_name}.{path}"), Vec::new()))
+        .collect();
+
+    let instance_names: BTreeSet<_> = outputs
+        .iter()
+        .map(|(instance_name, _path)| *instance_name)
+        .collect();
+    for instance_name in instance_names {
+        let timings: Vec<_> = replayer
+            .get_recording_indices()
+            .get(instance_name)
+            .ok_or_else(|| eyre!("could not find recording of `{instance_name}`"))?
+            .iter()
+            .collect();
+        for timing in timings {
+            let frame = replayer
+                .get_recording_indices_mut()
+                .get_mut(instance_name)
+                .expect("recording index should exist")
+                .find_latest_frame_up_to(timing.timestamp)
+                .wrap_err("failed to find frame")?;
+            let Some(frame) = frame else {
+                continue;
+            };
+            replayer
+                .replay(instance_name, frame.timing.timestamp, &frame.data)
+                .wrap_err("failed to replay frame")?;
+
+            let timestamp = frame
+                .timing
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            for (output_instance_name, path) in outputs {
+                if *output_instance_name != instance_name {
+                    continue;
+                }
+                let value = replayer
+                    .serialize_database_path(instance_name, path)
+                    .wrap_err_with(|| format!("failed to export `{instance_name}.{path}`"))?;
+                time_series
+                    .get_mut(&format!("{instance_name}.{path}"))
+                    .expect("time series should exist for every output")
+                    .push(Sample {
+                        timestamp,
+                        value,
+                        baseline: None,
+                    });
+            }
+        }
+    }
+    Ok(time_series)
+}
+
+/// Writes one row per leaf value of each sample, nested values are flattened into their paths
+fn to_csv(time_series: &BTreeMap<String, Vec<Sample>>, with_baseline: bool) -> String {
+    let mut csv = if with_baseline {
+        "timestamp,path,value,baseline,difference\n".to_string()
+    } else {
+        "timestamp,path,value\n".to_string()
+    };
+    for (output, samples) in time_series {
+        for sample in samples {
+            let mut values = BTreeMap::new();
+            flatten(&sample.value, output, &mut values);
+            let mut baseline_values = BTreeMap::new();
+            if let Some(baseline) = &sample.baseline {
+                flatten(baseline, output, &mut baseline_values);
+            }
+            let paths: BTreeSet<_> = values.keys().chain(baseline_values.keys()).collect();
+            for path in paths {
+                let value = values.get(path).copied();
+                csv.push_str(&format!(
+                    "{},{},{}",
+                    sample.timestamp,
+                    path,
+                    to_csv_cell(value)
+                ));
+                if with_baseline {
+                    let baseline = baseline_values.get(path).copied();
+                    let difference = match (value, baseline) {
+                        (Some(Value::Number(value)), Some(Value::Number(baseline))) => value
+                            .as_f64()
+                            .zip(baseline.as_f64())
+                            .map(|(value, baseline)| (value - baseline).to_string())
+                            .unwrap_or_default(),
+                        _ => String::new(),
+                    };
+                    csv.push_str(&format!(",{},{difference}", to_csv_cell(baseline)));
+                }
+                csv.push('\n');
+            }
+        }
+    }
+    csv
+}
+
+fn flatten<'value>(value: &'value Value, path: &str, leafs: &mut BTreeMap<String, &'value Value>) {
+    match value {
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                flatten(item, &format!("{path}.{index}"), leafs);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                flatten(field, &format!("{path}.{key}"), leafs);
+            }
+        }
+        _ => {
+            leafs.insert(path.to_string(), value);
+        }
+    }
+}
+
+fn to_csv_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(string)) => format!("\"{}\"", string.replace('"', "\"\"")),
+        Some(value) => value.to_string(),
+    }
+}
@@ -1,18 +1,26 @@
 #![recursion_limit = "256"]
 mod coordinate_systems;
+mod export;
 mod frames;
 mod labels;
+mod mcap_export;
 mod replayer;
 mod ticks;
 mod timeline;
+mod verify;
 mod window;
 mod worker_thread;
 
+use std::env::args;
+
+use clap::Parser;
 use color_eyre::{eyre::Result, install};
+use export::{export, ExportArguments};
 use hardware::{
     ActuatorInterface, CameraInterface, IdInterface, MicrophoneInterface, NetworkInterface,
     PathsInterface, RecordingInterface, SensorInterface, SpeakerInterface,
 };
+use mcap_export::{export_mcap, McapArguments};
 use replayer::replayer;
 use types::{
     audio::SpeakerRequest,
@@ -25,6 +33,7 @@ use types::{
     sensor_data::SensorData,
     ycbcr422_image::YCbCr422Image,
 };
+use verify::{verify, VerifyArguments};
 
 pub trait HardwareInterface:
     ActuatorInterface
@@ -116,5 +125,11 @@ impl HardwareInterface for ReplayerHardwareInterface {}
 
 fn main() -> Result<()> {
     install()?;
-    replayer()
+    // headless modes are selected by the first argument, otherwise the first argument is the replay path
+    match args().nth(1).as_deref() {
+        Some("verify") => verify(VerifyArguments::parse_from(args().skip(1))),
+        Some("export") => export(ExportArguments::parse_from(args().skip(1))),
+        Some("mcap") => export_mcap(McapArguments::parse_from(args().skip(1))),
+        _ => replayer(),
+    }
 }
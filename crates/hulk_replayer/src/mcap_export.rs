@@ -0,0 +1,417 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ::coordinate_systems::{Field, Ground};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
+use color_eyre::{eyre::WrapErr, Result};
+use image::{codecs::jpeg::JpegEncoder, RgbImage};
+use linear_algebra::Isometry2;
+use mcap::{records::MessageHeader, Channel, Schema, Writer};
+use serde_json::{json, Map, Value};
+use types::{ball_position::BallPosition, obstacles::Obstacle, ycbcr422_image::YCbCr422Image};
+
+use crate::{execution::Replayer, replayer::headless_replayer, ReplayerHardwareInterface};
+
+/// Height of the cylinders representing obstacles, the obstacles themselves have no height
+const OBSTACLE_HEIGHT: f32 = 0.5;
+const JPEG_QUALITY: u8 = 90;
+
+#[derive(Parser)]
+pub struct McapArguments {
+    /// Directory containing the recording and its parameters
+    replay_path: PathBuf,
+    /// MCAP file to write
+    output_file: PathBuf,
+}
+
+/// Replays a recording and writes the main outputs of all cycler instances into an MCAP file
+pub fn export_mcap(arguments: McapArguments) -> Result<()> {
+    let mut replayer = headless_replayer(&arguments.replay_path, &arguments.replay_path)?;
+    let ball_radius = replayer
+        .get_parameters_receiver()
+        .borrow_and_mark_as_seen()
+        .1
+        .field_dimensions
+        .ball_radius;
+
+    // frames of all cycler instances in chronological order, as visualizers expect them
+    let mut frames: Vec<(SystemTime, String)> = replayer
+        .get_recording_indices()
+        .into_iter()
+        .flat_map(|(instance_name, index)| {
+            index
+                .iter()
+                .map(|timing| (timing.timestamp, instance_name.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    frames.sort();
+
+    let file = File::create(&arguments.output_file)
+        .wrap_err_with(|| format!("failed to create {:?}", arguments.output_file))?;
+    let mut writer = McapWriter::new(BufWriter::new(file))?;
+    let mut main_output_schemas = HashMap::new();
+    let mut control_receiver = replayer.control_receiver();
+    let mut vision_top_receiver = replayer.vision_top_receiver();
+    let mut vision_bottom_receiver = replayer.vision_bottom_receiver();
+
+    for (timestamp, instance_name) in frames {
+        let frame = replayer
+            .get_recording_indices_mut()
+            .get_mut(&instance_name)
+            .expect("recording index should exist")
+            .find_latest_frame_up_to(timestamp)
+            .wrap_err("failed to find frame")?;
+        let Some(frame) = frame else {
+            continue;
+        };
+        replayer
+            .replay(&instance_name, frame.timing.timestamp, &frame.data)
+            .wrap_err("failed to replay frame")?;
+
+        if !main_output_schemas.contains_key(&instance_name) {
+            let fields =
+                Replayer::<ReplayerHardwareInterface>::get_main_output_fields(&instance_name)?;
+            main_output_schemas.insert(instance_name.clone(), schemas_from_fields(&fields));
+        }
+        let mut schemas: Vec<_> = main_output_schemas[&instance_name].iter().collect();
+        schemas.sort_by_key(|(name, _)| *name);
+        for (name, schema) in schemas {
+            // images are written as compressed images instead, serializing them is expensive
+            if name == "image" {
+                continue;
+            }
+            let value = replayer
+                .serialize_database_path(&instance_name, &format!("main_outputs.{name}"))
+                .wrap_err_with(|| format!("failed to serialize main output {name}"))?;
+            let schema = json!({
+                "type": "object",
+                "properties": {
+                    name: schema,
+                },
+            });
+            let mut message = Map::new();
+            message.insert(name.clone(), value);
+            writer.write(
+                &format!("/{instance_name}/main_outputs/{name}"),
+                &format!("{instance_name}.main_outputs.{name}"),
+                &schema,
+                timestamp,
+                &Value::Object(message),
+            )?;
+        }
+
+        match instance_name.as_str() {
+            "Control" => {
+                let (_, database) = &*control_receiver.borrow_and_mark_as_seen();
+                if let Some(ground_to_field) = database.main_outputs.ground_to_field {
+                    writer.write(
+                        "/ground_to_field",
+                        "foxglove.FrameTransform",
+                        &frame_transform_schema(),
+                        timestamp,
+                        &frame_transform(timestamp, ground_to_field),
+                    )?;
+                }
+                writer.write(
+                    "/Control/scene",
+                    "foxglove.SceneUpdate",
+                    &scene_update_schema(),
+                    timestamp,
+                    &scene_update(
+                        timestamp,
+                        database.main_outputs.ball_position,
+                        ball_radius,
+                        &database.main_outputs.obstacles,
+                    ),
+                )?;
+            }
+            "VisionTop" | "VisionBottom" => {
+                let receiver = if instance_name == "VisionTop" {
+                    &mut vision_top_receiver
+                } else {
+                    &mut vision_bottom_receiver
+                };
+                let (_, database) = &*receiver.borrow_and_mark_as_seen();
+                writer.write(
+                    &format!("/{instance_name}/image"),
+                    "foxglove.CompressedImage",
+                    &compressed_image_schema(),
+                    timestamp,
+                    &compressed_image(timestamp, &instance_name, &database.main_outputs.image)?,
+                )?;
+            }
+            _ => {}
+        }
+    }
+
+    writer.finish()
+}
+
+struct McapWriter {
+    writer: Writer<'static, BufWriter<File>>,
+    channels: HashMap<String, u16>,
+    sequence: u32,
+}
+
+impl McapWriter {
+    fn new(file: BufWriter<File>) -> Result<Self> {
+        Ok(Self {
+            writer: Writer::new(file).wrap_err("failed to create MCAP writer")?,
+            channels: HashMap::new(),
+            sequence: 0,
+        })
+    }
+
+    /// Writes a JSON message, the channel of the topic is created with its first message
+    fn write(
+        &mut self,
+        topic: &str,
+        schema_name: &str,
+        schema: &Value,
+        timestamp: SystemTime,
+        message: &Value,
+    ) -> Result<()> {
+        let channel_id = match self.channels.get(topic) {
+            Some(channel_id) => *channel_id,
+            None => {
+                let channel = Channel {
+                    topic: topic.to_string(),
+                    schema: Some(Arc::new(Schema {
+                        name: schema_name.to_string(),
+                        encoding: "jsonschema".to_string(),
+                        data: Cow::Owned(serde_json::to_vec(schema)?),
+                    })),
+                    message_encoding: "json".to_string(),
+                    metadata: BTreeMap::new(),
+                };
+                let channel_id = self
+                    .writer
+                    .add_channel(&channel)
+                    .wrap_err_with(|| format!("failed to add channel {topic}"))?;
+                self.channels.insert(topic.to_string(), channel_id);
+                channel_id
+            }
+        };
+        let time = timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        self.writer
+            .write_to_known_channel(
+                &MessageHeader {
+                    channel_id,
+                    sequence: self.sequence,
+                    log_time: time,
+                    publish_time: time,
+                },
+                &serde_json::to_vec(message)?,
+            )
+            .wrap_err_with(|| format!("failed to write message to {topic}"))?;
+        self.sequence += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.writer.finish().wrap_err("failed to finish MCAP file")
+    }
+}
+
+/// Builds a JSON schema per main output from the field paths of `PathIntrospect`, nodes with
+/// children are objects while the type of leaves is left open
+fn schemas_from_fields(fields: &HashSet<String>) -> HashMap<String, Value> {
+    let mut schemas = HashMap::new();
+    let mut sorted_fields: Vec<_> = fields.iter().collect();
+    sorted_fields.sort();
+    for field in sorted_fields {
+        let mut segments = field.split('.');
+        let name = segments.next().expect("split yields at least one segment");
+        let mut schema = schemas.entry(name.to_string()).or_insert_with(|| json!({}));
+        for segment in segments {
+            let schema_object = schema.as_object_mut().expect("schemas are objects");
+            schema_object.insert("type".to_string(), json!("object"));
+            schema = schema_object
+                .entry("properties")
+                .or_insert_with(|| json!({}))
+                .as_object_mut()
+                .expect("properties are objects")
+                .entry(segment)
+                .or_insert_with(|| json!({}));
+        }
+    }
+    schemas
+}
+
+fn time(timestamp: SystemTime) -> Value {
+    let duration = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+    json!({
+        "sec": duration.as_secs(),
+        "nsec": duration.subsec_nanos(),
+    })
+}
+
+fn time_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "sec": { "type": "integer" },
+            "nsec": { "type": "integer" },
+        },
+    })
+}
+
+fn compressed_image(
+    timestamp: SystemTime,
+    instance_name: &str,
+    image: &YCbCr422Image,
+) -> Result<Value> {
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+        .encode_image(&RgbImage::from(image))
+        .wrap_err("failed to encode image")?;
+    Ok(json!({
+        "timestamp": time(timestamp),
+        "frame_id": instance_name,
+        "data": STANDARD.encode(jpeg),
+        "format": "jpeg",
+    }))
+}
+
+fn compressed_image_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "timestamp": time_schema(),
+            "frame_id": { "type": "string" },
+            "data": { "type": "string", "contentEncoding": "base64" },
+            "format": { "type": "string" },
+        },
+    })
+}
+
+fn frame_transform(timestamp: SystemTime, ground_to_field: Isometry2<Ground, Field>) -> Value {
+    let translation = ground_to_field.translation();
+    let half_angle = ground_to_field.orientation().angle() / 2.0;
+    json!({
+        "timestamp": time(timestamp),
+        "parent_frame_id": "field",
+        "child_frame_id": "ground",
+        "translation": { "x": translation.x(), "y": translation.y(), "z": 0.0 },
+        "rotation": { "x": 0.0, "y": 0.0, "z": half_angle.sin(), "w": half_angle.cos() },
+    })
+}
+
+fn vector3_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "x": { "type": "number" },
+            "y": { "type": "number" },
+            "z": { "type": "number" },
+        },
+    })
+}
+
+fn frame_transform_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "timestamp": time_schema(),
+            "parent_frame_id": { "type": "string" },
+            "child_frame_id": { "type": "string" },
+            "translation": vector3_schema(),
+            "rotation": {
+                "type": "object",
+                "properties": {
+                    "x": { "type": "number" },
+                    "y": { "type": "number" },
+                    "z": { "type": "number" },
+                    "w": { "type": "number" },
+                },
+            },
+        },
+    })
+}
+
+/// Ball and obstacles as primitives in the ground frame
+fn scene_update(
+    timestamp: SystemTime,
+    ball_position: Option<BallPosition<Ground>>,
+    ball_radius: f32,
+    obstacles: &[Obstacle],
+) -> Value {
+    let balls: Vec<_> = ball_position
+        .map(|ball| {
+            json!({
+                "pose": pose(ball.position.x(), ball.position.y(), ball_radius),
+                "size": { "x": 2.0 * ball_radius, "y": 2.0 * ball_radius, "z": 2.0 * ball_radius },
+                "color": { "r": 1.0, "g": 1.0, "b": 1.0, "a": 1.0 },
+            })
+        })
+        .into_iter()
+        .collect();
+    let obstacles: Vec<_> = obstacles
+        .iter()
+        .map(|obstacle| {
+            let diameter = 2.0 * obstacle.radius_at_foot_height;
+            json!({
+                "pose": pose(obstacle.position.x(), obstacle.position.y(), OBSTACLE_HEIGHT / 2.0),
+                "size": { "x": diameter, "y": diameter, "z": OBSTACLE_HEIGHT },
+                "bottom_scale": 1.0,
+                "top_scale": obstacle.radius_at_hip_height / obstacle.radius_at_foot_height,
+                "color": { "r": 1.0, "g": 0.0, "b": 0.0, "a": 0.5 },
+            })
+        })
+        .collect();
+    json!({
+        "deletions": [],
+        "entities": [
+            entity(timestamp, "ball", balls, Vec::new()),
+            entity(timestamp, "obstacles", Vec::new(), obstacles),
+        ],
+    })
+}
+
+fn pose(x: f32, y: f32, z: f32) -> Value {
+    json!({
+        "position": { "x": x, "y": y, "z": z },
+        "orientation": { "x": 0.0, "y": 0.0, "z": 0.0, "w": 1.0 },
+    })
+}
+
+fn entity(timestamp: SystemTime, id: &str, spheres: Vec<Value>, cylinders: Vec<Value>) -> Value {
+    json!({
+        "timestamp": time(timestamp),
+        "frame_id": "ground",
+        "id": id,
+        "lifetime": { "sec": 0, "nsec": 0 },
+        "frame_locked": false,
+        "metadata": [],
+        "arrows": [],
+        "cubes": [],
+        "spheres": spheres,
+        "cylinders": cylinders,
+        "lines": [],
+        "triangles": [],
+        "texts": [],
+        "models": [],
+    })
+}
+
+fn scene_update_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "deletions": { "type": "array" },
+            "entities": { "type": "array" },
+        },
+    })
+}
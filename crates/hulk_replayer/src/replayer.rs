@@ -1,4 +1,9 @@
-use std::{env::args, fs::File, path::PathBuf, sync::Arc};
+use std::{
+    env::args,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use color_eyre::{
     eyre::{Report, WrapErr},
@@ -87,3 +92,27 @@ pub fn replayer() -> Result<()> {
     .map_err(|error| Report::msg(error.to_string()))
     .wrap_err("failed to run user interface")
 }
+
+/// Creates a replayer without communication and user interface
+pub fn headless_replayer(
+    parameters_directory: &Path,
+    replay_path: &Path,
+) -> Result<Replayer<ReplayerHardwareInterface>> {
+    let hardware_interface = ReplayerHardwareInterface {
+        ids: Ids {
+            body_id: "replayer".to_string(),
+            head_id: "replayer".to_string(),
+        },
+    };
+    let ids = hardware_interface.get_ids();
+
+    Replayer::new(
+        Arc::new(hardware_interface),
+        parameters_directory.to_path_buf(),
+        ids,
+        replay_path,
+        None::<String>,
+        CancellationToken::new(),
+    )
+    .wrap_err("failed to create replayer")
+}
@@ -0,0 +1,126 @@
+use std::{collections::BTreeMap, fs::File, path::PathBuf, time::UNIX_EPOCH};
+
+use clap::Parser;
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
+use framework::{Divergence, Tolerances};
+use serde_json::from_reader;
+
+use crate::replayer::headless_replayer;
+
+struct NodeDivergences {
+    number_of_cycles: usize,
+    first_timestamp: f64,
+    first_divergence: Divergence,
+}
+
+#[derive(Parser)]
+pub struct VerifyArguments {
+    /// Directory containing the recording and its parameters
+    replay_path: PathBuf,
+    /// JSON file with the tolerances of numbers in main outputs
+    tolerances_path: Option<PathBuf>,
+}
+
+/// Replays every frame of a recording and compares the recomputed main outputs with the recorded ones
+pub fn verify(arguments: VerifyArguments) -> Result<()> {
+    let replay_path = arguments.replay_path;
+    let tolerances = match arguments.tolerances_path {
+        Some(tolerances_path) => {
+            let file = File::open(tolerances_path).wrap_err("failed to open tolerances")?;
+            from_reader(file).wrap_err("failed to parse tolerances")?
+        }
+        None => Tolerances::default(),
+    };
+
+    let mut replayer = headless_replayer(&replay_path, &replay_path)?;
+
+    let indices: BTreeMap<_, (bool, Vec<_>)> = replayer
+        .get_recording_indices()
+        .into_iter()
+        .map(|(name, index)| {
+            let has_main_outputs = index.header().is_some_and(|header| header.has_main_outputs);
+            (name, (has_main_outputs, index.iter().collect()))
+        })
+        .collect();
+    if indices
+        .values()
+        .all(|(has_main_outputs, _)| !has_main_outputs)
+    {
+        bail!("no cycler instance recorded its main outputs, set `verify_main_outputs` to record them");
+    }
+
+    let mut number_of_diverged_nodes = 0;
+    for (instance_name, (has_main_outputs, timings)) in indices {
+        if !has_main_outputs {
+            println!("{instance_name}: skipped, main outputs were not recorded");
+            continue;
+        }
+        let mut node_divergences: BTreeMap<String, NodeDivergences> = BTreeMap::new();
+        for timing in &timings {
+            let frame = replayer
+                .get_recording_indices_mut()
+                .get_mut(&instance_name)
+                .expect("recording index should exist")
+                .find_latest_frame_up_to(timing.timestamp)
+                .wrap_err("failed to find frame")?;
+            let Some(frame) = frame else {
+                continue;
+            };
+            replayer
+                .replay(&instance_name, frame.timing.timestamp, &frame.data)
+                .wrap_err("failed to replay frame")?;
+
+            let divergences = replayer
+                .find_main_output_divergences(&instance_name, &tolerances)
+                .wrap_err("failed to compare main outputs")?;
+            // each node is only counted once per cycle, even if several of its main outputs diverged
+            let mut diverged_nodes: BTreeMap<String, Divergence> = BTreeMap::new();
+            for divergence in divergences {
+                diverged_nodes
+                    .entry(divergence.node.clone())
+                    .or_insert(divergence);
+            }
+            for (node, divergence) in diverged_nodes {
+                node_divergences
+                    .entry(node)
+                    .or_insert_with(|| NodeDivergences {
+                        number_of_cycles: 0,
+                        first_timestamp: frame
+                            .timing
+                            .timestamp
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs_f64(),
+                        first_divergence: divergence,
+                    })
+                    .number_of_cycles += 1;
+            }
+        }
+
+        println!(
+            "{instance_name}: {} cycles, {} diverged nodes",
+            timings.len(),
+            node_divergences.len()
+        );
+        for (node, divergences) in &node_divergences {
+            let divergence = &divergences.first_divergence;
+            println!(
+                "  {node}: diverged in {} cycles, first at {:.3}s in `{}`: recorded {}, recomputed {}",
+                divergences.number_of_cycles,
+                divergences.first_timestamp,
+                divergence.path,
+                divergence.recorded,
+                divergence.recomputed,
+            );
+        }
+        number_of_diverged_nodes += node_divergences.len();
+    }
+
+    if number_of_diverged_nodes > 0 {
+        bail!("{number_of_diverged_nodes} node(s) diverged from the recording");
+    }
+    Ok(())
+}
@@ -57,7 +57,7 @@ fn main() -> Result<()> {
     let framework_parameters: FrameworkParameters =
         from_reader(file).wrap_err("failed to parse framework parameters")?;
 
-    let file = File::open(framework_parameters.hardware_parameters)
+    let file = File::open(&framework_parameters.hardware_parameters)
         .wrap_err("failed to open hardware parameters")?;
     let hardware_parameters: HardwareParameters =
         from_reader(file).wrap_err("failed to parse hardware parameters")?;
@@ -69,11 +69,9 @@ fn main() -> Result<()> {
 
     run(
         Arc::new(hardware_interface),
-        framework_parameters.communication_addresses,
-        framework_parameters.parameters_directory,
+        framework_parameters,
         "logs",
         ids,
         keep_running,
-        framework_parameters.recording_intervals,
     )
 }
@@ -3,7 +3,7 @@ use std::{
     path::Path,
 };
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use toposort_scc::IndexGraph;
 
 use crate::{
@@ -77,7 +77,7 @@ impl Cyclers {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum CyclerKind {
     Perception,
     RealTime,
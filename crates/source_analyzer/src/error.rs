@@ -9,6 +9,13 @@ use threadbound::ThreadBound;
 pub enum Error {
     #[error("failed to perform IO on `{path}`")]
     Io { source: io::Error, path: PathBuf },
+    #[error("failed to parse manifest `{path}`")]
+    ManifestParse {
+        source: toml::de::Error,
+        path: PathBuf,
+    },
+    #[error("manifest has no target `{target}`")]
+    UnknownTarget { target: String },
     #[error("failed to parse Rust at {path}:{source}")]
     RustParse { source: ParseError, path: PathBuf },
     #[error("failed to read node `{node}` at {path}:{source}")]
@@ -0,0 +1,388 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use convert_case::{Case, Casing};
+use quote::ToTokens;
+use serde::Serialize;
+
+use crate::{
+    contexts::Field,
+    cyclers::{CyclerKind, CyclerName, Cyclers, InstanceName},
+    node::{Node, NodeName},
+    path::Path,
+};
+
+/// The nodes of all cyclers and the main outputs flowing between them
+#[derive(Debug, Serialize)]
+pub struct DataflowGraph {
+    pub cyclers: Vec<CyclerGraph>,
+    pub edges: Vec<Edge>,
+    /// Main outputs which are not consumed by any node
+    pub unused_main_outputs: Vec<MainOutputReference>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CyclerGraph {
+    pub name: CyclerName,
+    pub kind: CyclerKind,
+    pub instances: Vec<InstanceName>,
+    pub nodes: Vec<NodeGraph>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NodeGraph {
+    pub name: NodeName,
+    pub module: String,
+    pub is_setup_node: bool,
+    pub main_outputs: Vec<String>,
+    pub parameters: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Edge {
+    pub from: NodeReference,
+    pub to: NodeReference,
+    /// Path of the consumed data, starting with the name of the main output
+    pub path: String,
+    pub kind: EdgeKind,
+}
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NodeReference {
+    pub cycler: CyclerName,
+    pub node: NodeName,
+}
+
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct MainOutputReference {
+    pub cycler: CyclerName,
+    pub node: NodeName,
+    pub main_output: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub enum EdgeKind {
+    Input,
+    RequiredInput,
+    HistoricInput,
+    PerceptionInput,
+    /// Input from the main outputs of another cycler instance
+    CrossCyclerInput,
+}
+
+impl DataflowGraph {
+    pub fn from_cyclers(cyclers: &Cyclers) -> Self {
+        let instance_to_cycler: BTreeMap<&str, &str> = cyclers
+            .instances()
+            .map(|(cycler, instance)| (instance.as_str(), cycler.name.as_str()))
+            .collect();
+        let producers: BTreeMap<(&str, String), &Node> = cyclers
+            .cyclers
+            .iter()
+            .flat_map(|cycler| {
+                cycler.iter_nodes().flat_map(move |node| {
+                    node.contexts
+                        .main_outputs
+                        .iter()
+                        .filter_map(move |field| match field {
+                            Field::MainOutput { name, .. } => {
+                                Some(((cycler.name.as_str(), name.to_string()), node))
+                            }
+                            _ => None,
+                        })
+                })
+            })
+            .collect();
+
+        let mut edges = Vec::new();
+        for cycler in &cyclers.cyclers {
+            for node in cycler.iter_nodes() {
+                for field in &node.contexts.cycle_context {
+                    let (cycler_instance, path, kind) = match field {
+                        Field::Input {
+                            cycler_instance,
+                            path,
+                            ..
+                        } => (cycler_instance.as_deref(), path, EdgeKind::Input),
+                        Field::RequiredInput {
+                            cycler_instance,
+                            path,
+                            ..
+                        } => (cycler_instance.as_deref(), path, EdgeKind::RequiredInput),
+                        Field::HistoricInput { path, .. } => (None, path, EdgeKind::HistoricInput),
+                        Field::PerceptionInput {
+                            cycler_instance,
+                            path,
+                            ..
+                        } => (
+                            Some(cycler_instance.as_str()),
+                            path,
+                            EdgeKind::PerceptionInput,
+                        ),
+                        _ => continue,
+                    };
+                    let (source_cycler, kind) = match cycler_instance {
+                        Some(instance) => {
+                            let Some(source_cycler) = instance_to_cycler.get(instance) else {
+                                continue;
+                            };
+                            let kind = match kind {
+                                EdgeKind::PerceptionInput => kind,
+                                _ => EdgeKind::CrossCyclerInput,
+                            };
+                            (*source_cycler, kind)
+                        }
+                        None => (cycler.name.as_str(), kind),
+                    };
+                    let Some(main_output) = path.segments.first() else {
+                        continue;
+                    };
+                    let Some(producer) = producers.get(&(source_cycler, main_output.name.clone()))
+                    else {
+                        continue;
+                    };
+                    edges.push(Edge {
+                        from: NodeReference {
+                            cycler: source_cycler.to_string(),
+                            node: producer.name.clone(),
+                        },
+                        to: NodeReference {
+                            cycler: cycler.name.clone(),
+                            node: node.name.clone(),
+                        },
+                        path: path_to_string(path),
+                        kind,
+                    });
+                }
+            }
+        }
+
+        let consumed_main_outputs: BTreeSet<(&str, &str)> = edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.from.cycler.as_str(),
+                    edge.path.split('.').next().unwrap_or_default(),
+                )
+            })
+            .collect();
+        let unused_main_outputs = producers
+            .iter()
+            .filter(|((cycler, main_output), _node)| {
+                !consumed_main_outputs.contains(&(*cycler, main_output.as_str()))
+            })
+            .map(|((cycler, main_output), node)| MainOutputReference {
+                cycler: cycler.to_string(),
+                node: node.name.clone(),
+                main_output: main_output.clone(),
+            })
+            .collect();
+
+        let cyclers = cyclers
+            .cyclers
+            .iter()
+            .map(|cycler| CyclerGraph {
+                name: cycler.name.clone(),
+                kind: cycler.kind,
+                instances: cycler.instances.clone(),
+                nodes: cycler
+                    .setup_nodes
+                    .iter()
+                    .map(|node| NodeGraph::from_node(node, true))
+                    .chain(
+                        cycler
+                            .cycle_nodes
+                            .iter()
+                            .map(|node| NodeGraph::from_node(node, false)),
+                    )
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            cyclers,
+            edges,
+            unused_main_outputs,
+        }
+    }
+
+    /// Returns the given parameter paths which are not consumed by any node
+    pub fn unused_parameters<'a>(
+        &self,
+        parameter_paths: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<&'a str> {
+        let consumed_parameters: Vec<String> = self
+            .cyclers
+            .iter()
+            .flat_map(|cycler| {
+                cycler.nodes.iter().flat_map(|node| {
+                    node.parameters
+                        .iter()
+                        .flat_map(|parameter| expand_cycler_instance(parameter, &cycler.instances))
+                })
+            })
+            .collect();
+        parameter_paths
+            .into_iter()
+            .filter(|path| {
+                !consumed_parameters.iter().any(|consumed| {
+                    *path == consumed
+                        || path.starts_with(&format!("{consumed}."))
+                        || consumed.starts_with(&format!("{path}."))
+                })
+            })
+            .collect()
+    }
+
+    /// Renders the graph in the Graphviz DOT language with a cluster per cycler
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph dataflow {{").unwrap();
+        writeln!(dot, "  rankdir=LR;").unwrap();
+        writeln!(dot, "  node [shape=box];").unwrap();
+        for cycler in &self.cyclers {
+            writeln!(dot, "  subgraph \"cluster_{}\" {{", cycler.name).unwrap();
+            writeln!(
+                dot,
+                "    label=\"{} ({:?}: {})\";",
+                cycler.name,
+                cycler.kind,
+                cycler.instances.join(", ")
+            )
+            .unwrap();
+            for node in &cycler.nodes {
+                let style = if node.is_setup_node {
+                    ", style=bold"
+                } else {
+                    ""
+                };
+                writeln!(
+                    dot,
+                    "    \"{}::{}\" [label=\"{}\"{style}];",
+                    cycler.name, node.name, node.name
+                )
+                .unwrap();
+            }
+            writeln!(dot, "  }}").unwrap();
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Input | EdgeKind::RequiredInput => "solid",
+                EdgeKind::HistoricInput => "dotted",
+                EdgeKind::PerceptionInput | EdgeKind::CrossCyclerInput => "dashed",
+            };
+            writeln!(
+                dot,
+                "  \"{}::{}\" -> \"{}::{}\" [label=\"{}\", style={style}];",
+                edge.from.cycler, edge.from.node, edge.to.cycler, edge.to.node, edge.path
+            )
+            .unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+impl NodeGraph {
+    fn from_node(node: &Node, is_setup_node: bool) -> Self {
+        Self {
+            name: node.name.clone(),
+            module: node.module.to_token_stream().to_string().replace(' ', ""),
+            is_setup_node,
+            main_outputs: node
+                .contexts
+                .main_outputs
+                .iter()
+                .filter_map(|field| match field {
+                    Field::MainOutput { name, .. } => Some(name.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            parameters: node
+                .contexts
+                .creation_context
+                .iter()
+                .chain(&node.contexts.cycle_context)
+                .filter_map(|field| match field {
+                    Field::Parameter { path, .. } => Some(path_to_string(path)),
+                    _ => None,
+                })
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.segments
+        .iter()
+        .map(|segment| {
+            if segment.is_variable {
+                format!("${}", segment.name)
+            } else {
+                segment.name.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Replaces `$cycler_instance` with the snake case names of the instances
+fn expand_cycler_instance(path: &str, instances: &[InstanceName]) -> Vec<String> {
+    if !path.contains("$cycler_instance") {
+        return vec![path.to_string()];
+    }
+    instances
+        .iter()
+        .map(|instance| path.replace("$cycler_instance", &instance.to_case(Case::Snake)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_of_nodes_are_used() {
+        let graph = DataflowGraph {
+            cyclers: vec![CyclerGraph {
+                name: "Vision".to_string(),
+                kind: CyclerKind::Perception,
+                instances: vec!["VisionTop".to_string(), "VisionBottom".to_string()],
+                nodes: vec![NodeGraph {
+                    name: "BallDetection".to_string(),
+                    module: "vision::ball_detection".to_string(),
+                    is_setup_node: false,
+                    main_outputs: vec!["balls".to_string()],
+                    parameters: vec![
+                        "ball_detection.$cycler_instance".to_string(),
+                        "field_dimensions".to_string(),
+                    ],
+                }],
+            }],
+            edges: Vec::new(),
+            unused_main_outputs: Vec::new(),
+        };
+
+        let unused_parameters = graph.unused_parameters([
+            "ball_detection.vision_top.minimum_radius",
+            "ball_detection.vision_bottom",
+            "ball_detection.vision_left",
+            "field_dimensions.length",
+            "field",
+            "line_detection.maximum_gap",
+        ]);
+
+        assert_eq!(
+            unused_parameters,
+            [
+                "ball_detection.vision_left",
+                "field",
+                "line_detection.maximum_gap"
+            ]
+        );
+    }
+}
@@ -1,17 +1,124 @@
+use std::{collections::HashMap, fs::read_to_string, path::Path};
+
 use serde::Deserialize;
 
-use crate::cyclers::CyclerKind;
+use crate::{cyclers::CyclerKind, error::Error};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Deserialize)]
 pub struct FrameworkManifest {
     pub cyclers: Vec<CyclerManifest>,
+    /// Adjustments of the cyclers for individual binaries, selected by name
+    #[serde(default)]
+    pub targets: HashMap<String, TargetManifest>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CyclerManifest {
-    pub name: &'static str,
+    pub name: String,
     pub kind: CyclerKind,
-    pub instances: Vec<&'static str>,
-    pub setup_nodes: Vec<&'static str>,
-    pub nodes: Vec<&'static str>,
+    pub instances: Vec<String>,
+    pub setup_nodes: Vec<String>,
+    pub nodes: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TargetManifest {
+    /// Keeps only the cyclers with these names if given
+    pub cyclers: Option<Vec<String>>,
+    pub disabled_cyclers: Vec<String>,
+    /// Nodes removed from all cyclers, e.g. `vision::ball_detection`
+    pub disabled_nodes: Vec<String>,
+}
+
+impl FrameworkManifest {
+    pub fn try_from_toml_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = read_to_string(path).map_err(|source| Error::Io {
+            source,
+            path: path.to_path_buf(),
+        })?;
+        toml::from_str(&content).map_err(|source| Error::ManifestParse {
+            source,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Removes the cyclers and nodes disabled for the given target
+    pub fn apply_target(&mut self, target: &str) -> Result<(), Error> {
+        let target = self
+            .targets
+            .remove(target)
+            .ok_or_else(|| Error::UnknownTarget {
+                target: target.to_string(),
+            })?;
+        if let Some(selected_cyclers) = &target.cyclers {
+            self.cyclers
+                .retain(|cycler| selected_cyclers.contains(&cycler.name));
+        }
+        self.cyclers
+            .retain(|cycler| !target.disabled_cyclers.contains(&cycler.name));
+        for cycler in &mut self.cyclers {
+            cycler
+                .setup_nodes
+                .retain(|node| !target.disabled_nodes.contains(node));
+            cycler
+                .nodes
+                .retain(|node| !target.disabled_nodes.contains(node));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        [[cyclers]]
+        name = "Vision"
+        kind = "Perception"
+        instances = ["Top", "Bottom"]
+        setup_nodes = ["vision::image_receiver"]
+        nodes = ["vision::ball_detection", "vision::line_detection"]
+
+        [[cyclers]]
+        name = "ObjectDetection"
+        kind = "Perception"
+        instances = ["Top"]
+        setup_nodes = ["vision::image_receiver"]
+        nodes = ["object_detection::pose_detection"]
+
+        [targets.lightweight]
+        disabled_cyclers = ["ObjectDetection"]
+        disabled_nodes = ["vision::line_detection"]
+
+        [targets.vision]
+        cyclers = ["Vision"]
+    "#;
+
+    #[test]
+    fn targets_remove_cyclers_and_nodes() {
+        let mut manifest: FrameworkManifest = toml::from_str(MANIFEST).unwrap();
+        assert_eq!(manifest.cyclers.len(), 2);
+
+        manifest.apply_target("lightweight").unwrap();
+
+        assert_eq!(manifest.cyclers.len(), 1);
+        assert_eq!(manifest.cyclers[0].name, "Vision");
+        assert_eq!(manifest.cyclers[0].kind, CyclerKind::Perception);
+        assert_eq!(manifest.cyclers[0].instances, ["Top", "Bottom"]);
+        assert_eq!(manifest.cyclers[0].nodes, ["vision::ball_detection"]);
+    }
+
+    #[test]
+    fn targets_select_cyclers() {
+        let mut manifest: FrameworkManifest = toml::from_str(MANIFEST).unwrap();
+
+        manifest.apply_target("vision").unwrap();
+
+        assert_eq!(manifest.cyclers.len(), 1);
+        assert_eq!(manifest.cyclers[0].name, "Vision");
+        assert!(manifest.apply_target("unknown").is_err());
+    }
 }
@@ -5,7 +5,7 @@ use std::{
 };
 
 use quote::ToTokens;
-use syn::{parse_file, ImplItem, Item, ItemImpl, Type};
+use syn::{parse_file, Fields, ImplItem, Item, ItemImpl, Type};
 
 use crate::{
     contexts::Contexts,
@@ -20,6 +20,8 @@ pub struct Node {
     pub module: syn::Path,
     pub file_path: PathBuf,
     pub contexts: Contexts,
+    /// Fields of the node struct, which is recorded as the state of the node
+    pub state_fields: Fields,
 }
 
 pub fn parse_rust_file(file_path: impl AsRef<Path>) -> Result<syn::File, Error> {
@@ -58,11 +60,22 @@ impl Node {
             .ok_or_else(|| wrap_error(ParseError::new_spanned(&rust_file, "cannot find node declaration, expected a type with new(...) and cycle(...) method")))?
             .to_string();
         let contexts = Contexts::try_from_file(&rust_file).map_err(wrap_error)?;
+        let state_fields = rust_file
+            .items
+            .iter()
+            .find_map(|item| match item {
+                Item::Struct(structure) if structure.ident == name => {
+                    Some(structure.fields.clone())
+                }
+                _ => None,
+            })
+            .unwrap_or(Fields::Unit);
         Ok(Self {
             name,
             module,
             file_path,
             contexts,
+            state_fields,
         })
     }
 }
@@ -0,0 +1,58 @@
+local inspect = require 'inspect'
+print("Hello world from lua!")
+
+function spawn_robot(number)
+    table.insert(state.robots, create_robot(number))
+end
+
+function spawn_opponent(position, policy)
+    table.insert(state.opponents, create_opponent(position, policy))
+end
+
+spawn_robot(1)
+spawn_robot(2)
+spawn_robot(3)
+spawn_robot(4)
+spawn_robot(5)
+spawn_robot(6)
+spawn_robot(7)
+
+spawn_opponent({ 4.0, 0.0 }, { WalkTo = { target = { 4.3, 0.0 } } })
+spawn_opponent({ 1.0, 1.0 }, "ChaseBall")
+spawn_opponent({ 1.0, -1.0 }, "Block")
+spawn_opponent({ 0.5, 2.0 }, { Mark = { player_number = "Seven" } })
+
+local game_end_time = 15000
+
+function on_goal()
+    print("Goal scored, resetting ball!")
+    print("Ball: " .. inspect(state.ball))
+    print("Ball was at x: " .. state.ball.position[1] .. " y: " .. state.ball.position[2])
+    state.ball = nil
+end
+
+function on_cycle()
+    if state.ball == nil and state.cycle_count % 1000 == 0 then
+        state.ball = {
+            position = { 0.0, 0.0 },
+            velocity = { 0.0, 0.0 },
+        }
+    end
+
+    if state.cycle_count == 100 then
+        state.game_controller_state.game_state = "Ready"
+        state.game_controller_state.kicking_team = "Hulks"
+    end
+
+    if state.cycle_count == 1600 then
+        state.game_controller_state.game_state = "Set"
+    end
+
+    if state.cycle_count == 1700 then
+        state.game_controller_state.game_state = "Playing"
+    end
+
+    if state.cycle_count == game_end_time then
+        state.finished = true
+    end
+end
@@ -0,0 +1,55 @@
+function spawn_robot(number)
+    table.insert(state.robots, create_robot(number))
+end
+
+spawn_robot(1)
+spawn_robot(2)
+spawn_robot(3)
+spawn_robot(4)
+spawn_robot(5)
+spawn_robot(6)
+spawn_robot(7)
+
+local keeper = 1
+
+function is_keeper_in_penalty_area()
+    return get_robot_pose_x(keeper) < -2.85 and math.abs(get_robot_pose_y(keeper)) < 2.0
+end
+
+function is_ball_seen_by_striker()
+    for number = 1, 7 do
+        local main_outputs = get_main_outputs(number)
+        if main_outputs.role == "Striker" and main_outputs.ball_position ~= nil then
+            return true
+        end
+    end
+    return false
+end
+
+function on_cycle()
+    if state.cycle_count == 100 then
+        state.game_controller_state.game_state = "Ready"
+        state.game_controller_state.kicking_team = "Hulks"
+    end
+
+    if state.cycle_count == 1600 then
+        state.game_controller_state.game_state = "Set"
+    end
+
+    if state.cycle_count == 1700 then
+        state.game_controller_state.game_state = "Playing"
+        state.ball = {
+            position = { 2.0, 0.0 },
+            velocity = { 0.0, 0.0 },
+        }
+        assert_eventually(function()
+            return get_main_outputs(keeper).role == "Keeper"
+        end, 1.0, "robot 1 is the keeper")
+        assert_eventually(is_ball_seen_by_striker, 10.0, "the striker sees the ball")
+        assert_always(is_keeper_in_penalty_area, 20.0, "the keeper stays in its penalty area")
+    end
+
+    if state.cycle_count == 3500 then
+        state.finished = true
+    end
+end
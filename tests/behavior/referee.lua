@@ -0,0 +1,52 @@
+local inspect = require 'inspect'
+print("Hello world from lua!")
+
+function spawn_robot(number)
+    table.insert(state.robots, create_robot(number))
+end
+
+spawn_robot(1)
+spawn_robot(2)
+spawn_robot(3)
+spawn_robot(4)
+spawn_robot(5)
+spawn_robot(6)
+spawn_robot(7)
+
+table.insert(state.opponents, create_opponent({ 1.0, 0.5 }, "ChaseBall"))
+
+state.referee.enabled = true
+state.referee.ready_duration = 15.0
+state.referee.half_duration = 60.0
+state.referee.half_time_break_duration = 5.0
+state.game_controller_state.kicking_team = "Hulks"
+state.ball = {
+    position = { 0.0, 0.0 },
+    velocity = { 0.0, 0.0 },
+}
+
+local game_end_time = 30000
+local last_game_state = state.game_controller_state.game_state
+
+function on_ball_out()
+    print("Ball out, sub state: " .. inspect(state.game_controller_state.sub_state))
+end
+
+function on_goal()
+    print("Goal scored, kicking team: " .. state.game_controller_state.kicking_team)
+end
+
+function on_cycle()
+    local game_state = state.game_controller_state.game_state
+    if game_state ~= last_game_state then
+        print("Game state changed to " .. game_state .. " at cycle " .. state.cycle_count)
+        if last_game_state == "Finished" and game_state == "Initial" then
+            state.finished = true
+        end
+        last_game_state = game_state
+    end
+
+    if state.cycle_count == game_end_time then
+        error("Referee did not start the second half!")
+    end
+end
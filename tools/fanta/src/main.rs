@@ -1,6 +1,8 @@
+use std::{num::NonZeroUsize, time::Duration};
+
 use clap::Parser;
 use color_eyre::Result;
-use communication::client::Client;
+use communication::{client::Client, messages::SubscriptionOptions};
 use tokio::spawn;
 
 pub fn setup_logger() -> Result<(), fern::InitError> {
@@ -24,7 +26,28 @@ pub fn setup_logger() -> Result<(), fern::InitError> {
 struct CommandlineArguments {
     #[clap(short, long, default_value = "localhost")]
     address: String,
+    /// Token to authenticate at the communication server
+    #[clap(long)]
+    token: Option<String>,
     path: String,
+    /// Maximum number of updates per second
+    #[clap(long = "max-rate", value_parser = parse_max_rate)]
+    minimum_interval: Option<Duration>,
+    /// Only receive every n-th update
+    #[clap(long)]
+    decimation: Option<NonZeroUsize>,
+    /// Only receive updates if the value changed
+    #[clap(long)]
+    only_on_change: bool,
+    /// Receive updates as patches against the previous value
+    #[clap(long)]
+    delta: bool,
+}
+
+fn parse_max_rate(max_rate: &str) -> Result<Duration, String> {
+    let max_rate: f32 = max_rate.parse().map_err(|error| format!("{error}"))?;
+    Duration::try_from_secs_f32(1.0 / max_rate)
+        .map_err(|_| format!("expected a positive rate, got {max_rate}"))
 }
 
 #[tokio::main]
@@ -36,9 +59,18 @@ async fn main() -> Result<()> {
     let address = format!("ws://{}:1337", arguments.address);
     let (client, handle) = Client::new(address);
     let task = spawn(client.run());
+    handle.set_token(arguments.token).await;
     handle.connect().await;
 
-    let mut subscription = handle.subscribe_text(arguments.path).await;
+    let options = SubscriptionOptions {
+        minimum_interval: arguments.minimum_interval,
+        decimation: arguments.decimation,
+        only_on_change: arguments.only_on_change,
+        delta: arguments.delta,
+    };
+    let mut subscription = handle
+        .subscribe_text_with_options(arguments.path, options)
+        .await;
 
     while let Ok(message) = subscription.receiver.recv().await {
         println!("{message:#?}");
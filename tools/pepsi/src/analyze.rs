@@ -1,11 +1,15 @@
-use std::path::PathBuf;
+use std::{fs::read_to_string, path::PathBuf};
 
 use bat::{PagingMode, PrettyPrinter};
 use clap::Subcommand;
 use color_eyre::{eyre::WrapErr, Result};
+use serde_json::Value;
 
 use repository::Repository;
-use source_analyzer::{contexts::Contexts, node::parse_rust_file, pretty::to_string_pretty};
+use source_analyzer::{
+    contexts::Contexts, cyclers::Cyclers, graph::DataflowGraph, manifest::FrameworkManifest,
+    node::parse_rust_file, pretty::to_string_pretty,
+};
 
 #[derive(Subcommand)]
 #[allow(clippy::enum_variant_names)]
@@ -18,6 +22,15 @@ pub enum Arguments {
         /// File name to dump (may contain wildcard characters usable by glob())
         file_name: String,
     },
+    /// Export the dataflow graph between the nodes of all cyclers as Graphviz DOT
+    Graph {
+        /// Export JSON instead of DOT, including unused main outputs and parameters
+        #[arg(long, short = 'j')]
+        json: bool,
+        /// Write the graph into this file instead of stdout
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
 }
 
 pub async fn analyze(arguments: Arguments, repository: &Repository) -> Result<()> {
@@ -46,7 +59,68 @@ pub async fn analyze(arguments: Arguments, repository: &Repository) -> Result<()
                 .print()
                 .wrap_err("failed to print file")?;
         }
+        Arguments::Graph { json, output } => {
+            let graph = export_graph(repository, json)?;
+            match output {
+                Some(path) => std::fs::write(&path, graph)
+                    .wrap_err_with(|| format!("failed to write {}", path.display()))?,
+                None => print!("{graph}"),
+            }
+        }
     }
 
     Ok(())
 }
+
+fn export_graph(repository: &Repository, json: bool) -> Result<String> {
+    let crates_directory = repository.crates_directory();
+    let manifest =
+        FrameworkManifest::try_from_toml_file(crates_directory.join("hulk_manifest/hulk.toml"))
+            .wrap_err("failed to read manifest")?;
+    let cyclers = Cyclers::try_from_manifest(manifest, &crates_directory)
+        .wrap_err("failed to analyze cyclers")?;
+    let graph = DataflowGraph::from_cyclers(&cyclers);
+
+    let parameters_path = repository.parameters_root().join("default.json");
+    let parameters: Value = serde_json::from_str(
+        &read_to_string(&parameters_path)
+            .wrap_err_with(|| format!("failed to read {}", parameters_path.display()))?,
+    )
+    .wrap_err("failed to parse default parameters")?;
+    let mut parameter_paths = Vec::new();
+    collect_leaf_paths(&parameters, String::new(), &mut parameter_paths);
+    let unused_parameters = graph.unused_parameters(parameter_paths.iter().map(String::as_str));
+
+    if json {
+        let mut value = serde_json::to_value(&graph).wrap_err("failed to serialize graph")?;
+        value["unused_parameters"] = unused_parameters.into();
+        return serde_json::to_string_pretty(&value).wrap_err("failed to serialize graph");
+    }
+
+    for unused in &graph.unused_main_outputs {
+        eprintln!(
+            "unused main output: {}.{} of {}",
+            unused.cycler, unused.main_output, unused.node
+        );
+    }
+    for path in unused_parameters {
+        eprintln!("unused parameter: {path}");
+    }
+    Ok(graph.to_dot())
+}
+
+fn collect_leaf_paths(value: &Value, prefix: String, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                let path = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{prefix}.{name}")
+                };
+                collect_leaf_paths(field, path, paths);
+            }
+        }
+        _ => paths.push(prefix),
+    }
+}
@@ -27,6 +27,9 @@ fn config_path() -> PathBuf {
 #[derive(Debug, Deserialize)]
 pub struct Configuration {
     pub keys: keys::Keybinds,
+    /// Token to authenticate at the communication server of the robots
+    #[serde(default)]
+    pub communication_token: Option<String>,
 }
 
 impl Configuration {
@@ -56,9 +59,15 @@ impl Configuration {
     }
 
     pub fn merge(&mut self, other: Self) {
-        let Self { keys } = other;
+        let Self {
+            keys,
+            communication_token,
+        } = other;
 
         self.keys.merge(keys);
+        if communication_token.is_some() {
+            self.communication_token = communication_token;
+        }
     }
 }
 
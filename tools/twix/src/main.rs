@@ -35,8 +35,8 @@ use nao::Nao;
 use panel::Panel;
 use panels::{
     BallCandidatePanel, BehaviorSimulatorPanel, EnumPlotPanel, ImageColorSelectPanel, ImagePanel,
-    ImageSegmentsPanel, LookAtPanel, ManualCalibrationPanel, MapPanel, ParameterPanel, PlotPanel,
-    RemotePanel, TextPanel, VisionTunerPanel,
+    ImageSegmentsPanel, LookAtPanel, ManualCalibrationPanel, MapPanel, OverridesPanel,
+    ParameterPanel, PlotPanel, RemotePanel, TextPanel, VisionTunerPanel,
 };
 
 use repository::{get_repository_root, Repository};
@@ -56,6 +56,7 @@ mod panel;
 mod panels;
 mod players_buffer_handle;
 mod selectable_panel_macro;
+mod subscription_options;
 mod twix_painter;
 mod value_buffer;
 mod visuals;
@@ -165,6 +166,7 @@ impl_selectable_panel!(
     LookAtPanel,
     ManualCalibrationPanel,
     MapPanel,
+    OverridesPanel,
     ParameterPanel,
     PlotPanel,
     EnumPlotPanel,
@@ -200,7 +202,10 @@ impl TwixApp {
             .or_else(|| creation_context.storage?.get_string("address"))
             .unwrap_or_else(|| "localhost".to_string());
 
-        let nao = Arc::new(Nao::new(format!("ws://{address}:1337")));
+        let nao = Arc::new(Nao::new(
+            format!("ws://{address}:1337"),
+            configuration.communication_token.clone(),
+        ));
 
         let connection_intent = creation_context
             .storage
@@ -10,7 +10,7 @@ use color_eyre::{
 };
 use communication::{
     client::{Client, ClientHandle, PathsEvent, Status},
-    messages::{Path, TextOrBinary},
+    messages::{Path, SubscriptionOptions, TextOrBinary},
 };
 use log::{error, warn};
 use parameters::{directory::Scope, json::nest_value_at_path};
@@ -34,11 +34,12 @@ pub struct Nao {
 }
 
 impl Nao {
-    pub fn new(address: String) -> Self {
+    pub fn new(address: String, token: Option<String>) -> Self {
         let runtime = Builder::new_multi_thread().enable_all().build().unwrap();
 
         let (client, handle) = Client::new(address);
         runtime.spawn(client.run());
+        runtime.block_on(handle.set_token(token));
 
         let repository = match runtime.block_on(get_repository_root()) {
             Ok(root) => Some(Repository::new(root)),
@@ -98,17 +99,34 @@ impl Nao {
         self.subscribe_buffered_json(path, Duration::ZERO)
     }
 
+    pub fn subscribe_json_with_options(
+        &self,
+        path: impl Into<Path>,
+        options: SubscriptionOptions,
+    ) -> BufferHandle<Value> {
+        self.subscribe_buffered_json_with_options(path, Duration::ZERO, options)
+    }
+
     pub fn subscribe_buffered_json(
         &self,
         path: impl Into<Path>,
         history: Duration,
+    ) -> BufferHandle<Value> {
+        self.subscribe_buffered_json_with_options(path, history, SubscriptionOptions::default())
+    }
+
+    fn subscribe_buffered_json_with_options(
+        &self,
+        path: impl Into<Path>,
+        history: Duration,
+        options: SubscriptionOptions,
     ) -> BufferHandle<Value> {
         let path = path.into();
         let _guard = self.runtime.enter();
         let (task, buffer) = Buffer::new(history);
         let client = self.client.clone();
         spawn(async move {
-            let subscription = client.subscribe_text(path).await;
+            let subscription = client.subscribe_text_with_options(path, options).await;
             task.map(subscription, |datum| -> Result<_, Report> {
                 let datum = datum.map_err(|error| eyre!("{error:#}"))?;
                 Ok(Datum {
@@ -147,11 +165,34 @@ impl Nao {
         self.subscribe_buffered_value(path, Duration::ZERO)
     }
 
+    pub fn subscribe_value_with_options<T>(
+        &self,
+        path: impl Into<Path>,
+        options: SubscriptionOptions,
+    ) -> BufferHandle<T>
+    where
+        for<'de> T: serde::Deserialize<'de> + Send + Sync + 'static,
+    {
+        self.subscribe_buffered_value_with_options(path, Duration::ZERO, options)
+    }
+
     pub fn subscribe_buffered_value<T>(
         &self,
         path: impl Into<Path>,
         history: Duration,
     ) -> BufferHandle<T>
+    where
+        for<'de> T: serde::Deserialize<'de> + Send + Sync + 'static,
+    {
+        self.subscribe_buffered_value_with_options(path, history, SubscriptionOptions::default())
+    }
+
+    fn subscribe_buffered_value_with_options<T>(
+        &self,
+        path: impl Into<Path>,
+        history: Duration,
+        options: SubscriptionOptions,
+    ) -> BufferHandle<T>
     where
         for<'de> T: serde::Deserialize<'de> + Send + Sync + 'static,
     {
@@ -160,7 +201,7 @@ impl Nao {
         let (task, buffer) = Buffer::new(history);
         let client = self.client.clone();
         spawn(async move {
-            let subscription = client.subscribe_binary(path).await;
+            let subscription = client.subscribe_binary_with_options(path, options).await;
             task.map(subscription, |datum| -> Result<_, Report> {
                 let datum = datum.map_err(|error| eyre!("protocol: {error:#}"))?;
                 Ok(Datum {
@@ -183,6 +224,15 @@ impl Nao {
         });
     }
 
+    pub fn write_many(&self, values: Vec<(Path, TextOrBinary)>) {
+        let client = self.client.clone();
+        self.runtime.spawn(async move {
+            if let Err(error) = client.write_many(values).await {
+                error!("{error:#}")
+            }
+        });
+    }
+
     pub fn on_change(&self, callback: impl Fn() + Send + Sync + 'static) {
         let _guard = self.runtime.enter();
         self.client.on_change(callback)
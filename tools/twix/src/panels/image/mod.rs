@@ -2,18 +2,20 @@ use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use color_eyre::{eyre::eyre, Result};
+use communication::messages::SubscriptionOptions;
 use coordinate_systems::Pixel;
 use eframe::egui::{ColorImage, Response, SizeHint, TextureOptions, Ui, Widget};
 use geometry::rectangle::Rectangle;
 use image::RgbImage;
 use linear_algebra::{point, vector};
-use serde_json::{json, Value};
+use serde_json::{from_value, json, Value};
 
 use types::{jpeg::JpegImage, ycbcr422_image::YCbCr422Image};
 
 use crate::{
     nao::Nao,
     panel::Panel,
+    subscription_options::SubscriptionOptionsEdit,
     twix_painter::{Orientation, TwixPainter},
     value_buffer::BufferHandle,
     zoom_and_pan::ZoomAndPanTransform,
@@ -36,6 +38,7 @@ enum RawOrJpeg {
 pub struct ImagePanel {
     nao: Arc<Nao>,
     image_buffer: RawOrJpeg,
+    subscription_options: SubscriptionOptions,
     cycler: VisionCycler,
     overlays: Overlays,
     zoom_and_pan: ZoomAndPanTransform,
@@ -57,13 +60,17 @@ impl Panel for ImagePanel {
             .and_then(|value| value.get("is_jpeg"))
             .and_then(|value| value.as_bool())
             .unwrap_or(false);
+        let subscription_options = value
+            .and_then(|value| value.get("subscription_options"))
+            .and_then(|value| from_value(value.clone()).ok())
+            .unwrap_or_default();
 
         let image_buffer = if is_jpeg {
             let path = format!("{cycler_path}.main_outputs.image.jpeg");
-            RawOrJpeg::Jpeg(nao.subscribe_value(path))
+            RawOrJpeg::Jpeg(nao.subscribe_value_with_options(path, subscription_options))
         } else {
             let path = format!("{cycler_path}.main_outputs.image");
-            RawOrJpeg::Raw(nao.subscribe_value(path))
+            RawOrJpeg::Raw(nao.subscribe_value_with_options(path, subscription_options))
         };
 
         let overlays = Overlays::new(
@@ -74,6 +81,7 @@ impl Panel for ImagePanel {
         Self {
             nao,
             image_buffer,
+            subscription_options,
             cycler,
             overlays,
             zoom_and_pan: ZoomAndPanTransform::default(),
@@ -87,6 +95,7 @@ impl Panel for ImagePanel {
             "is_jpeg": matches!(self.image_buffer, RawOrJpeg::Jpeg(_)),
             "cycler": self.cycler.as_path(),
             "overlays": overlays,
+            "subscription_options": self.subscription_options,
         })
     }
 }
@@ -104,6 +113,12 @@ impl Widget for &mut ImagePanel {
             if ui.checkbox(&mut jpeg, "JPEG").changed() {
                 self.resubscribe(jpeg);
             }
+            if ui
+                .add(SubscriptionOptionsEdit::new(&mut self.subscription_options))
+                .changed()
+            {
+                self.resubscribe(jpeg);
+            }
             let maybe_timestamp = match &self.image_buffer {
                 RawOrJpeg::Raw(buffer) => buffer.get_last_timestamp(),
                 RawOrJpeg::Jpeg(buffer) => buffer.get_last_timestamp(),
@@ -135,15 +150,15 @@ impl ImagePanel {
     fn resubscribe(&mut self, jpeg: bool) {
         let cycler_path = self.cycler.as_path();
         self.image_buffer = if jpeg {
-            RawOrJpeg::Jpeg(
-                self.nao
-                    .subscribe_value(format!("{cycler_path}.main_outputs.image.jpeg")),
-            )
+            RawOrJpeg::Jpeg(self.nao.subscribe_value_with_options(
+                format!("{cycler_path}.main_outputs.image.jpeg"),
+                self.subscription_options,
+            ))
         } else {
-            RawOrJpeg::Raw(
-                self.nao
-                    .subscribe_value(format!("{cycler_path}.main_outputs.image")),
-            )
+            RawOrJpeg::Raw(self.nao.subscribe_value_with_options(
+                format!("{cycler_path}.main_outputs.image"),
+                self.subscription_options,
+            ))
         };
     }
 
@@ -2,9 +2,10 @@ use std::sync::Arc;
 
 use color_eyre::{eyre::Context, Result};
 use eframe::epaint::{Color32, Stroke};
+use serde::Deserialize;
 
 use coordinate_systems::{Field, Ground};
-use linear_algebra::{IntoFramed, Isometry2, Point2};
+use linear_algebra::{IntoFramed, Isometry2, Point2, Pose2};
 use types::{
     ball_position::SimulatorBallState, field_dimensions::FieldDimensions,
     motion_command::MotionCommand,
@@ -18,11 +19,18 @@ use crate::{
 const TRANSPARENT_BLUE: Color32 = Color32::from_rgba_premultiplied(0, 0, 202, 150);
 const TRANSPARENT_LIGHT_BLUE: Color32 = Color32::from_rgba_premultiplied(136, 170, 182, 150);
 
+#[derive(Clone, Deserialize)]
+struct Opponent {
+    position: Point2<Field>,
+    orientation: f32,
+}
+
 pub struct BehaviorSimulator {
     ground_to_field: PlayersBufferHandle<Option<Isometry2<Ground, Field>>>,
     motion_command: PlayersBufferHandle<MotionCommand>,
     head_yaw: PlayersBufferHandle<f32>,
     ball: BufferHandle<Option<SimulatorBallState>>,
+    opponents: BufferHandle<Vec<Opponent>>,
 }
 
 impl Layer<Field> for BehaviorSimulator {
@@ -48,11 +56,13 @@ impl Layer<Field> for BehaviorSimulator {
         )
         .unwrap();
         let ball = nao.subscribe_value("BehaviorSimulator.main_outputs.ball");
+        let opponents = nao.subscribe_value("BehaviorSimulator.main_outputs.opponents");
         Self {
             ground_to_field,
             motion_command,
             head_yaw: sensor_data,
             ball,
+            opponents,
         }
     }
 
@@ -119,6 +129,24 @@ impl Layer<Field> for BehaviorSimulator {
             );
         }
 
+        for opponent in self
+            .opponents
+            .get_last_value()
+            .wrap_err("opponents")?
+            .unwrap_or_default()
+        {
+            painter.pose(
+                Pose2::new(opponent.position, opponent.orientation),
+                0.15,
+                0.25,
+                Color32::from_rgb(200, 30, 30),
+                Stroke {
+                    width: 0.02,
+                    color: Color32::BLACK,
+                },
+            );
+        }
+
         if let Some(ball_state) = self.ball.get_last_value().wrap_err("ball state")?.flatten() {
             painter.ball(ball_state.position, 0.05, Color32::WHITE);
         }
@@ -7,6 +7,7 @@ mod image_segments;
 mod look_at;
 mod manual_camera_calibration;
 mod map;
+mod overrides;
 mod parameter;
 mod plot;
 mod remote;
@@ -22,6 +23,7 @@ pub use image_segments::ImageSegmentsPanel;
 pub use look_at::LookAtPanel;
 pub use manual_camera_calibration::ManualCalibrationPanel;
 pub use map::MapPanel;
+pub use overrides::OverridesPanel;
 pub use parameter::ParameterPanel;
 pub use plot::PlotPanel;
 pub use remote::RemotePanel;
@@ -0,0 +1,205 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use communication::messages::TextOrBinary;
+use eframe::egui::{
+    Button, CollapsingHeader, ComboBox, Response, ScrollArea, TextEdit, Ui, Widget,
+};
+use log::error;
+use serde_json::{json, Map, Value};
+
+use crate::{nao::Nao, panel::Panel, value_buffer::BufferHandle};
+
+pub struct OverridesPanel {
+    nao: Arc<Nao>,
+    cycler_instance: String,
+    disabled_nodes: BufferHandle<Value>,
+    injected_main_outputs: BufferHandle<Value>,
+    main_output: String,
+    current_main_output: Option<BufferHandle<Value>>,
+    injected_value: String,
+}
+
+impl Panel for OverridesPanel {
+    const NAME: &'static str = "Overrides";
+
+    fn new(nao: Arc<Nao>, value: Option<&Value>) -> Self {
+        let cycler_instance = value
+            .and_then(|value| value.get("cycler_instance"))
+            .and_then(|cycler_instance| cycler_instance.as_str())
+            .unwrap_or("Control")
+            .to_string();
+        let disabled_nodes =
+            nao.subscribe_json(format!("overrides.{cycler_instance}.disabled_nodes"));
+        let injected_main_outputs =
+            nao.subscribe_json(format!("overrides.{cycler_instance}.injected_main_outputs"));
+
+        Self {
+            nao,
+            cycler_instance,
+            disabled_nodes,
+            injected_main_outputs,
+            main_output: String::new(),
+            current_main_output: None,
+            injected_value: String::new(),
+        }
+    }
+
+    fn save(&self) -> Value {
+        json!({
+            "cycler_instance": self.cycler_instance.clone(),
+        })
+    }
+}
+
+impl OverridesPanel {
+    fn cycler_instances(&self) -> BTreeSet<String> {
+        match &*self.nao.latest_paths() {
+            Some(Ok(paths)) => paths
+                .keys()
+                .filter_map(|path| path.strip_prefix("overrides."))
+                .filter_map(|path| path.split('.').next())
+                .map(ToString::to_string)
+                .collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    fn subscribe(&mut self) {
+        let cycler_instance = &self.cycler_instance;
+        self.disabled_nodes = self
+            .nao
+            .subscribe_json(format!("overrides.{cycler_instance}.disabled_nodes"));
+        self.injected_main_outputs = self
+            .nao
+            .subscribe_json(format!("overrides.{cycler_instance}.injected_main_outputs"));
+        self.main_output.clear();
+        self.current_main_output = None;
+    }
+
+    fn write(&self, path: String, value: Value) {
+        self.nao.write(
+            format!("overrides.{}.{path}", self.cycler_instance),
+            TextOrBinary::Text(value),
+        );
+    }
+
+    fn show_disabled_nodes(&self, ui: &mut Ui, disabled_nodes: &Map<String, Value>) {
+        for (node, is_disabled) in disabled_nodes {
+            let mut is_disabled = is_disabled.as_bool().unwrap_or_default();
+            if ui.checkbox(&mut is_disabled, node).changed() {
+                self.write(format!("disabled_nodes.{node}"), Value::Bool(is_disabled));
+            }
+        }
+    }
+
+    fn show_injected_main_outputs(&mut self, ui: &mut Ui, main_outputs: &Map<String, Value>) {
+        for (name, value) in main_outputs.iter().filter(|(_, value)| !value.is_null()) {
+            ui.horizontal(|ui| {
+                if ui.button("Clear").clicked() {
+                    self.write(format!("injected_main_outputs.{name}"), Value::Null);
+                }
+                ui.label(format!("{name} = {value}"));
+            });
+        }
+
+        ui.horizontal(|ui| {
+            let previous_main_output = self.main_output.clone();
+            ComboBox::from_label("Main output")
+                .selected_text(&self.main_output)
+                .show_ui(ui, |ui| {
+                    for name in main_outputs.keys() {
+                        ui.selectable_value(&mut self.main_output, name.clone(), name);
+                    }
+                });
+            if self.main_output != previous_main_output {
+                self.current_main_output = Some(self.nao.subscribe_json(format!(
+                    "{}.main_outputs.{}",
+                    self.cycler_instance, self.main_output
+                )));
+            }
+
+            let current_value = self
+                .current_main_output
+                .as_ref()
+                .and_then(|buffer| buffer.get_last_value().ok().flatten());
+            if ui
+                .add_enabled(current_value.is_some(), Button::new("Copy current"))
+                .on_hover_text("Start editing from the currently computed value")
+                .clicked()
+            {
+                if let Some(value) = current_value {
+                    self.injected_value = serde_json::to_string_pretty(&value).unwrap_or_default();
+                }
+            }
+            if ui
+                .add_enabled(!self.main_output.is_empty(), Button::new("Inject"))
+                .clicked()
+            {
+                match serde_json::from_str::<Value>(&self.injected_value) {
+                    Ok(value) => {
+                        self.write(format!("injected_main_outputs.{}", self.main_output), value);
+                    }
+                    Err(error) => error!("Failed to parse injected value: {error:#?}"),
+                }
+            }
+        });
+        ScrollArea::vertical().show(ui, |ui| {
+            ui.add(
+                TextEdit::multiline(&mut self.injected_value)
+                    .code_editor()
+                    .desired_width(f32::INFINITY),
+            );
+        });
+    }
+}
+
+impl Widget for &mut OverridesPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.vertical(|ui| {
+            let previous_cycler_instance = self.cycler_instance.clone();
+            let cycler_instances = self.cycler_instances();
+            ComboBox::from_label("Cycler instance")
+                .selected_text(&self.cycler_instance)
+                .show_ui(ui, |ui| {
+                    for cycler_instance in cycler_instances {
+                        ui.selectable_value(
+                            &mut self.cycler_instance,
+                            cycler_instance.clone(),
+                            cycler_instance,
+                        );
+                    }
+                });
+            if self.cycler_instance != previous_cycler_instance {
+                self.subscribe();
+            }
+
+            CollapsingHeader::new("Disabled nodes")
+                .default_open(true)
+                .show(ui, |ui| match self.disabled_nodes.get_last_value() {
+                    Ok(Some(Value::Object(disabled_nodes))) => {
+                        self.show_disabled_nodes(ui, &disabled_nodes)
+                    }
+                    Ok(_) => {
+                        ui.label("no data yet");
+                    }
+                    Err(error) => {
+                        ui.label(format!("{error:#?}"));
+                    }
+                });
+            CollapsingHeader::new("Injected main outputs")
+                .default_open(true)
+                .show(ui, |ui| match self.injected_main_outputs.get_last_value() {
+                    Ok(Some(Value::Object(main_outputs))) => {
+                        self.show_injected_main_outputs(ui, &main_outputs)
+                    }
+                    Ok(_) => {
+                        ui.label("no data yet");
+                    }
+                    Err(error) => {
+                        ui.label(format!("{error:#?}"));
+                    }
+                });
+        })
+        .response
+    }
+}
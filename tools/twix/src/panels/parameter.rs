@@ -19,6 +19,7 @@ pub struct ParameterPanel {
     path: String,
     buffer: Option<BufferHandle<Value>>,
     parameter_value: Result<String>,
+    staged_values: Vec<(String, Value)>,
 }
 
 impl Panel for ParameterPanel {
@@ -36,6 +37,7 @@ impl Panel for ParameterPanel {
             path: path.unwrap_or("").to_string(),
             buffer: value_buffer,
             parameter_value: Err(eyre!("no subscription yet")),
+            staged_values: Vec::new(),
         }
     }
     fn save(&self) -> Value {
@@ -70,33 +72,80 @@ impl Widget for &mut ParameterPanel {
                             Err(error) => error!("Failed to serialize parameter value: {error:#?}"),
                         }
                     }
-                    if ui.button("Save to Head").clicked() {
+                    if ui
+                        .button("Stage")
+                        .on_hover_text("Collect this value to set it together with others")
+                        .clicked()
+                    {
                         let serialized =
                             serde_json::from_str::<Value>(self.parameter_value.as_ref().unwrap());
                         match serialized {
                             Ok(value) => {
-                                self.nao
-                                    .store_parameters(&self.path, value, Scope::current_head())
-                                    .log_err();
+                                self.staged_values.retain(|(path, _)| *path != self.path);
+                                self.staged_values.push((self.path.clone(), value));
                             }
                             Err(error) => error!("Failed to serialize parameter value: {error:#?}"),
                         }
                     }
-                    if ui.button("Save to Body").clicked() {
-                        let serialized =
-                            serde_json::from_str::<Value>(self.parameter_value.as_ref().unwrap());
-                        match serialized {
-                            Ok(value) => {
-                                self.nao
-                                    .store_parameters(&self.path, value, Scope::current_body())
-                                    .log_err();
+                    // overrides and other writable outputs only exist at runtime
+                    let is_parameter = self.path.starts_with("parameters.");
+                    ui.add_enabled_ui(is_parameter, |ui| {
+                        if ui.button("Save to Head").clicked() {
+                            let serialized = serde_json::from_str::<Value>(
+                                self.parameter_value.as_ref().unwrap(),
+                            );
+                            match serialized {
+                                Ok(value) => {
+                                    self.nao
+                                        .store_parameters(&self.path, value, Scope::current_head())
+                                        .log_err();
+                                }
+                                Err(error) => {
+                                    error!("Failed to serialize parameter value: {error:#?}")
+                                }
                             }
-                            Err(error) => error!("Failed to serialize parameter value: {error:#?}"),
                         }
-                    }
+                        if ui.button("Save to Body").clicked() {
+                            let serialized = serde_json::from_str::<Value>(
+                                self.parameter_value.as_ref().unwrap(),
+                            );
+                            match serialized {
+                                Ok(value) => {
+                                    self.nao
+                                        .store_parameters(&self.path, value, Scope::current_body())
+                                        .log_err();
+                                }
+                                Err(error) => {
+                                    error!("Failed to serialize parameter value: {error:#?}")
+                                }
+                            }
+                        }
+                    });
                 });
             });
 
+            if !self.staged_values.is_empty() {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("Commit {} staged", self.staged_values.len()))
+                        .clicked()
+                    {
+                        let values = self
+                            .staged_values
+                            .drain(..)
+                            .map(|(path, value)| (path, TextOrBinary::Text(value)))
+                            .collect();
+                        self.nao.write_many(values);
+                    }
+                    if ui.button("Discard").clicked() {
+                        self.staged_values.clear();
+                    }
+                });
+                for (path, value) in &self.staged_values {
+                    ui.label(format!("{path} = {value}"));
+                }
+            }
+
             if let Some(buffer) = &mut self.buffer {
                 if buffer.has_changed() {
                     buffer.mark_as_seen();
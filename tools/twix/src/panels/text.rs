@@ -1,14 +1,19 @@
 use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
+use communication::messages::SubscriptionOptions;
 use eframe::egui::{Label, Response, ScrollArea, Sense, Ui, Widget};
-use serde_json::{json, Value};
+use serde_json::{from_value, json, Value};
 
-use crate::{completion_edit::CompletionEdit, nao::Nao, panel::Panel, value_buffer::BufferHandle};
+use crate::{
+    completion_edit::CompletionEdit, nao::Nao, panel::Panel,
+    subscription_options::SubscriptionOptionsEdit, value_buffer::BufferHandle,
+};
 
 pub struct TextPanel {
     nao: Arc<Nao>,
     path: String,
+    subscription_options: SubscriptionOptions,
     buffer: Option<BufferHandle<Value>>,
 }
 
@@ -20,17 +25,27 @@ impl Panel for TextPanel {
             Some(Value::String(string)) => string.to_string(),
             _ => String::new(),
         };
+        let subscription_options = value
+            .and_then(|value| value.get("subscription_options"))
+            .and_then(|value| from_value(value.clone()).ok())
+            .unwrap_or_default();
         let buffer = if !path.is_empty() {
-            Some(nao.subscribe_json(path.clone()))
+            Some(nao.subscribe_json_with_options(path.clone(), subscription_options))
         } else {
             None
         };
-        Self { nao, path, buffer }
+        Self {
+            nao,
+            path,
+            subscription_options,
+            buffer,
+        }
     }
 
     fn save(&self) -> Value {
         json!({
-            "path": self.path.clone()
+            "path": self.path.clone(),
+            "subscription_options": self.subscription_options,
         })
     }
 }
@@ -43,8 +58,14 @@ impl Widget for &mut TextPanel {
                     &mut self.path,
                     self.nao.as_ref(),
                 ));
-                if edit_response.changed() {
-                    self.buffer = Some(self.nao.subscribe_json(self.path.clone()));
+                let options_response =
+                    ui.add(SubscriptionOptionsEdit::new(&mut self.subscription_options));
+                if edit_response.changed() || options_response.changed() {
+                    self.buffer =
+                        Some(self.nao.subscribe_json_with_options(
+                            self.path.clone(),
+                            self.subscription_options,
+                        ));
                 }
                 if let Some(buffer) = &self.buffer {
                     if let Ok(Some(timestamp)) = buffer.get_last_timestamp() {
@@ -0,0 +1,68 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use communication::messages::SubscriptionOptions;
+use eframe::egui::{DragValue, Response, Ui, Widget};
+
+pub struct SubscriptionOptionsEdit<'options> {
+    options: &'options mut SubscriptionOptions,
+}
+
+impl<'options> SubscriptionOptionsEdit<'options> {
+    pub fn new(options: &'options mut SubscriptionOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl<'options> Widget for SubscriptionOptionsEdit<'options> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let mut options_changed = false;
+        let mut menu = ui.menu_button("Updates", |ui| {
+            let mut max_rate = self
+                .options
+                .minimum_interval
+                .map_or(0.0, |interval| 1.0 / interval.as_secs_f64());
+            let widget = DragValue::new(&mut max_rate)
+                .range(0.0..=1000.0)
+                .prefix("Max rate [Hz]:")
+                .custom_formatter(|rate, _| {
+                    if rate > 0.0 {
+                        format!("{rate:.1}")
+                    } else {
+                        "unlimited".to_string()
+                    }
+                });
+            if ui.add(widget).changed() {
+                self.options.minimum_interval =
+                    (max_rate > 0.0).then(|| Duration::from_secs_f64(1.0 / max_rate));
+                options_changed = true;
+            }
+
+            let mut decimation = self.options.decimation.map_or(1, NonZeroUsize::get);
+            let widget = DragValue::new(&mut decimation)
+                .range(1..=1000)
+                .prefix("Every n-th update:");
+            if ui.add(widget).changed() {
+                self.options.decimation = NonZeroUsize::new(decimation).filter(|n| n.get() > 1);
+                options_changed = true;
+            }
+
+            if ui
+                .checkbox(&mut self.options.only_on_change, "Only on change")
+                .changed()
+            {
+                options_changed = true;
+            }
+            if ui
+                .checkbox(&mut self.options.delta, "Delta encoding")
+                .on_hover_text("Only applies to JSON subscriptions")
+                .changed()
+            {
+                options_changed = true;
+            }
+        });
+        if options_changed {
+            menu.response.mark_changed();
+        }
+        menu.response
+    }
+}